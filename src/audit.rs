@@ -0,0 +1,141 @@
+//! A structured record of the commands run through Loki, written as JSON lines.
+
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+};
+
+use log::{error, info, warn};
+use serde::Serialize;
+use serenity::model::id::{GuildId, UserId};
+use tokio::sync::mpsc::{self, error::TrySendError, Receiver, Sender};
+
+/// The file the audit log is written to if `LOKI_AUDIT_LOG` is set, but empty.
+const DEFAULT_PATH: &str = "audit.jsonl";
+/// The number of entries which may be waiting to be written before further entries are dropped.
+const CAPACITY: usize = 256;
+
+/// A single command invocation.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The full name of the command, including any subcommands (e.g. `config export`).
+    pub command: String,
+    pub user: UserId,
+    /// The guild the command was run in, or [None] if it was run in DMs.
+    pub guild: Option<GuildId>,
+    /// When the command was run, as a Unix timestamp.
+    pub timestamp: i64,
+    pub success: bool,
+}
+
+/// Handle for appending [AuditEntry]s to the audit log.
+///
+/// Entries are passed to a dedicated writer thread, so recording an entry never waits on the
+/// disk; if the writer falls too far behind, new entries are dropped (with a warning) instead.
+pub struct AuditLog {
+    sender: Sender<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Open the audit log at the path given by the `LOKI_AUDIT_LOG` environment variable (or
+    /// `audit.jsonl`, if it is set but empty), and start its writer.
+    ///
+    /// Returns [None] if `LOKI_AUDIT_LOG` is unset, or if the file can't be opened.
+    pub fn from_env() -> Option<Self> {
+        let path = env::var("LOKI_AUDIT_LOG").ok()?;
+        let path = if path.is_empty() { DEFAULT_PATH } else { &path };
+        let file = match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Unable to open audit log at '{path}'; commands won't be audited: {e}");
+                return None;
+            }
+        };
+        info!("Writing audit log to '{path}'.");
+        let (sender, receiver) = mpsc::channel(CAPACITY);
+        let path = path.to_string();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = write_entries(file, receiver) {
+                error!("Stopped writing audit log to '{path}': {e}");
+            }
+        });
+        Some(Self { sender })
+    }
+
+    /// Queue `entry` to be appended to the audit log.
+    pub fn record(&self, entry: AuditEntry) {
+        match self.sender.try_send(entry) {
+            Ok(()) => (),
+            Err(TrySendError::Full(entry)) => {
+                warn!("Audit log is backed up; dropping entry: {entry:?}")
+            }
+            Err(TrySendError::Closed(entry)) => {
+                warn!("Audit log is closed; dropping entry: {entry:?}")
+            }
+        }
+    }
+}
+
+/// Append each entry from `receiver` to `file` as a line of JSON, until every sender is dropped.
+fn write_entries(mut file: File, mut receiver: Receiver<AuditEntry>) -> io::Result<()> {
+    while let Some(entry) = receiver.blocking_recv() {
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use serenity::model::id::{GuildId, UserId};
+    use tokio::sync::mpsc;
+
+    use super::{write_entries, AuditEntry, AuditLog};
+
+    fn entry(success: bool) -> AuditEntry {
+        AuditEntry {
+            command: "config export".to_string(),
+            user: UserId::new(1),
+            guild: Some(GuildId::new(2)),
+            timestamp: 1_700_000_000,
+            success,
+        }
+    }
+
+    #[test]
+    fn entries_are_written_as_json_lines() {
+        let path = std::env::temp_dir().join(format!("loki-{}-audit.jsonl", std::process::id()));
+        let (sender, receiver) = mpsc::channel(2);
+        sender.try_send(entry(true)).unwrap();
+        sender
+            .try_send(AuditEntry {
+                guild: None,
+                ..entry(false)
+            })
+            .unwrap();
+        drop(sender);
+        write_entries(fs::File::create(&path).unwrap(), receiver).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            r#"{"command":"config export","user":"1","guild":"2","timestamp":1700000000,"success":true}
+{"command":"config export","user":"1","guild":null,"timestamp":1700000000,"success":false}
+"#
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn full_channel_drops_entries() {
+        let (sender, mut receiver) = mpsc::channel(1);
+        let log = AuditLog { sender };
+        log.record(entry(true));
+        log.record(entry(false));
+        assert_eq!(receiver.try_recv(), Ok(entry(true)));
+        assert!(receiver.try_recv().is_err());
+        // Nor should recording panic once the writer has gone.
+        drop(receiver);
+        log.record(entry(true));
+    }
+}
@@ -0,0 +1,257 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use serenity::{
+    async_trait,
+    model::{
+        application::Command as RegisteredCommand,
+        channel::{GuildChannel, Message, ReactionType},
+        guild::Member,
+        id::{ChannelId, CommandId, GuildId, MessageId, UserId},
+        user::User,
+    },
+};
+
+use super::Backend;
+use crate::command::Command;
+
+/// A [Backend] which records the messages sent through it, and serves messages injected into
+/// it, for use in tests.
+///
+/// Operations which aren't supported return an error naming the operation, so that tests which
+/// use them unexpectedly fail with a clear message.
+#[derive(Default)]
+pub struct MockBackend {
+    sent: Mutex<Vec<(ChannelId, String)>>,
+    messages: Mutex<Vec<Message>>,
+    /// The reactions injected on behalf of specific users, as (message, emoji, user).
+    reactors: Mutex<HashSet<(MessageId, String, UserId)>>,
+    unarchived: Mutex<Vec<ChannelId>>,
+}
+
+/// The error returned by operations which [MockBackend] doesn't support.
+fn unsupported<T>(operation: &'static str) -> crate::Result<T> {
+    Err(crate::Error::SerenityError(serenity::Error::Other(
+        operation,
+    )))
+}
+
+/// Injecting messages and reactions, and checking what was sent, is only needed by the memes
+/// tests.
+#[cfg(feature = "memes")]
+impl MockBackend {
+    /// Inject a message from `author` into `channel`, as if it had been posted there,
+    /// returning the injected message's ID.
+    pub fn inject_message(&self, channel: ChannelId, author: UserId) -> MessageId {
+        let mut messages = self.messages.lock().unwrap();
+        let mut message = Message::default();
+        message.id = MessageId::new(messages.len() as u64 + 1);
+        message.channel_id = channel;
+        message.author.id = author;
+        messages.push(message);
+        messages.last().unwrap().id
+    }
+
+    /// Inject `count` reactions of `emoji` onto the injected `message`.
+    pub fn inject_reactions(&self, message: MessageId, emoji: char, count: u64) {
+        let reaction = serde_json::from_value(serde_json::json!({
+            "count": count,
+            "count_details": { "burst": 0, "normal": count },
+            "me": false,
+            "me_burst": false,
+            "emoji": { "id": null, "name": emoji.to_string() },
+            "burst_colours": [],
+        }))
+        .unwrap();
+        let mut messages = self.messages.lock().unwrap();
+        let message = messages.iter_mut().find(|m| m.id == message).unwrap();
+        message.reactions.push(reaction);
+    }
+
+    /// Inject a reaction of `emoji` by `user` onto the injected `message`.
+    pub fn inject_reaction_from(&self, message: MessageId, emoji: char, user: UserId) {
+        self.reactors
+            .lock()
+            .unwrap()
+            .insert((message, emoji.to_string(), user));
+        let existing = {
+            let mut messages = self.messages.lock().unwrap();
+            let message = messages.iter_mut().find(|m| m.id == message).unwrap();
+            message
+                .reactions
+                .iter_mut()
+                .find(|r| r.reaction_type.unicode_eq(&emoji.to_string()))
+                .map(|r| r.count += 1)
+        };
+        if existing.is_none() {
+            self.inject_reactions(message, emoji, 1);
+        }
+    }
+
+    /// The channel and description of each message sent so far, in order.
+    pub fn sent(&self) -> Vec<(ChannelId, String)> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl MockBackend {
+    /// The threads unarchived so far, in order.
+    pub fn unarchived(&self) -> Vec<ChannelId> {
+        self.unarchived.lock().unwrap().clone()
+    }
+
+    fn record(&self, channel: ChannelId, description: String) -> MessageId {
+        let mut sent = self.sent.lock().unwrap();
+        sent.push((channel, description));
+        MessageId::new(sent.len() as u64)
+    }
+}
+
+#[async_trait]
+impl Backend for MockBackend {
+    async fn send_message(
+        &self,
+        channel: ChannelId,
+        description: String,
+    ) -> crate::Result<MessageId> {
+        Ok(self.record(channel, description))
+    }
+
+    async fn send_image_message(
+        &self,
+        channel: ChannelId,
+        description: String,
+        _image_url: &str,
+    ) -> crate::Result<MessageId> {
+        Ok(self.record(channel, description))
+    }
+
+    async fn edit_message(
+        &self,
+        _channel: ChannelId,
+        _message: MessageId,
+        _description: String,
+    ) -> crate::Result<()> {
+        unsupported("MockBackend doesn't support edit_message")
+    }
+
+    async fn delete_message(&self, _channel: ChannelId, _message: MessageId) -> crate::Result<()> {
+        unsupported("MockBackend doesn't support delete_message")
+    }
+
+    async fn add_reaction(
+        &self,
+        _channel: ChannelId,
+        _message: MessageId,
+        _reaction: ReactionType,
+    ) -> crate::Result<()> {
+        unsupported("MockBackend doesn't support add_reaction")
+    }
+
+    async fn send_direct_message(
+        &self,
+        _user: UserId,
+        _description: String,
+    ) -> crate::Result<MessageId> {
+        unsupported("MockBackend doesn't support send_direct_message")
+    }
+
+    async fn get_user(&self, _user: UserId) -> crate::Result<User> {
+        unsupported("MockBackend doesn't support get_user")
+    }
+
+    async fn get_member(&self, _guild: GuildId, _user: UserId) -> crate::Result<Member> {
+        unsupported("MockBackend doesn't support get_member")
+    }
+
+    async fn edit_member(
+        &self,
+        _guild: GuildId,
+        _user: UserId,
+        _nickname: Option<&str>,
+    ) -> crate::Result<()> {
+        unsupported("MockBackend doesn't support edit_member")
+    }
+
+    async fn create_command(
+        &self,
+        _guild: GuildId,
+        _command: &Command<'_>,
+    ) -> crate::Result<CommandId> {
+        unsupported("MockBackend doesn't support create_command")
+    }
+
+    async fn get_command(
+        &self,
+        _guild: GuildId,
+        _command: CommandId,
+    ) -> crate::Result<RegisteredCommand> {
+        unsupported("MockBackend doesn't support get_command")
+    }
+
+    async fn delete_command(&self, _guild: GuildId, _command: CommandId) -> crate::Result<()> {
+        unsupported("MockBackend doesn't support delete_command")
+    }
+
+    async fn get_messages(
+        &self,
+        channel: ChannelId,
+        after: MessageId,
+        limit: u8,
+    ) -> crate::Result<Vec<Message>> {
+        let mut messages: Vec<Message> = self
+            .messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| m.channel_id == channel && m.id > after)
+            .take(limit.into())
+            .cloned()
+            .collect();
+        messages.reverse();
+        Ok(messages)
+    }
+
+    async fn has_reacted(
+        &self,
+        _channel: ChannelId,
+        message: MessageId,
+        reaction: &ReactionType,
+        user: UserId,
+    ) -> crate::Result<bool> {
+        Ok(self
+            .reactors
+            .lock()
+            .unwrap()
+            .contains(&(message, reaction.to_string(), user)))
+    }
+
+    async fn get_channel(&self, _channel: ChannelId) -> crate::Result<GuildChannel> {
+        unsupported("MockBackend doesn't support get_channel")
+    }
+
+    async fn unarchive_thread(&self, thread: ChannelId) -> crate::Result<()> {
+        self.unarchived.lock().unwrap().push(thread);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serenity::model::id::{ChannelId, GuildId, UserId};
+
+    use super::MockBackend;
+    use crate::backend::Backend;
+
+    #[tokio::test]
+    async fn unsupported_operations_fail_clearly() {
+        let backend = MockBackend::default();
+        let e = backend
+            .edit_member(GuildId::new(1), UserId::new(2), Some("nickname"))
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains("edit_member"), "{e}");
+
+        backend.unarchive_thread(ChannelId::new(3)).await.unwrap();
+        assert_eq!(backend.unarchived(), vec![ChannelId::new(3)]);
+    }
+}
@@ -0,0 +1,129 @@
+//! Abstraction over the chat platform Loki is connected to.
+//!
+//! Subsystems should perform platform operations through a [Backend], rather than calling
+//! into [serenity] directly, so that they can be exercised without a live Discord connection
+//! and so that other platforms may be supported in future.
+
+#[cfg(test)]
+pub mod mock;
+mod serenity;
+
+use ::serenity::{
+    async_trait,
+    model::{
+        application::Command as RegisteredCommand,
+        channel::{GuildChannel, Message, ReactionType},
+        guild::Member,
+        id::{ChannelId, CommandId, GuildId, MessageId, UserId},
+        user::User,
+    },
+};
+
+use crate::command::Command;
+
+pub use self::serenity::SerenityBackend;
+
+/// The platform operations which Loki relies upon.
+///
+/// Each subsystem only needs some of them, so with only some subsystems enabled, others go unused.
+#[allow(dead_code)]
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Send an embed with the given `description` to `channel`, returning the new message's ID.
+    async fn send_message(
+        &self,
+        channel: ChannelId,
+        description: String,
+    ) -> crate::Result<MessageId>;
+
+    /// Send an embed with the given `description` and image (from `image_url`) to `channel`,
+    /// returning the new message's ID.
+    async fn send_image_message(
+        &self,
+        channel: ChannelId,
+        description: String,
+        image_url: &str,
+    ) -> crate::Result<MessageId>;
+
+    /// Replace the description of the embed in our `message` in `channel`.
+    async fn edit_message(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        description: String,
+    ) -> crate::Result<()>;
+
+    /// Delete `message` from `channel`.
+    async fn delete_message(&self, channel: ChannelId, message: MessageId) -> crate::Result<()>;
+
+    /// React to `message` in `channel` with `reaction`.
+    async fn add_reaction(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        reaction: ReactionType,
+    ) -> crate::Result<()>;
+
+    /// Send an embed with the given `description` to `user` directly, returning the new
+    /// message's ID.
+    async fn send_direct_message(
+        &self,
+        user: UserId,
+        description: String,
+    ) -> crate::Result<MessageId>;
+
+    /// Get `user`.
+    async fn get_user(&self, user: UserId) -> crate::Result<User>;
+
+    /// Get `user`'s membership of `guild`.
+    async fn get_member(&self, guild: GuildId, user: UserId) -> crate::Result<Member>;
+
+    /// Set (or with [None], reset) `user`'s nickname in `guild`.
+    async fn edit_member(
+        &self,
+        guild: GuildId,
+        user: UserId,
+        nickname: Option<&str>,
+    ) -> crate::Result<()>;
+
+    /// Register `command` in `guild`, returning the ID of the registered command.
+    async fn create_command(
+        &self,
+        guild: GuildId,
+        command: &Command<'_>,
+    ) -> crate::Result<CommandId>;
+
+    /// Get the registered `command` in `guild`, as Discord currently has it.
+    async fn get_command(
+        &self,
+        guild: GuildId,
+        command: CommandId,
+    ) -> crate::Result<RegisteredCommand>;
+
+    /// Remove the registered `command` from `guild`.
+    async fn delete_command(&self, guild: GuildId, command: CommandId) -> crate::Result<()>;
+
+    /// Get up to `limit` messages in `channel` which were sent after the message `after`,
+    /// **most recent first**.
+    async fn get_messages(
+        &self,
+        channel: ChannelId,
+        after: MessageId,
+        limit: u8,
+    ) -> crate::Result<Vec<Message>>;
+
+    /// Whether `user` has reacted with `reaction` to `message` in `channel`.
+    async fn has_reacted(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        reaction: &ReactionType,
+        user: UserId,
+    ) -> crate::Result<bool>;
+
+    /// Get the guild `channel` (which may be a thread), as it currently is.
+    async fn get_channel(&self, channel: ChannelId) -> crate::Result<GuildChannel>;
+
+    /// Unarchive the `thread`.
+    async fn unarchive_thread(&self, thread: ChannelId) -> crate::Result<()>;
+}
@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{CreateEmbed, CreateMessage, EditMember, EditMessage, EditThread, GetMessages},
+    async_trait,
+    http::Http,
+    model::{
+        application::Command as RegisteredCommand,
+        channel::{GuildChannel, Message, ReactionType},
+        guild::Member,
+        id::{ChannelId, CommandId, GuildId, MessageId, UserId},
+        user::User,
+    },
+    prelude::Context,
+};
+
+use super::Backend;
+use crate::command::{create_embed, create_raw_embed, Command};
+
+/// [Backend] implementation for Discord, via [serenity].
+#[derive(Clone)]
+pub struct SerenityBackend {
+    http: Arc<Http>,
+}
+
+impl SerenityBackend {
+    pub fn new(http: Arc<Http>) -> Self {
+        Self { http }
+    }
+}
+
+impl From<&Context> for SerenityBackend {
+    fn from(ctx: &Context) -> Self {
+        Self::new(ctx.http.clone())
+    }
+}
+
+#[async_trait]
+impl Backend for SerenityBackend {
+    async fn send_message(
+        &self,
+        channel: ChannelId,
+        description: String,
+    ) -> crate::Result<MessageId> {
+        Ok(channel
+            .send_message(&self.http, create_embed(description))
+            .await?
+            .id)
+    }
+
+    async fn send_image_message(
+        &self,
+        channel: ChannelId,
+        description: String,
+        image_url: &str,
+    ) -> crate::Result<MessageId> {
+        Ok(channel
+            .send_message(
+                &self.http,
+                CreateMessage::new().add_embed(
+                    CreateEmbed::new()
+                        .description(description)
+                        .image(image_url)
+                        .colour(crate::COLOUR),
+                ),
+            )
+            .await?
+            .id)
+    }
+
+    async fn edit_message(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        description: String,
+    ) -> crate::Result<()> {
+        channel
+            .edit_message(
+                &self.http,
+                message,
+                EditMessage::new().embeds(vec![create_raw_embed(description)]),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_message(&self, channel: ChannelId, message: MessageId) -> crate::Result<()> {
+        channel.delete_message(&self.http, message).await?;
+        Ok(())
+    }
+
+    async fn add_reaction(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        reaction: ReactionType,
+    ) -> crate::Result<()> {
+        channel
+            .create_reaction(&self.http, message, reaction)
+            .await?;
+        Ok(())
+    }
+
+    async fn send_direct_message(
+        &self,
+        user: UserId,
+        description: String,
+    ) -> crate::Result<MessageId> {
+        Ok(user
+            .create_dm_channel(&self.http)
+            .await?
+            .send_message(&self.http, create_embed(description))
+            .await?
+            .id)
+    }
+
+    async fn get_user(&self, user: UserId) -> crate::Result<User> {
+        Ok(self.http.get_user(user).await?)
+    }
+
+    async fn get_member(&self, guild: GuildId, user: UserId) -> crate::Result<Member> {
+        Ok(self.http.get_member(guild, user).await?)
+    }
+
+    async fn edit_member(
+        &self,
+        guild: GuildId,
+        user: UserId,
+        nickname: Option<&str>,
+    ) -> crate::Result<()> {
+        guild
+            .edit_member(
+                &self.http,
+                user,
+                EditMember::new().nickname(nickname.unwrap_or_default()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn create_command(
+        &self,
+        guild: GuildId,
+        command: &Command<'_>,
+    ) -> crate::Result<CommandId> {
+        Ok(guild
+            .create_command(
+                &self.http,
+                crate::serenity_handler::construct_command(command),
+            )
+            .await?
+            .id)
+    }
+
+    async fn get_command(
+        &self,
+        guild: GuildId,
+        command: CommandId,
+    ) -> crate::Result<RegisteredCommand> {
+        Ok(guild.get_command(&self.http, command).await?)
+    }
+
+    async fn delete_command(&self, guild: GuildId, command: CommandId) -> crate::Result<()> {
+        guild.delete_command(&self.http, command).await?;
+        Ok(())
+    }
+
+    async fn get_messages(
+        &self,
+        channel: ChannelId,
+        after: MessageId,
+        limit: u8,
+    ) -> crate::Result<Vec<Message>> {
+        Ok(channel
+            .messages(&self.http, GetMessages::new().after(after).limit(limit))
+            .await?)
+    }
+
+    async fn has_reacted(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        reaction: &ReactionType,
+        user: UserId,
+    ) -> crate::Result<bool> {
+        // Reactors are listed in order of ID, so asking for the first reactor after the ID just
+        // below `user`'s finds them, if they reacted, in a single request.
+        let after = user
+            .get()
+            .checked_sub(1)
+            .filter(|id| *id > 0)
+            .map(UserId::new);
+        Ok(channel
+            .reaction_users(&self.http, message, reaction.clone(), Some(1), after)
+            .await?
+            .first()
+            .is_some_and(|reactor| reactor.id == user))
+    }
+
+    async fn get_channel(&self, channel: ChannelId) -> crate::Result<GuildChannel> {
+        self.http
+            .get_channel(channel)
+            .await?
+            .guild()
+            .ok_or(crate::Error::InvalidChannel)
+    }
+
+    async fn unarchive_thread(&self, thread: ChannelId) -> crate::Result<()> {
+        thread
+            .edit_thread(&self.http, EditThread::new().archived(false))
+            .await?;
+        Ok(())
+    }
+}
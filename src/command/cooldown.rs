@@ -0,0 +1,64 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serenity::model::id::UserId;
+use tokio::time::Instant;
+
+/// The minimum gap between a user's invocations of a [super::Command], and when each user last
+/// invoked it.
+///
+/// This is only held in memory, so all cooldowns reset when Loki restarts.
+#[derive(Clone)]
+pub struct Cooldown {
+    duration: Duration,
+    last_used: Arc<Mutex<HashMap<UserId, Instant>>>,
+}
+
+impl Cooldown {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            last_used: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Record an invocation by `user`, unless they're still on cooldown, in which case the time
+    /// remaining until they may invoke the command again is returned instead.
+    pub fn try_use(&self, user: UserId) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut last_used = self.last_used.lock().unwrap();
+        // Forget any users whose cooldowns have expired, so the map doesn't grow indefinitely.
+        last_used.retain(|_, t| now.duration_since(*t) < self.duration);
+        if let Some(t) = last_used.get(&user) {
+            return Err(self.duration - now.duration_since(*t));
+        }
+        last_used.insert(user, now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serenity::model::id::UserId;
+
+    use super::Cooldown;
+
+    #[tokio::test(start_paused = true)]
+    async fn cooldown_is_per_user_and_expires() {
+        let cooldown = Cooldown::new(Duration::from_secs(10));
+        let (a, b) = (UserId::new(1), UserId::new(2));
+        assert_eq!(cooldown.try_use(a), Ok(()));
+        tokio::time::advance(Duration::from_secs(4)).await;
+        assert_eq!(cooldown.try_use(a), Err(Duration::from_secs(6)));
+        assert_eq!(cooldown.try_use(b), Ok(()));
+        // A rejected invocation doesn't restart the cooldown.
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert_eq!(cooldown.try_use(a), Ok(()));
+        assert_eq!(cooldown.try_use(b), Err(Duration::from_secs(4)));
+    }
+}
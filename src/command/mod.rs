@@ -1,14 +1,19 @@
+mod cooldown;
+mod paginated_embed;
 mod util;
 
+pub use cooldown::Cooldown;
+pub use paginated_embed::PaginatedEmbed;
 use tinyvec::ArrayVec;
 pub use util::*;
 
-use std::{pin::Pin, sync::Arc};
+use std::{ops::BitOr, pin::Pin, sync::Arc, time::Duration};
 
 use serenity::{
-    all::{CommandDataOption, CreateEmbed},
+    all::{CommandDataOption, CreateAttachment, CreateEmbed},
     model::{
         application::{CommandInteraction, CommandOptionType},
+        id::UserId,
         prelude::ChannelType,
         Permissions,
     },
@@ -37,14 +42,42 @@ type ActionRoutine = Box<
         + Send,
 >;
 
+/// Suggests values for an [Option] from the value typed so far, for Discord to show whilst the
+/// user is typing.
+type AutocompleteRoutine = Box<
+    dyn (for<'b> Fn(
+            &'b Context,
+            &'b CommandInteraction,
+            &'b str,
+        ) -> Pin<Box<dyn std::future::Future<Output = Vec<String>> + Send + 'b>>)
+        + Sync
+        + Send,
+>;
+
 pub struct ActionResponse {
     embed: CreateEmbed,
     ephemeral: bool,
+    attachments: Vec<CreateAttachment>,
 }
 
 impl ActionResponse {
     pub fn new(embed: CreateEmbed, ephemeral: bool) -> Self {
-        Self { embed, ephemeral }
+        Self {
+            embed,
+            ephemeral,
+            attachments: Vec::new(),
+        }
+    }
+
+    /// Attach a file to the response.
+    pub fn add_attachment(mut self, attachment: CreateAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Take the files attached to the response, leaving none behind.
+    pub fn take_attachments(&mut self) -> Vec<CreateAttachment> {
+        std::mem::take(&mut self.attachments)
     }
 
     pub fn embed(self) -> CreateEmbed {
@@ -67,6 +100,18 @@ pub enum PermissionType {
     ServerPerms(Permissions),
 }
 
+/// The ways in which a [Command] can be invoked.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum CommandKind {
+    /// Typed in the chat box, as `/name`.
+    #[default]
+    SlashCommand,
+    /// Chosen from the "Apps" menu when right-clicking a user.
+    UserContextMenu,
+    /// Chosen from the "Apps" menu when right-clicking a message.
+    MessageContextMenu,
+}
+
 /// Meta-information about a command.
 ///
 /// A vector of these objects is used to create the Discord-side
@@ -77,16 +122,22 @@ pub struct Command<'a> {
     name: &'a str,
     description: &'a str,
     permissions: PermissionType,
+    kind: CommandKind,
     options: Vec<Option<'a>>,
     variants: Vec<Command<'a>>,
     action: Arc<std::option::Option<ActionRoutine>>,
+    cooldown: std::option::Option<Cooldown>,
     global: bool,
+    stub: bool,
 }
 
 impl<'a> Command<'a> {
     /// Construct a new global Command with the given name and description,
     /// which performs the given [ActionRoutine] when called.
     ///
+    /// If a `cooldown` is given, each user must wait at least that long between
+    /// invocations of the command.
+    ///
     /// ## Example
     ///
     /// ```
@@ -96,6 +147,7 @@ impl<'a> Command<'a> {
     ///     "name",
     ///     "A description of what the command does.",
     ///     PermissionType::Universal,
+    ///     None, // no cooldown
     ///     Some(
     ///         Box::new(move |ctx, command, params| {
     ///             Box::pin(async {
@@ -110,6 +162,7 @@ impl<'a> Command<'a> {
         name: &'a str,
         description: &'a str,
         permissions: PermissionType,
+        cooldown: std::option::Option<Duration>,
         action: std::option::Option<ActionRoutine>,
     ) -> Self {
         if description.len() > 100 {
@@ -119,22 +172,35 @@ impl<'a> Command<'a> {
             name,
             description,
             permissions,
+            kind: CommandKind::SlashCommand,
             options: Vec::new(),
             variants: Vec::new(),
             action: Arc::new(action),
+            cooldown: cooldown.map(Cooldown::new),
             global: true,
+            stub: false,
         }
     }
 
+    /// Construct a new routing-only stub [Command], which performs the given
+    /// [ActionRoutine] when called.
+    ///
+    /// Stubs exist to route interactions for commands which are registered
+    /// elsewhere (such as guild-specific ephemeral commands), so they have no
+    /// description, and may only have [Option]s which route autocomplete
+    /// interactions; they must never be submitted to Discord themselves.
     pub fn new_stub(name: &'a str, action: std::option::Option<ActionRoutine>) -> Self {
         Self {
             name,
             description: "",
             permissions: PermissionType::Universal,
+            kind: CommandKind::SlashCommand,
             options: Vec::new(),
             variants: Vec::new(),
             action: Arc::new(action),
+            cooldown: None,
             global: false,
+            stub: true,
         }
     }
 
@@ -153,11 +219,36 @@ impl<'a> Command<'a> {
         &self.permissions
     }
 
+    /// Make this a context menu [Command] (or, with [CommandKind::SlashCommand], a slash command
+    /// again).
+    ///
+    /// Context menu commands have no description, [Option]s or variants; their target can be
+    /// retrieved with [target_user] or [target_message].
+    pub fn with_kind(mut self, kind: CommandKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn kind(&self) -> CommandKind {
+        self.kind
+    }
+
     pub fn global(&self) -> bool {
         self.global
     }
 
+    /// Whether this [Command] is a routing-only stub (see [Command::new_stub]).
+    pub fn stub(&self) -> bool {
+        self.stub
+    }
+
     pub fn add_option(mut self, option: Option<'a>) -> Self {
+        assert!(
+            !self.stub || option.autocomplete().is_some(),
+            "Stub commands are routing-only and can only have autocompleted options; define option '{}' on the registered command instead. (Command: {})",
+            option.name(),
+            self.name
+        );
         self.options.push(option);
         self
     }
@@ -176,6 +267,14 @@ impl<'a> Command<'a> {
         &self.variants
     }
 
+    /// Record an invocation of this [Command] by `user`, unless they're still on cooldown, in
+    /// which case the time remaining on their cooldown is returned instead.
+    pub fn try_use(&self, user: UserId) -> Result<(), Duration> {
+        self.cooldown
+            .as_ref()
+            .map_or(Ok(()), |cooldown| cooldown.try_use(user))
+    }
+
     /// Run the [ActionRoutine] for this [Command].
     pub async fn run(
         &self,
@@ -184,23 +283,45 @@ impl<'a> Command<'a> {
         params: &Vec<CommandDataOption>,
     ) -> crate::Result<std::option::Option<ActionResponse>> {
         if let Some(action) = &*self.action {
-            (action)(ctx, command, params).await
+            log::debug!("Running command '{}'", self.name);
+            let result = (action)(ctx, command, params).await;
+            match &result {
+                Ok(_) => log::debug!("Command '{}' completed", self.name),
+                Err(e) => log::debug!("Command '{}' failed: {e:?}", self.name),
+            }
+            result
         } else {
             Err(Error::MissingActionRoutine)
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Option<'a> {
     name: &'a str,
     description: &'a str,
     kind: OptionType,
     required: bool,
+    autocomplete: Arc<std::option::Option<AutocompleteRoutine>>,
+}
+
+impl std::fmt::Debug for Option<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Option")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("kind", &self.kind)
+            .field("required", &self.required)
+            .field("autocomplete", &self.autocomplete.is_some())
+            .finish()
+    }
 }
 
 impl<'a> Option<'a> {
     pub fn new(name: &'a str, description: &'a str, kind: OptionType, required: bool) -> Self {
+        if description.len() > 100 {
+            panic!("Description should be <= 100 characters. (Option: {name})");
+        }
         match kind.clone() {
             OptionType::StringInput(min, max) => {
                 if let Some(min) = min {
@@ -289,9 +410,22 @@ impl<'a> Option<'a> {
             description,
             kind,
             required,
+            autocomplete: Arc::new(None),
         }
     }
 
+    /// Suggest values for this [Option] whilst the user types, using the given
+    /// [AutocompleteRoutine]. Only [OptionType::StringInput]s may be autocompleted.
+    pub fn with_autocomplete(mut self, autocomplete: AutocompleteRoutine) -> Self {
+        assert!(
+            matches!(self.kind, OptionType::StringInput(_, _)),
+            "Only StringInput options can be autocompleted. (Option: {})",
+            self.name
+        );
+        self.autocomplete = Arc::new(Some(autocomplete));
+        self
+    }
+
     pub fn name(&self) -> &'a str {
         self.name
     }
@@ -307,6 +441,10 @@ impl<'a> Option<'a> {
     pub fn required(&self) -> bool {
         self.required
     }
+
+    pub fn autocomplete(&self) -> std::option::Option<&AutocompleteRoutine> {
+        self.autocomplete.as_ref().as_ref()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -324,7 +462,8 @@ pub enum OptionType {
     IntegerSelect(ArrayVec<[i64; NUM_SELECTABLES]>),
     Boolean,
     User,
-    Channel(std::option::Option<Vec<ChannelType>>),
+    /// A channel input, optionally limited to the given [ChannelTypes].
+    Channel(std::option::Option<ChannelTypes>),
     Role,
     Mentionable,
     /// A double input, optionally limited to a specific range.
@@ -336,6 +475,73 @@ pub enum OptionType {
     Attachment,
 }
 
+/// A set of [ChannelType]s, for restricting [OptionType::Channel] inputs.
+///
+/// Each [ChannelType] maps to a single bit (based on its Discord value), so
+/// the set can't contain duplicates and compares equal regardless of the
+/// order in which it was constructed.
+///
+/// ## Example
+///
+/// ```
+/// use loki_discord_bot::ChannelTypes;
+/// use serenity::model::channel::ChannelType;
+///
+/// let types = ChannelTypes::VOICE | ChannelTypes::TEXT | ChannelTypes::TEXT;
+/// assert_eq!(types, ChannelTypes::TEXT | ChannelTypes::VOICE);
+/// assert_eq!(types.to_vec(), vec![ChannelType::Text, ChannelType::Voice]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChannelTypes(u32);
+
+impl ChannelTypes {
+    // Bits correspond to each type's Discord value.
+    pub const TEXT: Self = Self(1 << 0);
+    pub const PRIVATE: Self = Self(1 << 1);
+    pub const VOICE: Self = Self(1 << 2);
+    pub const GROUP_DM: Self = Self(1 << 3);
+    pub const CATEGORY: Self = Self(1 << 4);
+    pub const NEWS: Self = Self(1 << 5);
+    pub const NEWS_THREAD: Self = Self(1 << 10);
+    pub const PUBLIC_THREAD: Self = Self(1 << 11);
+    pub const PRIVATE_THREAD: Self = Self(1 << 12);
+    pub const STAGE: Self = Self(1 << 13);
+    pub const DIRECTORY: Self = Self(1 << 14);
+    pub const FORUM: Self = Self(1 << 15);
+
+    /// Whether the set contains the given [ChannelType].
+    pub fn contains(&self, kind: ChannelType) -> bool {
+        self.0 & Self::from(kind).0 != 0
+    }
+
+    /// Convert the set into the list of [ChannelType]s expected by Discord.
+    pub fn to_vec(&self) -> Vec<ChannelType> {
+        (0..u32::BITS)
+            .filter(|bit| self.0 & (1 << bit) != 0)
+            .map(|bit| ChannelType::from(bit as u8))
+            .collect()
+    }
+}
+
+impl From<ChannelType> for ChannelTypes {
+    fn from(kind: ChannelType) -> Self {
+        let bit = u8::from(kind) as u32;
+        assert!(
+            bit < u32::BITS,
+            "ChannelType {kind:?} cannot be represented in ChannelTypes"
+        );
+        Self(1 << bit)
+    }
+}
+
+impl BitOr for ChannelTypes {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
 impl From<OptionType> for CommandOptionType {
     fn from(ot: OptionType) -> Self {
         match ot {
@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use log::error;
+use serenity::{
+    all::{
+        ButtonStyle, CommandInteraction, ComponentInteractionCollector, CreateActionRow,
+        CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+        CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    prelude::Context,
+};
+
+use super::create_raw_embed;
+
+/// The maximum length of an embed's description.
+const DESCRIPTION_LIMIT: usize = 4096;
+/// How long the buttons keep working after they were last used.
+const NAVIGATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A response made up of several embeds, shown one at a time with "Previous" and "Next" buttons
+/// to move between them.
+pub struct PaginatedEmbed {
+    pages: Vec<CreateEmbed>,
+    current_page: usize,
+}
+
+impl PaginatedEmbed {
+    /// Construct a [PaginatedEmbed] from pre-built `pages`, starting on the first.
+    pub fn new(pages: Vec<CreateEmbed>) -> Self {
+        assert!(
+            !pages.is_empty(),
+            "A PaginatedEmbed needs at least one page."
+        );
+        Self {
+            pages,
+            current_page: 0,
+        }
+    }
+
+    /// List the `entries`, one per line, with up to `page_size` on each page (fewer, if they
+    /// wouldn't otherwise fit in an embed). Each page is numbered in its footer.
+    pub fn paginate(entries: impl Iterator<Item = String>, page_size: usize) -> Self {
+        let mut descriptions = vec![String::new()];
+        let mut count = 0;
+        for entry in entries {
+            let entry: String = entry.chars().take(DESCRIPTION_LIMIT).collect();
+            let description = descriptions.last_mut().unwrap();
+            if count == page_size
+                || description.chars().count() + entry.chars().count() + 1 > DESCRIPTION_LIMIT
+            {
+                descriptions.push(entry);
+                count = 1;
+            } else {
+                if count > 0 {
+                    description.push('\n');
+                }
+                description.push_str(&entry);
+                count += 1;
+            }
+        }
+        let num_pages = descriptions.len();
+        Self::new(
+            descriptions
+                .into_iter()
+                .enumerate()
+                .map(|(i, description)| {
+                    create_raw_embed(description).footer(CreateEmbedFooter::new(format!(
+                        "Page {}/{num_pages}",
+                        i + 1
+                    )))
+                })
+                .collect(),
+        )
+    }
+
+    /// Set the title of every page.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        let title = title.into();
+        self.pages = self
+            .pages
+            .into_iter()
+            .map(|page| page.title(title.clone()))
+            .collect();
+        self
+    }
+
+    /// Start on the `page`th page (numbered from 0), or the last page if there are fewer.
+    pub fn starting_at(mut self, page: usize) -> Self {
+        self.current_page = page.min(self.pages.len() - 1);
+        self
+    }
+
+    pub fn num_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Respond to `command` with the current page, then let the user who ran it move between
+    /// pages until the buttons time out (after [NAVIGATION_TIMEOUT] without being used).
+    ///
+    /// Only the initial response is awaited; the buttons are handled in the background, so that
+    /// the command finishes as soon as it has been answered.
+    pub async fn send(
+        self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        ephemeral: bool,
+    ) -> crate::Result<()> {
+        // Unique to this response, so that other paginated responses' buttons aren't caught.
+        let previous_id = format!("{}-previous", command.id);
+        let next_id = format!("{}-next", command.id);
+        command
+            .create_response(
+                ctx,
+                CreateInteractionResponse::Message(
+                    self.message(&previous_id, &next_id).ephemeral(ephemeral),
+                ),
+            )
+            .await?;
+        if self.pages.len() == 1 {
+            return Ok(());
+        }
+        let ctx = ctx.clone();
+        let command = command.clone();
+        tokio::spawn(async move {
+            if let Err(e) = self.navigate(&ctx, &command, previous_id, next_id).await {
+                error!(
+                    "Error handling page navigation for `/{}`: {e:?}",
+                    command.data.name
+                );
+            }
+        });
+        Ok(())
+    }
+
+    /// Move between pages as the user who ran `command` presses its buttons, until they time
+    /// out, at which point the buttons are removed.
+    async fn navigate(
+        mut self,
+        ctx: &Context,
+        command: &CommandInteraction,
+        previous_id: String,
+        next_id: String,
+    ) -> crate::Result<()> {
+        while let Some(interaction) = ComponentInteractionCollector::new(ctx)
+            .author_id(command.user.id)
+            .custom_ids(vec![previous_id.clone(), next_id.clone()])
+            .timeout(NAVIGATION_TIMEOUT)
+            .await
+        {
+            if interaction.data.custom_id == next_id {
+                self.current_page = (self.current_page + 1).min(self.pages.len() - 1);
+            } else {
+                self.current_page = self.current_page.saturating_sub(1);
+            }
+            interaction
+                .create_response(
+                    ctx,
+                    CreateInteractionResponse::UpdateMessage(self.message(&previous_id, &next_id)),
+                )
+                .await?;
+        }
+        // The buttons no longer do anything, so remove them.
+        command
+            .edit_response(ctx, EditInteractionResponse::new().components(Vec::new()))
+            .await?;
+        Ok(())
+    }
+
+    /// The message showing the current page, with buttons to move to the adjacent pages if
+    /// there are any.
+    fn message(&self, previous_id: &str, next_id: &str) -> CreateInteractionResponseMessage {
+        let message =
+            CreateInteractionResponseMessage::new().embed(self.pages[self.current_page].clone());
+        if self.pages.len() == 1 {
+            return message;
+        }
+        message.components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(previous_id)
+                .label("Previous")
+                .style(ButtonStyle::Secondary)
+                .disabled(self.current_page == 0),
+            CreateButton::new(next_id)
+                .label("Next")
+                .style(ButtonStyle::Secondary)
+                .disabled(self.current_page == self.pages.len() - 1),
+        ])])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PaginatedEmbed, DESCRIPTION_LIMIT};
+    use crate::command::create_raw_embed;
+    use serenity::all::CreateEmbedFooter;
+
+    #[test]
+    fn entries_are_split_into_pages() {
+        let paginated = PaginatedEmbed::paginate((1..=5).map(|i| i.to_string()), 2);
+        assert_eq!(paginated.num_pages(), 3);
+        assert_eq!(
+            paginated.pages[0],
+            create_raw_embed("1\n2").footer(CreateEmbedFooter::new("Page 1/3"))
+        );
+        assert_eq!(
+            paginated.pages[2],
+            create_raw_embed("5").footer(CreateEmbedFooter::new("Page 3/3"))
+        );
+        assert_eq!(paginated.starting_at(7).current_page, 2);
+    }
+
+    #[test]
+    fn pages_fit_in_an_embed() {
+        let entry = "x".repeat(DESCRIPTION_LIMIT / 2);
+        let paginated = PaginatedEmbed::paginate(std::iter::repeat_n(entry, 3), 10);
+        assert_eq!(paginated.num_pages(), 3);
+    }
+
+    #[test]
+    fn no_entries_make_one_empty_page() {
+        assert_eq!(
+            PaginatedEmbed::paginate(std::iter::empty(), 10).num_pages(),
+            1
+        );
+    }
+}
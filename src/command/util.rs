@@ -1,23 +1,50 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use log::error;
+use log::{error, warn};
 use serenity::{
-    all::{CreateInteractionResponseMessage, EditInteractionResponse},
+    all::{
+        ButtonStyle, ComponentInteraction, ComponentInteractionCollector, CreateActionRow,
+        CreateAttachment, CreateButton, CreateInteractionResponse,
+        CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+        EditInteractionResponse, ResolvedTarget,
+    },
+    async_trait,
     builder::{CreateEmbed, CreateMessage},
     http::Http,
-    model::application::CommandInteraction,
-    prelude::HttpError,
+    model::{application::CommandInteraction, channel::Message, user::User, Permissions},
+    prelude::{Context, HttpError},
     Error,
 };
 
-use crate::COLOUR;
+use crate::{ActionResponse, COLOUR};
 
 #[cfg(feature = "events")]
 use crate::{config::Config, subsystems::events::Event};
 #[cfg(feature = "events")]
-use serenity::prelude::{Context, TypeMap};
+use log::trace;
 #[cfg(feature = "events")]
-use tokio::sync::RwLockReadGuard;
+use serenity::model::id::{GuildId, UserId};
+#[cfg(feature = "events")]
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+#[cfg(feature = "events")]
+use tokio::time::Instant;
+
+/// How long [confirm_action] waits for the user to confirm or cancel.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The minimum gap, in seconds, between notifications of each [Event]; any notifications within
+/// the gap are suppressed, so that a recurring error doesn't flood subscribers. Unlisted events
+/// are always delivered.
+#[cfg(feature = "events")]
+pub const NOTIFICATION_COOLDOWN_SECS: [(Event, u64); 2] = [(Event::Startup, 0), (Event::Error, 60)];
+
+/// When each [Event] was last delivered.
+#[cfg(feature = "events")]
+static LAST_NOTIFIED: LazyLock<Mutex<HashMap<Event, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 /// Construct a closure for use in [serenity::model::channel::GuildChannel]::send_message
 /// from the provided input string.
@@ -31,12 +58,95 @@ pub fn create_raw_embed(s: impl Into<String>) -> CreateEmbed {
     CreateEmbed::default().description(s).colour(COLOUR)
 }
 
-/// Create an embed response.
+/// Construct an embed with the provided `title` as its description, followed by
+/// each of the `(name, value, inline)` fields in order.
+pub fn create_embed_fields(
+    title: impl Into<String>,
+    fields: &[(&str, impl AsRef<str>, bool)],
+) -> CreateEmbed {
+    fields
+        .iter()
+        .fold(create_raw_embed(title), |embed, (name, value, inline)| {
+            embed.field(*name, value.as_ref(), *inline)
+        })
+}
+
+/// The `candidates` which contain `current` (ignoring case), sorted, for suggesting as
+/// autocomplete values.
+pub fn autocomplete_matches<'s>(
+    candidates: impl IntoIterator<Item = &'s String>,
+    current: &str,
+) -> Vec<String> {
+    let current = current.to_lowercase();
+    let mut matches: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.to_lowercase().contains(&current))
+        .cloned()
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// The user which a [crate::CommandKind::UserContextMenu] `command` was invoked on.
+pub fn target_user(command: &CommandInteraction) -> Option<&User> {
+    match command.data.target()? {
+        ResolvedTarget::User(user, _) => Some(user),
+        _ => None,
+    }
+}
+
+/// The message which a [crate::CommandKind::MessageContextMenu] `command` was invoked on.
+pub fn target_message(command: &CommandInteraction) -> Option<&Message> {
+    match command.data.target()? {
+        ResolvedTarget::Message(message) => Some(message),
+        _ => None,
+    }
+}
+
+/// Why `command` may not be run, if the member who ran it lacks the `required` permissions (or
+/// it wasn't run in a server).
+///
+/// Discord only enforces the permissions of top-level commands, so any subcommand which needs
+/// more than its parent must check for itself.
+pub fn require_permissions(
+    command: &CommandInteraction,
+    required: Permissions,
+) -> Option<ActionResponse> {
+    let permissions = command
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions);
+    permissions_refusal(command.guild_id.is_some(), permissions, required)
+        .map(|refusal| ActionResponse::new(create_raw_embed(refusal), true))
+}
+
+/// Why a member with `permissions` (in a server, if `in_guild`) may not do something which
+/// needs the `required` permissions, if they may not.
+fn permissions_refusal(
+    in_guild: bool,
+    permissions: Option<Permissions>,
+    required: Permissions,
+) -> Option<String> {
+    if !in_guild {
+        return Some("**This can only be used in a server.**".to_string());
+    }
+    if permissions
+        .is_some_and(|permissions| permissions.administrator() || permissions.contains(required))
+    {
+        return None;
+    }
+    Some(format!(
+        "**Unauthorised:** You need the {required} permission to do that!"
+    ))
+}
+
+/// Create an embed response, with any `attachments` uploaded alongside it.
 pub async fn create_response_from_embed(
     http: &Arc<Http>,
     interaction: &mut CommandInteraction,
     embed: CreateEmbed,
     ephemeral: bool,
+    attachments: Vec<CreateAttachment>,
 ) {
     match interaction
         .create_response(
@@ -44,7 +154,8 @@ pub async fn create_response_from_embed(
             serenity::all::CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
                     .add_embed(embed.clone())
-                    .ephemeral(ephemeral),
+                    .ephemeral(ephemeral)
+                    .add_files(attachments.clone()),
             ),
         )
         .await
@@ -54,7 +165,9 @@ pub async fn create_response_from_embed(
             Error::Http(ref e) => match &e {
                 HttpError::UnsuccessfulRequest(req) => match req.error.code {
                     40060 => {
-                        edit_embed_response(http, interaction, embed).await.unwrap();
+                        edit_embed_response(http, interaction, embed, attachments)
+                            .await
+                            .unwrap();
                     }
                     _ => error!("{}", e),
                 },
@@ -73,55 +186,202 @@ pub async fn create_response(
     ephemeral: bool,
 ) {
     let embed = create_raw_embed(message);
-    create_response_from_embed(http, interaction, embed, ephemeral).await
+    create_response_from_embed(http, interaction, embed, ephemeral, Vec::new()).await
 }
 
-/// Edit the original text-based embed response, replacing it with
-/// the new `embed`.
+/// Edit the original text-based embed response (or a deferred one), replacing it with
+/// the new `embed` and uploading any `attachments` alongside it.
 pub async fn edit_embed_response(
     http: &Arc<Http>,
     interaction: &mut CommandInteraction,
     embed: CreateEmbed,
+    attachments: Vec<CreateAttachment>,
 ) -> Result<serenity::model::prelude::Message, serenity::Error> {
+    let response = EditInteractionResponse::new()
+        .content(" ")
+        .add_embed(embed)
+        .components(Vec::new());
     interaction
         .edit_response(
             &http,
-            EditInteractionResponse::new()
-                .content(" ")
-                .add_embed(embed)
-                .components(Vec::new()),
+            attachments
+                .into_iter()
+                .fold(response, EditInteractionResponse::new_attachment),
+        )
+        .await
+}
+
+/// Ask the user who ran `command` to confirm (or cancel) an irreversible action, described by
+/// `prompt`, returning whether they confirmed it within [CONFIRMATION_TIMEOUT].
+///
+/// The response to `command` is deferred (as `ephemeral` or not) whilst the user decides, and the
+/// prompt is sent as an ephemeral follow-up. If the action is confirmed, the command's response
+/// replaces the deferred one as usual; otherwise, the deferred response is deleted, so the command
+/// should respond with [None].
+pub async fn confirm_action(
+    ctx: &Context,
+    command: &CommandInteraction,
+    prompt: &str,
+    ephemeral: bool,
+) -> bool {
+    if let Err(e) = command
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Defer(
+                CreateInteractionResponseMessage::new().ephemeral(ephemeral),
+            ),
         )
         .await
+    {
+        error!("Unable to defer response to confirm action: {e}");
+        return false;
+    }
+    let mut buttons = InteractionConfirmation {
+        ctx,
+        command,
+        prompt: None,
+        press: None,
+    };
+    let confirmed = confirm_with(&mut buttons, prompt, rand::random()).await;
+    if !confirmed {
+        if let Err(e) = command.delete_response(ctx).await {
+            warn!("Unable to delete deferred response to cancelled action: {e}");
+        }
+    }
+    confirmed
+}
+
+/// The prompt and buttons through which [confirm_action] asks for confirmation.
+#[async_trait]
+trait Confirmation: Send {
+    /// Show `prompt`, with buttons to confirm or cancel with the given custom IDs.
+    async fn prompt(
+        &mut self,
+        prompt: &str,
+        confirm_id: &str,
+        cancel_id: &str,
+    ) -> crate::Result<()>;
+
+    /// Wait up to `timeout` for the user to press one of the buttons, returning its custom ID.
+    async fn next_press(&mut self, ids: Vec<String>, timeout: Duration) -> Option<String>;
+
+    /// Replace the prompt (and its buttons) with the `outcome`.
+    async fn resolve(&mut self, outcome: &str) -> crate::Result<()>;
+}
+
+/// Ask for confirmation of `prompt` through `buttons`, whose custom IDs include `nonce` so that
+/// they can't be confused with those of any other confirmation.
+async fn confirm_with(buttons: &mut impl Confirmation, prompt: &str, nonce: u64) -> bool {
+    let confirm_id = format!("confirm-{nonce}");
+    let cancel_id = format!("cancel-{nonce}");
+    if let Err(e) = buttons.prompt(prompt, &confirm_id, &cancel_id).await {
+        error!("Unable to ask for confirmation: {e}");
+        return false;
+    }
+    let (confirmed, outcome) = match buttons
+        .next_press(vec![confirm_id.clone(), cancel_id], CONFIRMATION_TIMEOUT)
+        .await
+    {
+        Some(id) if id == confirm_id => (true, "**Confirmed.**"),
+        Some(_) => (false, "**Cancelled.**"),
+        None => (false, "**Timed out**; nothing was changed."),
+    };
+    if let Err(e) = buttons.resolve(outcome).await {
+        warn!("Unable to update confirmation prompt: {e}");
+    }
+    confirmed
+}
+
+/// [Confirmation] through an ephemeral follow-up to a command interaction.
+struct InteractionConfirmation<'a> {
+    ctx: &'a Context,
+    command: &'a CommandInteraction,
+    /// The follow-up message showing the prompt, once sent.
+    prompt: Option<Message>,
+    /// The button press, once the user has chosen.
+    press: Option<ComponentInteraction>,
+}
+
+#[async_trait]
+impl Confirmation for InteractionConfirmation<'_> {
+    async fn prompt(
+        &mut self,
+        prompt: &str,
+        confirm_id: &str,
+        cancel_id: &str,
+    ) -> crate::Result<()> {
+        let followup = CreateInteractionResponseFollowup::new()
+            .ephemeral(true)
+            .add_embed(create_raw_embed(prompt))
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(confirm_id)
+                    .label("Confirm")
+                    .style(ButtonStyle::Danger),
+                CreateButton::new(cancel_id)
+                    .label("Cancel")
+                    .style(ButtonStyle::Secondary),
+            ])]);
+        self.prompt = Some(self.command.create_followup(self.ctx, followup).await?);
+        Ok(())
+    }
+
+    async fn next_press(&mut self, ids: Vec<String>, timeout: Duration) -> Option<String> {
+        let press = ComponentInteractionCollector::new(self.ctx)
+            .author_id(self.command.user.id)
+            .custom_ids(ids)
+            .timeout(timeout)
+            .await?;
+        let id = press.data.custom_id.clone();
+        self.press = Some(press);
+        Some(id)
+    }
+
+    async fn resolve(&mut self, outcome: &str) -> crate::Result<()> {
+        let message = CreateInteractionResponseMessage::new()
+            .add_embed(create_raw_embed(outcome))
+            .components(Vec::new());
+        if let Some(press) = &self.press {
+            press
+                .create_response(self.ctx, CreateInteractionResponse::UpdateMessage(message))
+                .await?;
+        } else if let Some(prompt) = &self.prompt {
+            self.command
+                .edit_followup(
+                    self.ctx,
+                    prompt.id,
+                    CreateInteractionResponseFollowup::new()
+                        .add_embed(create_raw_embed(outcome))
+                        .components(Vec::new()),
+                )
+                .await?;
+        }
+        Ok(())
+    }
 }
 
 /// Notify the subscribers to an event that it has fired.
 #[cfg(feature = "events")]
 pub async fn notify_subscribers(ctx: &Context, event: Event, message: &str) {
-    let data = crate::acquire_data_handle!(read ctx);
-    let config = data.get::<Config>().unwrap();
-    if let Some(subscribers) = config.subscribers(event) {
-        for subscriber in subscribers {
-            match subscriber.to_user(&ctx).await {
-                Ok(u) => {
-                    if let Err(e) = u
-                        .direct_message(
-                            &ctx,
-                            create_embed(format!(
-                                "{message}
+    notify(ctx, &[], event, message).await;
+}
 
-_You're receiving this message because you're subscribed to the \
-`{event}` event._"
-                            )),
-                        )
-                        .await
-                    {
-                        error!("Could not DM user {subscriber} ({}): {e:?}", u.name);
-                    }
-                }
-                Err(e) => error!("User {subscriber} could not be resolved: {e:?}"),
-            }
-        }
+/// Notify the subscribers to an event which happened in the `guilds` that it has fired, and post
+/// it to each of those guilds' events channels.
+#[cfg(feature = "events")]
+pub async fn notify_subscribers_in(ctx: &Context, guilds: &[GuildId], event: Event, message: &str) {
+    notify(ctx, guilds, event, message).await;
+}
+
+#[cfg(feature = "events")]
+async fn notify(ctx: &Context, guilds: &[GuildId], event: Event, message: &str) {
+    if !try_notify(&LAST_NOTIFIED, event) {
+        return;
     }
+    let data = crate::acquire_data_handle!(read ctx);
+    let unreachable = dm_subscribers(ctx, &data, event, message).await;
+    post_to_events_channels(ctx, &data, guilds, event, message).await;
+    crate::drop_data_handle!(data);
+    remove_unreachable_subscribers(ctx, event, unreachable).await;
 }
 
 /// Notify the subscribers to an event that it has fired, using an existing
@@ -129,32 +389,341 @@ _You're receiving this message because you're subscribed to the \
 #[cfg(feature = "events")]
 pub async fn notify_subscribers_with_handle(
     ctx: &Context,
-    data: &RwLockReadGuard<'_, TypeMap>,
+    config: &Config,
     event: Event,
     message: &str,
 ) {
-    let config = data.get::<Config>().unwrap();
-    if let Some(subscribers) = config.subscribers(event) {
-        for subscriber in subscribers {
-            match subscriber.to_user(&ctx).await {
-                Ok(u) => {
-                    if let Err(e) = u
-                        .direct_message(
-                            &ctx,
-                            create_embed(format!(
-                                "{message}
+    notify_with_handle(ctx, config, &[], event, message).await;
+}
+
+/// Notify the subscribers to an event which happened in the `guilds` that it has fired, and post
+/// it to each of those guilds' events channels, using an existing read handle for global data.
+#[cfg(feature = "events")]
+pub async fn notify_subscribers_in_with_handle(
+    ctx: &Context,
+    config: &Config,
+    guilds: &[GuildId],
+    event: Event,
+    message: &str,
+) {
+    notify_with_handle(ctx, config, guilds, event, message).await;
+}
+
+#[cfg(feature = "events")]
+async fn notify_with_handle(
+    ctx: &Context,
+    config: &Config,
+    guilds: &[GuildId],
+    event: Event,
+    message: &str,
+) {
+    if !try_notify(&LAST_NOTIFIED, event) {
+        return;
+    }
+    let unreachable = dm_subscribers(ctx, config, event, message).await;
+    post_to_events_channels(ctx, config, guilds, event, message).await;
+    if !unreachable.is_empty() {
+        // We can't acquire a write handle whilst the caller holds a read
+        // handle, so defer the removal until it's been dropped.
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            remove_unreachable_subscribers(&ctx, event, unreachable).await;
+        });
+    }
+}
+
+/// Record a delivery of `event` in `last_notified`, unless it's within its cooldown (from
+/// [NOTIFICATION_COOLDOWN_SECS]) since the last delivery, in which case it should be suppressed.
+#[cfg(feature = "events")]
+fn try_notify(last_notified: &Mutex<HashMap<Event, Instant>>, event: Event) -> bool {
+    let cooldown = NOTIFICATION_COOLDOWN_SECS
+        .iter()
+        .find(|(e, _)| *e == event)
+        .map_or(0, |(_, secs)| *secs);
+    let now = Instant::now();
+    let mut last_notified = last_notified.lock().unwrap();
+    if let Some(last) = last_notified.get(&event) {
+        let since = now.duration_since(*last);
+        if since < Duration::from_secs(cooldown) {
+            trace!(
+                "Suppressing {event} notification; the last was only {}s ago.",
+                since.as_secs()
+            );
+            return false;
+        }
+    }
+    last_notified.insert(event, now);
+    true
+}
+
+/// DM each subscriber to an event, returning the subscribers who could not
+/// be DM'd because Discord refused the request (`403 Forbidden`).
+#[cfg(feature = "events")]
+async fn dm_subscribers(
+    ctx: &Context,
+    config: &Config,
+    event: Event,
+    message: &str,
+) -> Vec<UserId> {
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_event(&event.to_string());
+    let subscribers = match config.subscribers(event) {
+        Some(subscribers) => subscribers,
+        None => return Vec::new(),
+    };
+    // DM everyone concurrently; a failure for one subscriber doesn't affect the others.
+    serenity::futures::future::join_all(subscribers.iter().map(|subscriber| async move {
+        match subscriber.to_user(&ctx).await {
+            Ok(u) => {
+                if let Err(e) = u
+                    .direct_message(
+                        &ctx,
+                        create_embed(format!(
+                            "{message}
 
 _You're receiving this message because you're subscribed to the \
 `{event}` event._"
-                            )),
-                        )
-                        .await
-                    {
-                        error!("Could not DM user {subscriber} ({}): {e:?}", u.name);
+                        )),
+                    )
+                    .await
+                {
+                    if is_forbidden(&e) {
+                        warn!(
+                            "Could not DM user {subscriber} ({}); unsubscribing them from {event}: {e:?}",
+                            u.name
+                        );
+                        return Some(*subscriber);
                     }
+                    error!("Could not DM user {subscriber} ({}): {e:?}", u.name);
                 }
-                Err(e) => error!("User {subscriber} could not be resolved: {e:?}"),
             }
+            Err(e) => error!("User {subscriber} could not be resolved: {e:?}"),
+        }
+        None
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Post an event to the events channels of the `guilds` it happened in, where they've set one.
+#[cfg(feature = "events")]
+async fn post_to_events_channels(
+    ctx: &Context,
+    config: &Config,
+    guilds: &[GuildId],
+    event: Event,
+    message: &str,
+) {
+    let channels = guilds
+        .iter()
+        .filter_map(|guild| config.events_channel(guild, event));
+    serenity::futures::future::join_all(channels.map(|channel| async move {
+        if let Err(e) = channel
+            .send_message(
+                &ctx,
+                CreateMessage::new()
+                    .add_embed(create_raw_embed(message).title(format!("Event: {event}"))),
+            )
+            .await
+        {
+            error!("Could not post {event} event to channel {channel}: {e:?}");
         }
+    }))
+    .await;
+}
+
+/// Unsubscribe users from an event, such as when they've blocked the bot or
+/// disabled DMs from server members.
+#[cfg(feature = "events")]
+async fn remove_unreachable_subscribers(ctx: &Context, event: Event, users: Vec<UserId>) {
+    if users.is_empty() {
+        return;
+    }
+    let mut data = crate::acquire_data_handle!(write ctx);
+    let config = &mut *data;
+    for user in users {
+        config.remove_subscriber(event, user);
+    }
+    config.save().await;
+    crate::drop_data_handle!(data);
+}
+
+/// Whether the error is Discord refusing the request (`403 Forbidden`).
+#[cfg(feature = "events")]
+fn is_forbidden(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::Http(HttpError::UnsuccessfulRequest(resp))
+            if resp.status_code == serenity::http::StatusCode::FORBIDDEN
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serenity::async_trait;
+
+    use serenity::model::Permissions;
+
+    use super::{
+        autocomplete_matches, confirm_with, create_embed_fields, create_raw_embed,
+        permissions_refusal, Confirmation,
+    };
+
+    /// [Confirmation] which records what it is shown and simulates a single button press.
+    #[derive(Default)]
+    struct MockConfirmation {
+        /// Which button to press: `Some(true)` to confirm, `Some(false)` to cancel, or [None] to
+        /// let the confirmation time out.
+        press: Option<bool>,
+        prompt: Option<(String, String, String)>,
+        outcome: Option<String>,
+    }
+
+    #[async_trait]
+    impl Confirmation for MockConfirmation {
+        async fn prompt(
+            &mut self,
+            prompt: &str,
+            confirm_id: &str,
+            cancel_id: &str,
+        ) -> crate::Result<()> {
+            self.prompt = Some((prompt.into(), confirm_id.into(), cancel_id.into()));
+            Ok(())
+        }
+
+        async fn next_press(&mut self, ids: Vec<String>, _timeout: Duration) -> Option<String> {
+            let (_, confirm_id, cancel_id) = self.prompt.clone().unwrap();
+            assert_eq!(ids, vec![confirm_id.clone(), cancel_id.clone()]);
+            self.press
+                .map(|confirm| if confirm { confirm_id } else { cancel_id })
+        }
+
+        async fn resolve(&mut self, outcome: &str) -> crate::Result<()> {
+            self.outcome = Some(outcome.into());
+            Ok(())
+        }
+    }
+
+    async fn confirm(press: Option<bool>) -> (bool, MockConfirmation) {
+        let mut buttons = MockConfirmation {
+            press,
+            ..Default::default()
+        };
+        let confirmed = confirm_with(&mut buttons, "Delete everything?", 42).await;
+        (confirmed, buttons)
+    }
+
+    #[tokio::test]
+    async fn confirmation_confirmed() {
+        let (confirmed, buttons) = confirm(Some(true)).await;
+        assert!(confirmed);
+        assert_eq!(
+            buttons.prompt,
+            Some((
+                "Delete everything?".to_string(),
+                "confirm-42".to_string(),
+                "cancel-42".to_string()
+            ))
+        );
+        assert_eq!(buttons.outcome.as_deref(), Some("**Confirmed.**"));
+    }
+
+    #[cfg(feature = "events")]
+    #[tokio::test(start_paused = true)]
+    async fn notifications_are_suppressed_during_cooldown() {
+        use std::{collections::HashMap, sync::Mutex};
+
+        use super::try_notify;
+        use crate::subsystems::events::Event;
+
+        let last_notified = Mutex::new(HashMap::new());
+        assert!(try_notify(&last_notified, Event::Error));
+        tokio::time::advance(Duration::from_secs(59)).await;
+        assert!(!try_notify(&last_notified, Event::Error));
+        // Other events have their own cooldowns.
+        assert!(try_notify(&last_notified, Event::Timeout));
+        assert!(try_notify(&last_notified, Event::Timeout));
+        assert!(try_notify(&last_notified, Event::Startup));
+        assert!(try_notify(&last_notified, Event::Startup));
+        // A suppressed notification doesn't restart the cooldown.
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(try_notify(&last_notified, Event::Error));
+        assert!(!try_notify(&last_notified, Event::Error));
+    }
+
+    #[tokio::test]
+    async fn confirmation_cancelled_or_timed_out() {
+        let (confirmed, buttons) = confirm(Some(false)).await;
+        assert!(!confirmed);
+        assert_eq!(buttons.outcome.as_deref(), Some("**Cancelled.**"));
+
+        let (confirmed, buttons) = confirm(None).await;
+        assert!(!confirmed);
+        assert!(buttons.outcome.unwrap().starts_with("**Timed out**"));
+    }
+
+    #[test]
+    fn embed_fields() {
+        let embed = create_embed_fields(
+            "**Title**",
+            &[
+                ("User", "a\nb", true),
+                ("Score", "1\n2", true),
+                ("Notes", "", false),
+            ],
+        );
+        let fields = toml::Value::try_from(&embed).unwrap()["fields"]
+            .as_array()
+            .unwrap()
+            .len();
+        assert_eq!(fields, 3);
+        assert_eq!(
+            embed,
+            create_raw_embed("**Title**")
+                .field("User", "a\nb", true)
+                .field("Score", "1\n2", true)
+                .field("Notes", "", false)
+        );
+    }
+
+    #[test]
+    fn autocomplete_matches_ignore_case() {
+        let candidates = [
+            "Wins".to_string(),
+            "losses".to_string(),
+            "Draws".to_string(),
+        ];
+        assert_eq!(
+            autocomplete_matches(&candidates, "S"),
+            vec!["Draws", "Wins", "losses"]
+        );
+        assert_eq!(autocomplete_matches(&candidates, "wIN"), vec!["Wins"]);
+        assert!(autocomplete_matches(&candidates, "ties").is_empty());
+    }
+
+    #[test]
+    fn permissions_are_required() {
+        let required = Permissions::MANAGE_NICKNAMES;
+        assert_eq!(
+            permissions_refusal(true, Some(Permissions::MANAGE_NICKNAMES), required),
+            None
+        );
+        assert_eq!(
+            permissions_refusal(true, Some(Permissions::ADMINISTRATOR), required),
+            None
+        );
+        assert_eq!(
+            permissions_refusal(true, Some(Permissions::MANAGE_CHANNELS), required).unwrap(),
+            "**Unauthorised:** You need the Manage Nicknames permission to do that!"
+        );
+        assert!(permissions_refusal(true, None, required).is_some());
+        assert_eq!(
+            permissions_refusal(false, None, required).unwrap(),
+            "**This can only be used in a server.**"
+        );
     }
 }
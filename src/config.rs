@@ -1,15 +1,24 @@
 use serenity::all::MessageId;
 use std::collections::hash_map::Keys;
-use std::collections::HashMap;
-use std::{env, fs};
-use tokio::sync::RwLockReadGuard;
+#[cfg(feature = "events")]
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{env, fs, io};
+use tokio::sync::RwLock;
 
-use log::error;
+use log::{error, info, warn};
 
 use serde::{Deserialize, Serialize};
 use serenity::client::{Client, ClientBuilder};
-use serenity::model::prelude::{GuildId, UserId};
-use serenity::prelude::{GatewayIntents, TypeMap, TypeMapKey};
+use serenity::http::CacheHttp;
+use serenity::model::prelude::{GuildId, User, UserId};
+use serenity::prelude::{GatewayIntents, TypeMapKey};
 
 #[cfg(feature = "events")]
 use crate::subsystems::events::Event;
@@ -19,39 +28,214 @@ use crate::subsystems::memes::Memes;
 use crate::subsystems::nickname_lottery::NicknameLotteryGuildData;
 #[cfg(feature = "scoreboard")]
 use crate::subsystems::scoreboard::ScoreboardData;
+#[cfg(feature = "stream-indicator")]
+use crate::subsystems::stream_indicator::STREAMING_PREFIX;
+#[cfg(feature = "text-response")]
+use crate::subsystems::text_response::ResponseEntry;
+#[cfg(feature = "thread-reviver")]
+use crate::subsystems::thread_reviver::ThreadReviverGuildConfig;
 #[cfg(feature = "timeout-monitor")]
 use crate::subsystems::timeout_monitor::{
-    AnnouncementsConfig as TimeoutAnnouncementsConfig, UserTimeoutData,
+    AnnouncementsConfig as TimeoutAnnouncementsConfig, TimeoutMonitorGuildConfig, UserTimeoutData,
 };
 #[cfg(feature = "timeout-monitor")]
 use serenity::model::prelude::Channel;
-#[cfg(feature = "memes")]
+#[cfg(any(feature = "events", feature = "memes", feature = "stream-indicator"))]
 use serenity::model::prelude::ChannelId;
 
+/// How long [Config::save] may take before a warning is logged.
+const SLOW_SAVE_THRESHOLD: Duration = Duration::from_millis(500);
+
 /// Abstraction to try get a handle to a [GuildId]'s [Guild] entry
-/// from the config, based on a [RwLockReadGuard<TypeMap>] obtained
-/// from a [serenity::prelude::Context].
-pub fn get_guild<'a>(data: &'a RwLockReadGuard<TypeMap>, guild: &GuildId) -> Option<&'a Guild> {
-    let config = data.get::<Config>().unwrap();
+/// from the config, such as through a handle obtained with `acquire_data_handle!`.
+pub fn get_guild<'a>(config: &'a Config, guild: &GuildId) -> Option<&'a Guild> {
     config.guild(guild)
 }
 
 /// Abstraction to try get a handle to a [GuildId]'s [Memes] entry
-/// from the config, based on a [RwLockReadGuard<TypeMap>] obtained
-/// from a [serenity::prelude::Context].
+/// from the config, such as through a handle obtained with `acquire_data_handle!`.
 ///
 /// In particular, this function helps to avoid a double-nested `if`.
 #[cfg(feature = "memes")]
-pub fn get_memes<'a>(data: &'a RwLockReadGuard<TypeMap>, guild: &GuildId) -> Option<&'a Memes> {
-    if let Some(guild) = get_guild(data, guild) {
+pub fn get_memes<'a>(config: &'a Config, guild: &GuildId) -> Option<&'a Memes> {
+    if let Some(guild) = get_guild(config, guild) {
         guild.memes()
     } else {
         None
     }
 }
 
+/// Check that each of `keys`, which key `what` by stringified Discord ID, is a valid ID: a
+/// non-zero number.
+#[cfg(any(
+    feature = "memes",
+    feature = "nickname-lottery",
+    feature = "scoreboard",
+    feature = "thread-reviver",
+    feature = "timeout-monitor"
+))]
+pub(crate) fn validate_id_keys<'a>(
+    what: &str,
+    keys: impl IntoIterator<Item = &'a String>,
+) -> crate::Result<()> {
+    for key in keys {
+        if !matches!(key.parse::<u64>(), Ok(id) if id != 0) {
+            return Err(crate::Error::InvalidConfig(format!(
+                "`{key}` in {what} isn't a valid Discord ID."
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Write `contents` to `path` such that `path` always holds either its previous
+/// contents or the new contents in full, even if interrupted (e.g. by a crash).
+///
+/// The contents are first written (and synced) to a temporary file alongside `path`, which is
+/// then renamed over it. Any temporary file left over from a previous interrupted write is
+/// overwritten.
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp_path, path)
+}
+
+/// The version of the config file's schema which this build reads and writes.
+///
+/// Bump this, and add a step to [MIGRATIONS], whenever a change would stop older config files
+/// from loading as they are.
+const SCHEMA_VERSION: u32 = 3;
+
+/// The steps to upgrade a config file between successive schema versions, where `MIGRATIONS[n]`
+/// upgrades a config from version `n` to `n + 1`.
+const MIGRATIONS: [fn(&mut toml::Table); SCHEMA_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// Upgrade a raw config file to [SCHEMA_VERSION], in place.
+///
+/// Files without a `schema_version` predate versioning, so are treated as version 0. Files from a
+/// newer version of Loki than this one are rejected, as they may hold data which this version
+/// would lose.
+fn migrate(config: &mut toml::Table) -> crate::Result<()> {
+    let version = match config.get("schema_version") {
+        None => 0,
+        Some(v) => v
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| crate::Error::InvalidConfig(format!("Invalid `schema_version`: {v}")))?,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(crate::Error::InvalidConfig(format!(
+            "The config's schema version ({version}) is newer than this version of Loki \
+understands ({SCHEMA_VERSION}); please upgrade Loki."
+        )));
+    }
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        info!(
+            "Migrating config from schema version {from} to {}.",
+            from + 1
+        );
+        step(config);
+    }
+    config.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+    Ok(())
+}
+
+/// The raw tables of every guild in the config.
+fn guild_tables(config: &mut toml::Table) -> impl Iterator<Item = &mut toml::Table> {
+    config
+        .get_mut("guilds")
+        .and_then(toml::Value::as_table_mut)
+        .into_iter()
+        .flat_map(|guilds| guilds.iter_mut().filter_map(|(_, g)| g.as_table_mut()))
+}
+
+/// Before v0.11, each user's nicknames were a plain list of strings; they are now tables which
+/// also record the nickname's author and creation time (unknown for these nicknames).
+fn migrate_v0_to_v1(config: &mut toml::Table) {
+    for guild in guild_tables(config) {
+        let Some(users) = guild
+            .get_mut("nickname_lottery_data")
+            .and_then(|data| data.get_mut("user_specific_nicknames"))
+            .and_then(toml::Value::as_table_mut)
+        else {
+            continue;
+        };
+        for nickname in users
+            .iter_mut()
+            .filter_map(|(_, nicknames)| nicknames.as_array_mut())
+            .flatten()
+        {
+            if let Some(s) = nickname.as_str() {
+                let mut table = toml::Table::new();
+                table.insert("nickname".to_string(), s.into());
+                *nickname = table.into();
+            }
+        }
+    }
+}
+
+/// Meme contests created before win counts and Loki's own reactions were recorded are missing
+/// those fields; fill them in as for a new contest, where nobody has won yet and Loki hasn't
+/// reacted.
+fn migrate_v1_to_v2(config: &mut toml::Table) {
+    for guild in guild_tables(config) {
+        if let Some(memes) = guild.get_mut("memes").and_then(toml::Value::as_table_mut) {
+            memes
+                .entry("times_won")
+                .or_insert_with(|| toml::Table::new().into());
+            memes.entry("reacted").or_insert(false.into());
+        }
+    }
+}
+
+/// Text responses were a plain map of (lowercase) activation phrases to responses; each response
+/// is now a table which also records how its phrase is matched, which for these is as a substring.
+fn migrate_v2_to_v3(config: &mut toml::Table) {
+    for guild in guild_tables(config) {
+        let Some(responses) = guild
+            .get_mut("response_map")
+            .and_then(toml::Value::as_table_mut)
+        else {
+            continue;
+        };
+        for (phrase, response) in responses.iter_mut() {
+            if let Some(s) = response.as_str() {
+                let mut pattern = toml::Table::new();
+                pattern.insert("kind".to_string(), "substring".into());
+                pattern.insert("value".to_string(), phrase.as_str().into());
+                let mut table = toml::Table::new();
+                table.insert("pattern".to_string(), pattern.into());
+                table.insert("response".to_string(), s.into());
+                *response = table.into();
+            }
+        }
+    }
+}
+
+/// Read the manager's [UserId] from the `LOKI_MANAGER_ID` environment
+/// variable, if it is set to a valid ID.
+fn env_manager() -> Option<UserId> {
+    let manager = env::var("LOKI_MANAGER_ID").ok()?;
+    match manager.parse::<u64>() {
+        Ok(id) if id != 0 => Some(UserId::new(id)),
+        _ => {
+            error!("Ignoring invalid `LOKI_MANAGER_ID`: {manager}");
+            None
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Config {
+    /// The [SCHEMA_VERSION] which the config was saved with.
+    #[serde(default)]
+    schema_version: u32,
     manager: UserId,
     status_meaning: Option<String>,
     tokens: Tokens,
@@ -59,49 +243,222 @@ pub struct Config {
     /// Using a [String] here as [toml] has issues deserialising this to
     /// anything else, for some reason?
     guilds: Option<HashMap<String, Guild>>,
+    /// Ordered collections, so that the serialised config is deterministic between saves.
     #[cfg(feature = "events")]
-    subscribers: Option<HashMap<crate::subsystems::events::Event, Vec<UserId>>>,
+    subscribers: Option<BTreeMap<crate::subsystems::events::Event, BTreeSet<UserId>>>,
+    /// Values set through environment variables, which are never saved to the file.
+    #[serde(skip)]
+    env: EnvOverrides,
+    /// How many times the config has been saved since it was loaded.
+    #[serde(skip)]
+    saves: AtomicU64,
+}
+
+/// Values set through environment variables, which take precedence over those in the file.
+#[derive(Default)]
+struct EnvOverrides {
+    discord: Option<String>,
+    manager: Option<UserId>,
+    #[cfg(feature = "status-meaning")]
+    status_meaning: Option<String>,
+}
+
+impl EnvOverrides {
+    fn read() -> Self {
+        Self {
+            discord: env::var("LOKI_DISCORD_TOKEN").ok(),
+            manager: env_manager(),
+            #[cfg(feature = "status-meaning")]
+            status_meaning: env::var("LOKI_STATUS_MEANING").ok(),
+        }
+    }
 }
 
 impl Config {
     /// Load config from the configuration file, located either at
     /// the location specified by the `LOKI_CONFIG_PATH` environment
     /// variable or `config.toml` by default.
+    ///
+    /// Values set through environment variables (see [Config::from_env])
+    /// override those in the file. If the file does not exist, the
+    /// configuration is loaded entirely from environment variables instead.
     pub fn load() -> Self {
-        let config_path =
-            env::var("LOKI_CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
+        let config_path = Self::path();
 
-        let config = match fs::read_to_string(&config_path) {
-            Ok(s) => s,
+        let mut config: Self = match fs::read_to_string(&config_path) {
+            Ok(s) => {
+                let mut config = Self::parse(&s).unwrap_or_else(|e| {
+                    panic!("Unable to load config at '{}': {e:?}", &config_path)
+                });
+                config.apply_env_overrides();
+                config
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Self::from_env().unwrap_or_else(|| {
+                    panic!(
+                    "No config found at '{}', and the `LOKI_DISCORD_TOKEN` and `LOKI_MANAGER_ID` \
+environment variables are not both set.",
+                    &config_path
+                )
+                })
+            }
             Err(e) => panic!("Unable to read config at '{}': {:?}", &config_path, e),
         };
-        let mut config: Self = toml::from_str(&config).unwrap();
         if config.guilds.is_none() {
             config.guilds = Some(HashMap::new());
         }
         #[cfg(feature = "events")]
         if config.subscribers.is_none() {
-            config.subscribers = Some(HashMap::new());
+            config.subscribers = Some(BTreeMap::new());
         }
         config
     }
 
-    pub fn save(&self) {
-        let config_path =
-            env::var("LOKI_CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
+    /// The location of the configuration file: either the `LOKI_CONFIG_PATH`
+    /// environment variable, or `config.toml` by default.
+    fn path() -> String {
+        env::var("LOKI_CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string())
+    }
 
-        match toml::to_string_pretty(self) {
-            Ok(s) => {
-                if let Err(e) = fs::write(config_path.clone(), s) {
-                    error!("Failed to write config to {config_path}: {e}");
+    /// Re-read the configuration file (see [Config::load]), without applying
+    /// it; see [Config::replace].
+    pub async fn reread() -> crate::Result<Self> {
+        Self::reread_from(Path::new(&Self::path())).await
+    }
+
+    async fn reread_from(path: &Path) -> crate::Result<Self> {
+        let s = tokio::fs::read_to_string(path).await.map_err(|e| {
+            crate::Error::InvalidConfig(format!(
+                "Unable to read config at '{}': {e}",
+                path.display()
+            ))
+        })?;
+        let mut config = Self::parse(&s).map_err(|e| match e {
+            crate::Error::InvalidConfig(e) => crate::Error::InvalidConfig(format!(
+                "Unable to parse config at '{}': {e}",
+                path.display()
+            )),
+            e => e,
+        })?;
+        config.apply_env_overrides();
+        Ok(config)
+    }
+
+    /// Replace this config with a freshly [reread](Config::reread) one.
+    ///
+    /// Runtime state which isn't saved to the file (such as whether a guild's
+    /// background tasks have been started) is kept, as are any guilds which
+    /// the file doesn't mention. A changed Discord token only takes effect on
+    /// restart.
+    pub fn replace(&mut self, mut config: Self) {
+        let guilds = config.guilds.get_or_insert_with(HashMap::new);
+        for (id, old) in self.guilds.take().unwrap_or_default() {
+            match guilds.get_mut(&id) {
+                Some(guild) => guild.keep_runtime_state(old),
+                None => {
+                    guilds.insert(id, old);
                 }
             }
-            Err(e) => error!("Failed to serialise config: {e}"),
+        }
+        #[cfg(feature = "events")]
+        if config.subscribers.is_none() {
+            config.subscribers = Some(BTreeMap::new());
+        }
+        // Reloading changes the config just as saving does.
+        config.saves = AtomicU64::new(self.saves() + 1);
+        *self = config;
+    }
+
+    /// Parse the contents of a config file, migrating it from an older schema version if needed.
+    fn parse(s: &str) -> crate::Result<Self> {
+        let mut table: toml::Table =
+            toml::from_str(s).map_err(|e| crate::Error::InvalidConfig(e.to_string()))?;
+        migrate(&mut table)?;
+        table
+            .try_into()
+            .map_err(|e: toml::de::Error| crate::Error::InvalidConfig(e.to_string()))
+    }
+
+    /// Construct a config purely from environment variables, for deployments
+    /// which pass configuration (and secrets) through the environment.
+    ///
+    /// - `LOKI_DISCORD_TOKEN` (required): the Discord bot token.
+    /// - `LOKI_MANAGER_ID` (required): the manager's Discord User ID.
+    /// - `LOKI_STATUS_MEANING`: the meaning of the manager's Discord status.
+    ///
+    /// Returns [None] if either of the required variables is unset or invalid.
+    ///
+    /// The token isn't saved to the file, so it must still be set in the environment next time.
+    pub fn from_env() -> Option<Self> {
+        let env = EnvOverrides::read();
+        env.discord.as_ref()?;
+        Some(Self {
+            schema_version: SCHEMA_VERSION,
+            manager: env.manager?,
+            status_meaning: None,
+            tokens: Tokens {
+                discord: String::new(),
+            },
+            guilds: None,
+            #[cfg(feature = "events")]
+            subscribers: None,
+            env,
+            saves: AtomicU64::new(0),
+        })
+    }
+
+    /// Override any values which are also set through environment variables, without changing
+    /// what's saved to the file.
+    fn apply_env_overrides(&mut self) {
+        self.env = EnvOverrides::read();
+    }
+
+    /// Save the config to the configuration file (see [Config::load]).
+    ///
+    /// The file is written atomically on a blocking thread, so that a crash
+    /// can't leave it partially written and slow storage doesn't stall the
+    /// async executor; a warning is logged if saving takes longer than
+    /// [SLOW_SAVE_THRESHOLD].
+    pub async fn save(&self) {
+        let config_path = Self::path();
+        self.saves.fetch_add(1, Ordering::Relaxed);
+
+        let start = Instant::now();
+        let s = match toml::to_string_pretty(self) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to serialise config: {e}");
+                return;
+            }
+        };
+        let path = config_path.clone();
+        match tokio::task::spawn_blocking(move || write_atomically(Path::new(&path), &s)).await {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => error!("Failed to write config to {config_path}: {e}"),
+            Err(e) => error!("Config write to {config_path} did not complete: {e}"),
+        }
+        let elapsed = start.elapsed();
+        if elapsed > SLOW_SAVE_THRESHOLD {
+            warn!(
+                "Saving config to {config_path} took {}ms.",
+                elapsed.as_millis()
+            );
         }
     }
 
+    /// How many times the config has been saved, so that callers can tell whether something
+    /// changed it in the meantime.
+    pub fn saves(&self) -> u64 {
+        self.saves.load(Ordering::Relaxed)
+    }
+
     pub fn get_manager(&self) -> UserId {
-        self.manager
+        self.env.manager.unwrap_or(self.manager)
+    }
+
+    /// Resolve the bot manager's [User].
+    pub async fn manager_user(&self, cache_http: impl CacheHttp) -> crate::Result<User> {
+        Ok(self.get_manager().to_user(cache_http).await?)
     }
 
     pub fn guilds(&self) -> Keys<'_, std::string::String, Guild> {
@@ -112,6 +469,11 @@ impl Config {
         }
     }
 
+    /// Every guild's data, in no particular order.
+    pub fn guild_data(&self) -> impl Iterator<Item = &Guild> {
+        self.guilds.iter().flat_map(HashMap::values)
+    }
+
     pub fn guild(&self, id: &GuildId) -> Option<&Guild> {
         if let Some(guilds) = &self.guilds {
             guilds.get(&id.to_string())
@@ -128,16 +490,66 @@ impl Config {
         }
     }
 
+    /// Remove the data for any guilds which aren't in `active_guilds` (such as those which Loki
+    /// has been removed from), returning how many were removed.
+    ///
+    /// Active guilds are always kept, even if they have no data, so that their entries aren't
+    /// immediately recreated.
+    pub fn prune_guilds(&mut self, active_guilds: &[GuildId]) -> usize {
+        let active: HashSet<String> = active_guilds.iter().map(GuildId::to_string).collect();
+        let guilds = self.guilds.get_or_insert_with(HashMap::new);
+        let before = guilds.len();
+        guilds.retain(|id, _| active.contains(id));
+        before - guilds.len()
+    }
+
+    /// Serialise all of the data for guild `id` as JSON, for `/config export`.
+    pub fn export_guild(&self, id: &GuildId) -> crate::Result<String> {
+        let guild = self.guild(id).ok_or_else(|| {
+            crate::Error::InvalidConfig(format!("There is no data for guild {id} to export."))
+        })?;
+        serde_json::to_string_pretty(guild).map_err(|e| {
+            crate::Error::InvalidConfig(format!("Unable to serialise guild {id}: {e}"))
+        })
+    }
+
+    /// Replace the data for guild `id` with that in `json` (as produced by
+    /// [Config::export_guild]), for `/config import`.
+    ///
+    /// The config is left untouched if `json` isn't a valid guild export, including if it holds
+    /// any invalid IDs. Runtime state, and anything registered with Discord for this guild, is
+    /// kept from the existing data.
+    pub fn import_guild(&mut self, id: &GuildId, json: &str) -> crate::Result<()> {
+        let mut guild: Guild = serde_json::from_str(json).map_err(|e| {
+            crate::Error::InvalidConfig(format!("Unable to import guild data: {e}"))
+        })?;
+        guild.validate_ids().map_err(|e| match e {
+            crate::Error::InvalidConfig(e) => {
+                crate::Error::InvalidConfig(format!("Unable to import guild data: {e}"))
+            }
+            e => e,
+        })?;
+        let guilds = self.guilds.get_or_insert_with(HashMap::new);
+        if let Some(old) = guilds.remove(&id.to_string()) {
+            guild.keep_local_state(old);
+        }
+        guilds.insert(id.to_string(), guild);
+        Ok(())
+    }
+
     /// Construct a [ClientBuilder] from the supplied
     /// [GatewayIntents] and the configured Discord token.
     pub fn discord_client(&self, intents: GatewayIntents) -> ClientBuilder {
-        Client::builder(&self.tokens.discord, intents)
+        Client::builder(
+            self.env.discord.as_ref().unwrap_or(&self.tokens.discord),
+            intents,
+        )
     }
 }
 
 #[cfg(feature = "events")]
 impl Config {
-    pub fn subscribers(&self, event: Event) -> Option<&Vec<UserId>> {
+    pub fn subscribers(&self, event: Event) -> Option<&BTreeSet<UserId>> {
         if let Some(subscribers) = &self.subscribers {
             subscribers.get(&event)
         } else {
@@ -145,29 +557,50 @@ impl Config {
         }
     }
 
-    pub fn subscribers_mut(&mut self, event: Event) -> &mut Vec<UserId> {
+    pub fn subscribers_mut(&mut self, event: Event) -> &mut BTreeSet<UserId> {
         if let Some(subscribers) = &mut self.subscribers {
-            subscribers.entry(event).or_insert_with(Vec::new)
+            subscribers.entry(event).or_default()
         } else {
             unreachable!()
         }
     }
+
+    /// Unsubscribe the [UserId] from the given [Event], returning whether
+    /// they were subscribed.
+    pub fn remove_subscriber(&mut self, event: Event, user_id: UserId) -> bool {
+        self.subscribers_mut(event).remove(&user_id)
+    }
+
+    /// The channel to post an `event` which happened in `guild` to, if it should be posted.
+    ///
+    /// [Event::Error] is never posted, as its details are only for the manager.
+    pub fn events_channel(&self, guild: &GuildId, event: Event) -> Option<ChannelId> {
+        if event == Event::Error {
+            return None;
+        }
+        self.guild(guild).and_then(Guild::events_channel)
+    }
 }
 
 #[cfg(feature = "status-meaning")]
 impl Config {
     pub fn get_status_meaning(&self) -> Option<String> {
-        self.status_meaning.clone()
+        self.env
+            .status_meaning
+            .clone()
+            .or_else(|| self.status_meaning.clone())
     }
 
-    pub fn set_status_meaning(&mut self, s: Option<String>) {
+    /// Set the status meaning, which takes precedence over `LOKI_STATUS_MEANING` from then on.
+    pub async fn set_status_meaning(&mut self, s: Option<String>) {
+        self.env.status_meaning = None;
         self.status_meaning = s;
-        self.save();
+        self.save().await;
     }
 }
 
 impl TypeMapKey for Config {
-    type Value = Config;
+    type Value = Arc<RwLock<Config>>;
 }
 
 #[derive(Deserialize, Serialize)]
@@ -175,23 +608,94 @@ struct Tokens {
     discord: String,
 }
 
-#[derive(Deserialize, Serialize, Default)]
+#[derive(Deserialize, Serialize)]
 pub struct Guild {
     #[serde(skip)]
     threads_started: bool,
-    response_map: Option<HashMap<String, String>>,
+    #[cfg(feature = "text-response")]
+    response_map: Option<HashMap<String, ResponseEntry>>,
     #[cfg(feature = "memes")]
     memes: Option<Memes>,
     #[cfg(feature = "timeout-monitor")]
     timeouts: Option<HashMap<String, UserTimeoutData>>,
     #[cfg(feature = "timeout-monitor")]
     timeouts_announcement_config: Option<TimeoutAnnouncementsConfig>,
+    #[cfg(feature = "timeout-monitor")]
+    #[serde(default)]
+    timeout_monitor_config: TimeoutMonitorGuildConfig,
     #[cfg(feature = "nickname-lottery")]
     #[serde(default)]
     nickname_lottery_data: NicknameLotteryGuildData,
     #[cfg(feature = "scoreboard")]
     #[serde(default)]
     scoreboards: ScoreboardData,
+    /// The prefix added to streaming members' nicknames, or [None] for [STREAMING_PREFIX].
+    #[cfg(feature = "stream-indicator")]
+    streaming_prefix: Option<String>,
+    /// Members who never get the streaming prefix, in addition to the server owner.
+    #[cfg(feature = "stream-indicator")]
+    streaming_excluded_users: Option<Vec<UserId>>,
+    /// The channel which members going live are announced in.
+    #[cfg(feature = "stream-indicator")]
+    stream_announcement_channel: Option<ChannelId>,
+    #[cfg(feature = "thread-reviver")]
+    #[serde(default)]
+    thread_reviver_config: ThreadReviverGuildConfig,
+    /// The top-level commands which may be run in this guild, or [None] if all of them may.
+    enabled_commands: Option<HashSet<String>>,
+    /// The channel which events are posted to, in addition to subscribers' DMs.
+    #[cfg(feature = "events")]
+    events_channel: Option<ChannelId>,
+}
+
+// Implemented manually (rather than derived) so that every field's default is a deliberate
+// choice; new fields must be added here explicitly.
+#[allow(clippy::derivable_impls)]
+impl Default for Guild {
+    fn default() -> Self {
+        Self {
+            // Background threads are started on the first `guild_create` after startup.
+            threads_started: false,
+            // No text responses until one is configured.
+            #[cfg(feature = "text-response")]
+            response_map: None,
+            // The meme contest is disabled until a channel is set.
+            #[cfg(feature = "memes")]
+            memes: None,
+            // No timeouts have been recorded yet.
+            #[cfg(feature = "timeout-monitor")]
+            timeouts: None,
+            // Timeout announcements are disabled until a channel is set.
+            #[cfg(feature = "timeout-monitor")]
+            timeouts_announcement_config: None,
+            // Every member's timeouts are tracked until a role is ignored.
+            #[cfg(feature = "timeout-monitor")]
+            timeout_monitor_config: TimeoutMonitorGuildConfig::default(),
+            // An empty lottery, using the default refresh interval and no announcement channel.
+            #[cfg(feature = "nickname-lottery")]
+            nickname_lottery_data: NicknameLotteryGuildData::default(),
+            // No scoreboards until one is created.
+            #[cfg(feature = "scoreboard")]
+            scoreboards: ScoreboardData::default(),
+            // Streaming members get the default prefix until another is set.
+            #[cfg(feature = "stream-indicator")]
+            streaming_prefix: None,
+            // Only the server owner is excluded from the streaming prefix until others are.
+            #[cfg(feature = "stream-indicator")]
+            streaming_excluded_users: None,
+            // Members going live are only DM'd to subscribers until a channel is set.
+            #[cfg(feature = "stream-indicator")]
+            stream_announcement_channel: None,
+            // Threads in every channel are revived as soon as they're archived.
+            #[cfg(feature = "thread-reviver")]
+            thread_reviver_config: ThreadReviverGuildConfig::default(),
+            // Every command is enabled until one is disabled.
+            enabled_commands: None,
+            // Events are only DM'd to subscribers until a channel is set.
+            #[cfg(feature = "events")]
+            events_channel: None,
+        }
+    }
 }
 
 impl Guild {
@@ -203,16 +707,94 @@ impl Guild {
         self.threads_started = true;
     }
 
-    pub fn response_map_mut(&mut self) -> &mut HashMap<String, String> {
+    #[cfg(feature = "text-response")]
+    pub fn response_map_mut(&mut self) -> &mut HashMap<String, ResponseEntry> {
         if self.response_map.is_none() {
             self.response_map = Some(HashMap::new());
         }
         self.response_map.as_mut().unwrap()
     }
 
-    pub fn response_map(&self) -> &Option<HashMap<String, String>> {
+    #[cfg(feature = "text-response")]
+    pub fn response_map(&self) -> &Option<HashMap<String, ResponseEntry>> {
         &self.response_map
     }
+
+    /// Whether the top-level command `name` may be run in this guild.
+    pub fn command_enabled(&self, name: &str) -> bool {
+        crate::ALWAYS_ENABLED_COMMANDS.contains(&name)
+            || self
+                .enabled_commands
+                .as_ref()
+                .is_none_or(|enabled| enabled.contains(name))
+    }
+
+    /// Allow the command `name` to be run in this guild, returning whether it was disabled.
+    pub fn enable_command(&mut self, name: &str) -> bool {
+        self.enabled_commands
+            .as_mut()
+            .is_some_and(|enabled| enabled.insert(name.to_string()))
+    }
+
+    /// Stop the command `name` from being run in this guild, returning whether it was enabled.
+    ///
+    /// If every command was enabled, the rest of `all_commands` remain so.
+    pub fn disable_command<'a>(
+        &mut self,
+        name: &str,
+        all_commands: impl IntoIterator<Item = &'a String>,
+    ) -> bool {
+        self.enabled_commands
+            .get_or_insert_with(|| all_commands.into_iter().cloned().collect())
+            .remove(name)
+    }
+
+    /// Carry over the runtime state (which isn't saved) from the `old` copy of this guild,
+    /// such as when the config is reloaded.
+    /// Check that the IDs which key this guild's data are valid, as they aren't checked when
+    /// it's deserialised.
+    fn validate_ids(&self) -> crate::Result<()> {
+        #[cfg(feature = "memes")]
+        if let Some(memes) = &self.memes {
+            memes.validate_ids()?;
+        }
+        #[cfg(feature = "timeout-monitor")]
+        if let Some(timeouts) = &self.timeouts {
+            validate_id_keys("timeouts", timeouts.keys())?;
+        }
+        #[cfg(feature = "nickname-lottery")]
+        self.nickname_lottery_data.validate_ids()?;
+        #[cfg(feature = "scoreboard")]
+        self.scoreboards.validate_ids()?;
+        #[cfg(feature = "thread-reviver")]
+        self.thread_reviver_config.validate_ids()?;
+        Ok(())
+    }
+
+    fn keep_runtime_state(&mut self, old: Guild) {
+        self.threads_started = old.threads_started;
+        #[cfg(feature = "scoreboard")]
+        self.scoreboards.keep_runtime_state(old.scoreboards);
+    }
+
+    /// Carry over the state which belongs to this particular guild from the `old` copy of it,
+    /// such as when importing data exported from another guild.
+    fn keep_local_state(&mut self, old: Guild) {
+        self.threads_started = old.threads_started;
+        #[cfg(feature = "scoreboard")]
+        self.scoreboards.keep_local_state(old.scoreboards);
+    }
+}
+
+#[cfg(feature = "events")]
+impl Guild {
+    pub fn events_channel(&self) -> Option<ChannelId> {
+        self.events_channel
+    }
+
+    pub fn set_events_channel(&mut self, channel: Option<ChannelId>) {
+        self.events_channel = channel;
+    }
 }
 
 #[cfg(feature = "memes")]
@@ -285,6 +867,14 @@ impl Guild {
     pub fn timeouts_announcement_config(&self) -> Option<&TimeoutAnnouncementsConfig> {
         self.timeouts_announcement_config.as_ref()
     }
+
+    pub fn timeout_monitor_config(&self) -> &TimeoutMonitorGuildConfig {
+        &self.timeout_monitor_config
+    }
+
+    pub fn timeout_monitor_config_mut(&mut self) -> &mut TimeoutMonitorGuildConfig {
+        &mut self.timeout_monitor_config
+    }
 }
 
 #[cfg(feature = "scoreboard")]
@@ -297,3 +887,464 @@ impl Guild {
         &mut self.scoreboards
     }
 }
+
+#[cfg(feature = "stream-indicator")]
+impl Guild {
+    /// The prefix added to streaming members' nicknames, falling back to [STREAMING_PREFIX].
+    pub fn streaming_prefix(&self) -> &str {
+        self.streaming_prefix.as_deref().unwrap_or(STREAMING_PREFIX)
+    }
+
+    pub fn set_streaming_prefix(&mut self, prefix: Option<String>) {
+        self.streaming_prefix = prefix;
+    }
+
+    pub fn stream_announcement_channel(&self) -> Option<ChannelId> {
+        self.stream_announcement_channel
+    }
+
+    pub fn set_stream_announcement_channel(&mut self, channel: Option<ChannelId>) {
+        self.stream_announcement_channel = channel;
+    }
+
+    pub fn streaming_excluded_users(&self) -> &[UserId] {
+        self.streaming_excluded_users.as_deref().unwrap_or_default()
+    }
+
+    /// Stop `user` getting the streaming prefix, returning whether they could before.
+    pub fn exclude_streaming_user(&mut self, user: UserId) -> bool {
+        let excluded = self.streaming_excluded_users.get_or_insert_with(Vec::new);
+        if excluded.contains(&user) {
+            return false;
+        }
+        excluded.push(user);
+        true
+    }
+
+    /// Let `user` get the streaming prefix again, returning whether they were excluded.
+    pub fn unexclude_streaming_user(&mut self, user: UserId) -> bool {
+        let Some(excluded) = &mut self.streaming_excluded_users else {
+            return false;
+        };
+        let before = excluded.len();
+        excluded.retain(|u| *u != user);
+        let removed = excluded.len() != before;
+        if excluded.is_empty() {
+            self.streaming_excluded_users = None;
+        }
+        removed
+    }
+}
+
+#[cfg(feature = "thread-reviver")]
+impl Guild {
+    pub fn thread_reviver_config(&self) -> &ThreadReviverGuildConfig {
+        &self.thread_reviver_config
+    }
+
+    pub fn thread_reviver_config_mut(&mut self) -> &mut ThreadReviverGuildConfig {
+        &mut self.thread_reviver_config
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "events")]
+    use std::collections::BTreeMap;
+    use std::{collections::HashMap, fs, path::PathBuf};
+
+    use serenity::model::prelude::{GuildId, UserId};
+
+    use super::{
+        migrate, migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3, write_atomically, Config,
+        EnvOverrides, Tokens, SCHEMA_VERSION,
+    };
+    #[cfg(feature = "text-response")]
+    use crate::subsystems::text_response::{ResponseEntry, ResponsePattern};
+
+    /// A text response of "world" to "hello".
+    #[cfg(feature = "text-response")]
+    fn hello_response() -> ResponseEntry {
+        ResponseEntry::new(
+            ResponsePattern::Substring("hello".to_string()),
+            "world".to_string(),
+        )
+    }
+
+    /// A fresh, empty directory for a test to write files in.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("loki-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A minimal config, managed by user 1, with no guilds.
+    fn config() -> Config {
+        Config {
+            schema_version: SCHEMA_VERSION,
+            manager: UserId::new(1),
+            status_meaning: None,
+            tokens: Tokens {
+                discord: "token".to_string(),
+            },
+            guilds: Some(HashMap::new()),
+            #[cfg(feature = "events")]
+            subscribers: Some(BTreeMap::new()),
+            env: EnvOverrides::default(),
+            saves: Default::default(),
+        }
+    }
+
+    #[test]
+    fn env_overrides_are_not_saved() {
+        let mut config = config();
+        config.env = EnvOverrides {
+            discord: Some("secret".to_string()),
+            manager: Some(UserId::new(2)),
+            #[cfg(feature = "status-meaning")]
+            status_meaning: Some("from the environment".to_string()),
+        };
+        assert_eq!(config.get_manager(), UserId::new(2));
+        let saved = toml::to_string(&config).unwrap();
+        assert!(!saved.contains("secret"));
+        assert!(!saved.contains("from the environment"));
+
+        let saved: Config = toml::from_str(&saved).unwrap();
+        assert_eq!(saved.get_manager(), UserId::new(1));
+        assert_eq!(saved.tokens.discord, "token");
+    }
+
+    #[tokio::test]
+    async fn reload_picks_up_changes_and_keeps_runtime_state() {
+        let dir = test_dir("reload");
+        let path = dir.join("config.toml");
+        let mut config = config();
+        let guild = GuildId::new(2);
+        config.guild_mut(&guild);
+        write_atomically(&path, &toml::to_string_pretty(&config).unwrap()).unwrap();
+
+        // Running state, which mustn't be lost on reload.
+        config.guild_mut(&guild).set_threads_started();
+        let runtime_only = GuildId::new(3);
+        config.guild_mut(&runtime_only);
+
+        // Someone edits the file on disk.
+        let edited = fs::read_to_string(&path)
+            .unwrap()
+            .replace("manager = \"1\"", "manager = \"4\"");
+        fs::write(&path, edited).unwrap();
+
+        config.replace(Config::reread_from(&path).await.unwrap());
+        assert_eq!(config.get_manager(), UserId::new(4));
+        assert!(config.guild(&guild).unwrap().threads_started());
+        assert!(config.guild(&runtime_only).is_some());
+        // Reloading counts as a change, so that subsystems pick it up.
+        assert_eq!(config.saves(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reload_rejects_invalid_config() {
+        let dir = test_dir("reload-invalid");
+        let path = dir.join("config.toml");
+        fs::write(&path, "manager = ").unwrap();
+        assert!(matches!(
+            Config::reread_from(&path).await,
+            Err(crate::Error::InvalidConfig(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_write_replaces_contents() {
+        let dir = test_dir("atomic-write");
+        let path = dir.join("config.toml");
+        write_atomically(&path, "first = 1\n").unwrap();
+        write_atomically(&path, "second = 2\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second = 2\n");
+        assert!(!dir.join("config.toml.tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn interrupted_write_leaves_config_intact() {
+        let dir = test_dir("interrupted-write");
+        let path = dir.join("config.toml");
+        write_atomically(&path, "manager = 1\n").unwrap();
+        // Simulate a crash part-way through writing the next version.
+        let tmp_path = dir.join("config.toml.tmp");
+        fs::write(&tmp_path, "manager = ").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "manager = 1\n");
+        // The next save should replace the partial temporary file, rather than fail.
+        write_atomically(&path, "manager = 2\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "manager = 2\n");
+        assert!(!tmp_path.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "text-response")]
+    fn export_import_round_trips() {
+        let mut config = config();
+        let source = GuildId::new(2);
+        config
+            .guild_mut(&source)
+            .response_map_mut()
+            .insert("hello".to_string(), hello_response());
+        let export = config.export_guild(&source).unwrap();
+
+        let target = GuildId::new(3);
+        config.guild_mut(&target).set_threads_started();
+        config.import_guild(&target, &export).unwrap();
+        let imported = config.guild(&target).unwrap();
+        assert_eq!(
+            imported.response_map().as_ref().unwrap()["hello"].responses(),
+            ["world"]
+        );
+        assert!(imported.threads_started());
+        assert_eq!(config.export_guild(&target).unwrap(), export);
+    }
+
+    #[test]
+    #[cfg(feature = "text-response")]
+    fn import_rejects_malformed_data() {
+        let mut config = config();
+        let guild = GuildId::new(2);
+        config
+            .guild_mut(&guild)
+            .response_map_mut()
+            .insert("hello".to_string(), hello_response());
+        for json in ["not json", r#"{"response_map": 5}"#, r#"[1, 2, 3]"#] {
+            assert!(matches!(
+                config.import_guild(&guild, json),
+                Err(crate::Error::InvalidConfig(_))
+            ));
+        }
+        assert!(config.export_guild(&GuildId::new(3)).is_err());
+        assert_eq!(
+            config
+                .guild(&guild)
+                .unwrap()
+                .response_map()
+                .as_ref()
+                .unwrap()["hello"]
+                .responses(),
+            ["world"]
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "nickname-lottery", feature = "thread-reviver"))]
+    fn import_rejects_invalid_ids() {
+        let mut config = config();
+        let guild = GuildId::new(2);
+        for json in [
+            r#"{"nickname_lottery_data": {"user_specific_nicknames": {"someone": []}}}"#,
+            r#"{"nickname_lottery_data": {"excluded_users": ["0"]}}"#,
+            r#"{"thread_reviver_config": {"revival_counts": {"-1": 2}}}"#,
+        ] {
+            assert!(matches!(
+                config.import_guild(&guild, json),
+                Err(crate::Error::InvalidConfig(_))
+            ));
+        }
+        assert!(config.guild(&guild).is_none());
+        config
+            .import_guild(
+                &guild,
+                r#"{"thread_reviver_config": {"revival_counts": {"3": 2}}}"#,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn nickname_lists_migrate_to_tables() {
+        let mut config: toml::Table = toml::from_str(
+            r#"
+            [guilds.2.nickname_lottery_data.user_specific_nicknames]
+            3 = ["Loki", "Thor"]
+            "#,
+        )
+        .unwrap();
+        migrate_v0_to_v1(&mut config);
+        let expected: toml::Table = toml::from_str(
+            r#"
+            [guilds.2.nickname_lottery_data.user_specific_nicknames]
+            3 = [{ nickname = "Loki" }, { nickname = "Thor" }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+        // Already-migrated nicknames are left as they are.
+        migrate_v0_to_v1(&mut config);
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn meme_contests_gain_missing_fields() {
+        let mut config: toml::Table = toml::from_str(
+            r#"
+            [guilds.2.memes]
+            channel = "4"
+
+            [guilds.3.memes]
+            channel = "5"
+            reacted = true
+
+            [guilds.3.memes.times_won]
+            6 = 1
+            "#,
+        )
+        .unwrap();
+        migrate_v1_to_v2(&mut config);
+        let expected: toml::Table = toml::from_str(
+            r#"
+            [guilds.2.memes]
+            channel = "4"
+            reacted = false
+            times_won = {}
+
+            [guilds.3.memes]
+            channel = "5"
+            reacted = true
+
+            [guilds.3.memes.times_won]
+            6 = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn text_responses_migrate_to_tables() {
+        let mut config: toml::Table = toml::from_str(
+            r#"
+            [guilds.2.response_map]
+            hello = "world"
+            "#,
+        )
+        .unwrap();
+        migrate_v2_to_v3(&mut config);
+        let expected: toml::Table = toml::from_str(
+            r#"
+            [guilds.2.response_map]
+            hello = { pattern = { kind = "substring", value = "hello" }, response = "world" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+        // Already-migrated responses are left as they are.
+        migrate_v2_to_v3(&mut config);
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn unversioned_config_is_migrated_on_parse() {
+        let config = Config::parse(
+            r#"
+            manager = "1"
+
+            [tokens]
+            discord = "token"
+
+            [guilds.2.nickname_lottery_data.user_specific_nicknames]
+            3 = ["Loki"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.schema_version, SCHEMA_VERSION);
+        #[cfg(feature = "nickname-lottery")]
+        assert_eq!(
+            config
+                .guild(&GuildId::new(2))
+                .unwrap()
+                .nickname_lottery_data()
+                .user_nicknames(&UserId::new(3))
+                .unwrap()[0]
+                .nickname(),
+            "Loki"
+        );
+        assert!(toml::to_string(&config)
+            .unwrap()
+            .starts_with(&format!("schema_version = {SCHEMA_VERSION}\n")));
+    }
+
+    #[test]
+    fn newer_schema_version_is_rejected() {
+        let mut config: toml::Table =
+            toml::from_str(&format!("schema_version = {}", SCHEMA_VERSION + 1)).unwrap();
+        assert!(matches!(
+            migrate(&mut config),
+            Err(crate::Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn pruning_removes_only_inactive_guilds() {
+        let mut config = config();
+        let (kept, empty, removed) = (GuildId::new(2), GuildId::new(3), GuildId::new(4));
+        config.guild_mut(&kept).set_threads_started();
+        config.guild_mut(&empty);
+        config.guild_mut(&removed);
+        assert_eq!(config.prune_guilds(&[kept, empty]), 1);
+        assert!(config.guild(&kept).is_some());
+        assert!(config.guild(&empty).is_some());
+        assert!(config.guild(&removed).is_none());
+        assert_eq!(config.prune_guilds(&[kept, empty]), 0);
+    }
+
+    #[test]
+    fn disabling_a_command_enables_the_rest() {
+        let mut config = config();
+        let guild = config.guild_mut(&GuildId::new(2));
+        let all = ["about", "memes", "admin"].map(String::from);
+        assert!(guild.command_enabled("memes"));
+        assert!(!guild.enable_command("memes"));
+        assert!(guild.disable_command("memes", &all));
+        assert!(!guild.disable_command("memes", &all));
+        assert!(!guild.command_enabled("memes"));
+        assert!(guild.command_enabled("about"));
+        // Once there's an allowlist, commands which aren't on it (such as those added since) are
+        // disabled.
+        assert!(!guild.command_enabled("scoreboard"));
+        assert!(guild.enable_command("memes"));
+        assert!(guild.command_enabled("memes"));
+    }
+
+    #[test]
+    fn admin_command_cannot_be_disabled() {
+        let mut config = config();
+        let guild = config.guild_mut(&GuildId::new(2));
+        guild.disable_command("admin", &["admin".to_string()]);
+        assert!(guild.command_enabled("admin"));
+    }
+
+    #[cfg(feature = "events")]
+    #[test]
+    fn events_stay_in_their_own_guild() {
+        use crate::subsystems::events::Event;
+        use serenity::model::prelude::ChannelId;
+
+        let mut config = config();
+        config.guild_mut(&GuildId::new(2));
+        config
+            .guild_mut(&GuildId::new(3))
+            .set_events_channel(Some(ChannelId::new(4)));
+        assert_eq!(
+            config.events_channel(&GuildId::new(3), Event::Timeout),
+            Some(ChannelId::new(4))
+        );
+        assert_eq!(
+            config.events_channel(&GuildId::new(2), Event::Timeout),
+            None
+        );
+        assert_eq!(config.events_channel(&GuildId::new(3), Event::Error), None);
+        config.guild_mut(&GuildId::new(3)).set_events_channel(None);
+        assert_eq!(
+            config.events_channel(&GuildId::new(3), Event::Timeout),
+            None
+        );
+    }
+}
@@ -6,6 +6,7 @@ pub enum Error {
     InvalidUser,
     InvalidEvent(String),
     InvalidParam(String),
+    InvalidConfig(String),
     MissingActionRoutine,
     SerenityError(serenity::Error),
 }
@@ -38,6 +39,11 @@ Either Discord has failed to provide a parameter marked required, a \
 parameter isn't marked required when it should be, or the parameter's
 type was different than expected:
 `{s}`"
+            ),
+            Self::InvalidConfig(s) => write!(
+                f,
+                "**Error: Invalid config**
+{s}"
             ),
             Self::MissingActionRoutine => write!(
                 f,
@@ -95,3 +101,12 @@ Well, something's clearly gone wrong.
         }
     }
 }
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::SerenityError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -1,10 +1,31 @@
+mod audit;
+#[cfg(any(
+    feature = "memes",
+    feature = "nickname-lottery",
+    feature = "scoreboard",
+    feature = "stream-indicator",
+    feature = "text-response",
+    feature = "thread-reviver"
+))]
+mod backend;
 mod command;
 mod config;
 mod error;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod serenity_handler;
 mod subsystems;
 
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
 pub use log::{error, info};
+use serenity::{
+    all::{CommandDataOption, CommandInteraction, CreateAttachment, GuildId},
+    model::Permissions,
+};
 pub use serenity::{
     model::Colour,
     prelude::{GatewayIntents, Mentionable},
@@ -23,17 +44,34 @@ const COLOUR: Colour = Colour::new(0x0099ff);
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const REPO_URL: &str = env!("CARGO_PKG_REPOSITORY");
 
+/// Top-level commands which can't be disabled in a guild, as they control which commands are
+/// enabled.
+pub(crate) const ALWAYS_ENABLED_COMMANDS: [&str; 1] = ["admin"];
+
+/// How long each user must wait between uses of `/config export` or `/config import`.
+const CONFIG_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// The largest file (in bytes) accepted by `/config import`.
+const MAX_IMPORT_SIZE: u32 = 1024 * 1024;
+
+/// Acquire a read (default) or write handle on the [Config] stored in a
+/// [serenity::prelude::Context]'s data.
+///
+/// The global data lock is only held for long enough to clone the [Config]'s [std::sync::Arc],
+/// so only the [Config] itself remains locked whilst the handle is held.
 macro_rules! acquire_data_handle {
     ($ctx:ident) => { acquire_data_handle!(read $ctx) };
     (read $ctx:ident) => {{
         log::trace!("Acquiring data read handle...");
-        let data = $ctx.data.read().await;
+        let config = $ctx.data.read().await.get::<crate::Config>().unwrap().clone();
+        let data = config.read_owned().await;
         log::trace!("Acquired data read handle.");
         data
     }};
     (write $ctx:ident) => {{
         log::trace!("Acquiring data write handle...");
-        let data = $ctx.data.write().await;
+        let config = $ctx.data.read().await.get::<crate::Config>().unwrap().clone();
+        let data = config.write_owned().await;
         log::trace!("Acquired data write handle.");
         data
     }};
@@ -44,8 +82,22 @@ macro_rules! drop_data_handle {
         log::trace!("Dropping data handle.");
     };
 }
+/// Run `body` with the [config::Guild] for `guild_id` (a `&GuildId`), under a read handle which
+/// is dropped straight afterwards.
+///
+/// Evaluates to the body's result, or [None] if the guild has no config. As the body is a closure,
+/// it can't `.await`.
+macro_rules! with_guild {
+    ($ctx:ident, $guild_id:expr, |$guild:ident| $body:expr) => {{
+        let data = crate::acquire_data_handle!(read $ctx);
+        let result = crate::config::get_guild(&data, $guild_id).map(|$guild| $body);
+        crate::drop_data_handle!(data);
+        result
+    }};
+}
 pub(crate) use acquire_data_handle;
 pub(crate) use drop_data_handle;
+pub(crate) use with_guild;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -81,58 +133,309 @@ start streaming (excluding server owner).";
     if cfg!(feature = "scoreboard") {
         features += "\n**•** Scoreboards.";
     }
+    if cfg!(feature = "metrics") {
+        features += "\n**•** Prometheus metrics exporter.";
+    }
 
     features
 }
 
-fn intents() -> GatewayIntents {
-    let mut intents = GatewayIntents::non_privileged();
-
-    if cfg!(feature = "guild-presences") {
-        intents |= GatewayIntents::GUILD_PRESENCES;
-    }
-    if cfg!(feature = "guild-members") {
-        intents |= GatewayIntents::GUILD_MEMBERS;
-    }
-    if cfg!(feature = "message-content") {
-        intents |= GatewayIntents::MESSAGE_CONTENT;
-    }
-
-    intents
+fn intents(config: &Config) -> GatewayIntents {
+    subsystems()
+        .iter()
+        .fold(GatewayIntents::non_privileged(), |acc, s| {
+            acc | s.required_intents(config)
+        })
 }
 
 fn generate_commands() -> Vec<Command<'static>> {
-    let mut commands = vec![Command::new(
-        "about",
-        "Provides information about Loki.",
-        command::PermissionType::Universal,
-        Some(Box::new(move |ctx, _command, _params| {
-            Box::pin(async move {
-                let manager_tag = ctx
-                    .data
-                    .read()
-                    .await
-                    .get::<Config>()
-                    .unwrap()
-                    .get_manager()
-                    .to_user(&ctx)
-                    .await?
-                    .mention();
-                Ok(Some(ActionResponse::new(
-                    create_raw_embed(format!(
-                        "Loki is a trickster ~~god~~ bot.
+    let mut commands = vec![
+        Command::new(
+            "about",
+            "Provides information about Loki.",
+            command::PermissionType::Universal,
+            None,
+            Some(Box::new(move |ctx, _command, _params| {
+                Box::pin(async move {
+                    let data = crate::acquire_data_handle!(read ctx);
+                    let manager_tag = data.manager_user(&ctx).await?.mention();
+                    crate::drop_data_handle!(data);
+                    Ok(Some(ActionResponse::new(
+                        create_raw_embed(format!(
+                            "Loki is a trickster ~~god~~ bot.
 Version [{VERSION}]({REPO_URL}/releases/tag/v{VERSION}); [source code]({REPO_URL}).
 
 This instance of Loki is managed by {manager_tag}.
 
 Currently enabled features: {}",
-                        features()
-                    )),
-                    false,
-                )))
-            })
-        })),
-    )];
+                            features()
+                        )),
+                        false,
+                    )))
+                })
+            })),
+        ),
+        Command::new(
+            "reload",
+            "Manager-only: reloads the configuration file from disk.",
+            command::PermissionType::Universal,
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    let data = crate::acquire_data_handle!(read ctx);
+                    if command.user.id != data.get_manager() {
+                        let resp = format!(
+                            "**Unauthorised:** You're not {}!",
+                            data.manager_user(&ctx).await?.mention()
+                        );
+                        crate::drop_data_handle!(data);
+                        return Ok(Some(ActionResponse::new(create_raw_embed(resp), true)));
+                    }
+                    crate::drop_data_handle!(data);
+                    // Only hold the lock to swap the new config in, not while reading it.
+                    let config = match Config::reread().await {
+                        Ok(config) => config,
+                        Err(e) => {
+                            error!("Failed to reload config: {e:?}");
+                            return Ok(Some(ActionResponse::new(
+                                create_raw_embed(e.to_string()),
+                                true,
+                            )));
+                        }
+                    };
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    data.replace(config);
+                    let guilds: Vec<GuildId> =
+                        data.guilds().filter_map(|id| id.parse().ok()).collect();
+                    crate::drop_data_handle!(data);
+                    // Any guild's settings may have changed, so bring each one's commands up to date.
+                    for guild_id in guilds {
+                        subsystems::config_changed(ctx, &guild_id).await;
+                    }
+                    Ok(Some(ActionResponse::new(
+                        create_raw_embed("**Config reloaded.**"),
+                        true,
+                    )))
+                })
+            })),
+        ),
+        Command::new(
+            "admin",
+            "Bot and server administration.",
+            command::PermissionType::Universal,
+            None,
+            None,
+        )
+        .add_variant(Command::new(
+            "prune_config",
+            "Remove the data for servers which Loki is no longer in.",
+            command::PermissionType::Universal,
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    let data = crate::acquire_data_handle!(read ctx);
+                    if command.user.id != data.get_manager() {
+                        let resp = format!(
+                            "**Unauthorised:** You're not {}!",
+                            data.manager_user(&ctx).await?.mention()
+                        );
+                        crate::drop_data_handle!(data);
+                        return Ok(Some(ActionResponse::new(create_raw_embed(resp), true)));
+                    }
+                    crate::drop_data_handle!(data);
+                    if !confirm_action(
+                        ctx,
+                        command,
+                        "**Prune the config?**
+The data for every server Loki is no longer in will be permanently deleted.",
+                        true,
+                    )
+                    .await
+                    {
+                        return Ok(None);
+                    }
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let pruned = data.prune_guilds(&ctx.cache.guilds());
+                    if pruned > 0 {
+                        data.save().await;
+                    }
+                    crate::drop_data_handle!(data);
+                    info!("Pruned config for {pruned} guild(s).");
+                    Ok(Some(ActionResponse::new(
+                        create_raw_embed(format!("**Pruned {pruned} server(s) from the config.**")),
+                        true,
+                    )))
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "enable_command",
+                "Server admin-only: allow a command to be used in this server.",
+                command::PermissionType::Universal,
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let name = match toggleable_command(command, params)? {
+                            Ok(name) => name,
+                            Err(resp) => return Ok(Some(resp)),
+                        };
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let guild_id = command.guild_id.unwrap();
+                        let resp = if config.guild_mut(&guild_id).enable_command(name) {
+                            config.save().await;
+                            info!("[Guild: {guild_id}] Enabled `/{name}`.");
+                            format!("**Enabled `/{name}`.**")
+                        } else {
+                            format!("`/{name}` is already enabled.")
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(command_name_option()),
+        )
+        .add_variant(
+            Command::new(
+                "disable_command",
+                "Server admin-only: stop a command from being used in this server.",
+                command::PermissionType::Universal,
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let name = match toggleable_command(command, params)? {
+                            Ok(name) => name,
+                            Err(resp) => return Ok(Some(resp)),
+                        };
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let guild_id = command.guild_id.unwrap();
+                        let resp = if config
+                            .guild_mut(&guild_id)
+                            .disable_command(name, command_names())
+                        {
+                            config.save().await;
+                            info!("[Guild: {guild_id}] Disabled `/{name}`.");
+                            format!("**Disabled `/{name}`.**")
+                        } else {
+                            format!("`/{name}` is already disabled.")
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(command_name_option()),
+        ),
+        Command::new(
+            "config",
+            "Export or import this server's data.",
+            command::PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+            None,
+            None,
+        )
+        .add_variant(Command::new(
+            "export",
+            "Export this server's data as a JSON file.",
+            command::PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+            Some(CONFIG_COOLDOWN),
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    let guild_id = command.guild_id.unwrap();
+                    let data = crate::acquire_data_handle!(read ctx);
+                    let export = data.export_guild(&guild_id);
+                    crate::drop_data_handle!(data);
+                    let resp = match export {
+                        Ok(json) => ActionResponse::new(
+                            create_raw_embed("**Exported this server's data.**"),
+                            true,
+                        )
+                        .add_attachment(CreateAttachment::bytes(
+                            json,
+                            format!("loki-{guild_id}.json"),
+                        )),
+                        Err(e) => ActionResponse::new(create_raw_embed(e.to_string()), true),
+                    };
+                    Ok(Some(resp))
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "import",
+                "Replace this server's data with an exported JSON file.",
+                command::PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                Some(CONFIG_COOLDOWN),
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let guild_id = command.guild_id.unwrap();
+                        let attachment = params
+                            .iter()
+                            .find(|opt| opt.name == "file")
+                            .and_then(|opt| opt.value.as_attachment_id())
+                            .and_then(|id| command.data.resolved.attachments.get(&id))
+                            .ok_or_else(|| Error::InvalidParam("file".to_string()))?;
+                        if attachment.size > MAX_IMPORT_SIZE {
+                            return Ok(Some(ActionResponse::new(
+                                create_raw_embed(format!(
+                                    "**Unable to import `{}`:** the file is larger than {} KiB.",
+                                    attachment.filename,
+                                    MAX_IMPORT_SIZE / 1024
+                                )),
+                                true,
+                            )));
+                        }
+                        let bytes = attachment.download().await?;
+                        let Ok(json) = String::from_utf8(bytes) else {
+                            return Ok(Some(ActionResponse::new(
+                                create_raw_embed(format!(
+                                    "**Unable to import `{}`:** the file isn't valid UTF-8 text.",
+                                    attachment.filename
+                                )),
+                                true,
+                            )));
+                        };
+                        if !confirm_action(
+                            ctx,
+                            command,
+                            &format!(
+                                "**Import `{}`?**
+All of this server's current data will be replaced.",
+                                attachment.filename
+                            ),
+                            true,
+                        )
+                        .await
+                        {
+                            return Ok(None);
+                        }
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let resp = match data.import_guild(&guild_id, &json) {
+                            Ok(()) => {
+                                data.save().await;
+                                info!(
+                                    "[Guild: {guild_id}] Imported data from `{}`.",
+                                    attachment.filename
+                                );
+                                "**Imported this server's data.**".to_string()
+                            }
+                            Err(e) => e.to_string(),
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(command::Option::new(
+                "file",
+                "A file produced by `/config export`.",
+                command::OptionType::Attachment,
+                true,
+            )),
+        ),
+    ];
     subsystems()
         .iter()
         .for_each(|s| commands.append(&mut s.generate_commands()));
@@ -140,6 +443,65 @@ Currently enabled features: {}",
     commands
 }
 
+/// The names of all of the top-level commands.
+///
+/// These are fixed at compile time, so they're only collected once rather than rebuilding every
+/// command (e.g. on each autocomplete keystroke).
+fn command_names() -> &'static [String] {
+    static COMMAND_NAMES: LazyLock<Vec<String>> = LazyLock::new(|| {
+        generate_commands()
+            .iter()
+            .map(|command| command.name().to_string())
+            .collect()
+    });
+    &COMMAND_NAMES
+}
+
+/// The `name` option of `/admin enable_command` and `/admin disable_command`, which suggests the
+/// commands which can be toggled.
+fn command_name_option() -> command::Option<'static> {
+    command::Option::new(
+        "name",
+        "The name of the command (without the `/`).",
+        command::OptionType::StringInput(Some(1), None),
+        true,
+    )
+    .with_autocomplete(Box::new(|_ctx, _command, current| {
+        Box::pin(async move {
+            let names = command_names()
+                .iter()
+                .filter(|name| !ALWAYS_ENABLED_COMMANDS.contains(&name.as_str()));
+            autocomplete_matches(names, current)
+        })
+    }))
+}
+
+/// Get the command named by the `name` parameter of `/admin enable_command` or
+/// `/admin disable_command`, if it can be toggled by the user running `command`.
+///
+/// Otherwise, gives the response explaining why not.
+fn toggleable_command<'p>(
+    command: &CommandInteraction,
+    params: &'p [CommandDataOption],
+) -> Result<core::result::Result<&'p str, ActionResponse>> {
+    let name = params
+        .iter()
+        .find(|opt| opt.name == "name")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or_else(|| Error::InvalidParam("name".to_string()))?;
+    if let Some(refusal) = command::require_permissions(command, Permissions::ADMINISTRATOR) {
+        return Ok(Err(refusal));
+    }
+    let refusal = if ALWAYS_ENABLED_COMMANDS.contains(&name) {
+        format!("`/{name}` can't be disabled.")
+    } else if !command_names().iter().any(|command| command == name) {
+        format!("There is no `/{name}` command.")
+    } else {
+        return Ok(Ok(name));
+    };
+    Ok(Err(ActionResponse::new(create_raw_embed(refusal), true)))
+}
+
 pub async fn run() {
     env_logger::init();
 
@@ -151,20 +513,23 @@ pub async fn run() {
 
     let config = Config::load();
 
+    #[cfg(feature = "metrics")]
+    tokio::spawn(metrics::serve());
+
     let commands = generate_commands();
 
     let handler = SerenityHandler::new(commands);
 
     // Login with a bot token from the environment
     let mut client = config
-        .discord_client(intents())
+        .discord_client(intents(&config))
         .event_handler(handler)
         .await
         .expect("Error creating client");
 
     {
         let mut data = client.data.write().await;
-        data.insert::<Config>(config);
+        data.insert::<Config>(Arc::new(tokio::sync::RwLock::new(config)));
     }
 
     loop {
@@ -0,0 +1,138 @@
+use std::{env, sync::LazyLock};
+
+use log::{error, info, warn};
+use prometheus::{register_int_counter_vec, Encoder, IntCounterVec, TextEncoder};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// The port the exporter listens on if `LOKI_METRICS_PORT` is not set.
+const DEFAULT_PORT: u16 = 9091;
+
+static COMMANDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "loki_commands_total",
+        "Number of commands run, by command and outcome.",
+        &["command", "status"]
+    )
+    .unwrap()
+});
+
+#[cfg(feature = "events")]
+static EVENTS_DISPATCHED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "loki_events_dispatched_total",
+        "Number of events dispatched to subscribers, by event.",
+        &["event"]
+    )
+    .unwrap()
+});
+
+#[cfg(any(
+    feature = "memes",
+    feature = "nickname-lottery",
+    feature = "scoreboard",
+    feature = "thread-reviver"
+))]
+static BACKGROUND_TASK_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "loki_background_task_errors_total",
+        "Number of errors in background tasks, by subsystem and guild.",
+        &["subsystem", "guild"]
+    )
+    .unwrap()
+});
+
+/// Record that `command` was run, with the given `status` (`ok` or `error`).
+pub fn record_command(command: &str, status: &str) {
+    COMMANDS.with_label_values(&[command, status]).inc();
+}
+
+/// Record that `event` was dispatched to its subscribers.
+#[cfg(feature = "events")]
+pub fn record_event(event: &str) {
+    EVENTS_DISPATCHED.with_label_values(&[event]).inc();
+}
+
+/// Record an error in a `subsystem`'s background task for `guild`.
+#[cfg(any(
+    feature = "memes",
+    feature = "nickname-lottery",
+    feature = "scoreboard",
+    feature = "thread-reviver"
+))]
+pub fn record_background_error(subsystem: &str, guild: &str) {
+    BACKGROUND_TASK_ERRORS
+        .with_label_values(&[subsystem, guild])
+        .inc();
+}
+
+/// Serve Prometheus-compatible metrics at `/metrics` on the port given by
+/// the `LOKI_METRICS_PORT` environment variable (default 9091).
+pub async fn serve() {
+    let port = match env::var("LOKI_METRICS_PORT") {
+        Ok(port) => port.parse().unwrap_or_else(|_| {
+            warn!("Ignoring invalid `LOKI_METRICS_PORT` ({port}); using {DEFAULT_PORT}.");
+            DEFAULT_PORT
+        }),
+        Err(_) => DEFAULT_PORT,
+    };
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Unable to start metrics exporter on port {port}: {e}");
+            return;
+        }
+    };
+    info!("Serving metrics on port {port}.");
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(respond(stream));
+            }
+            Err(e) => warn!("Error accepting metrics connection: {e}"),
+        }
+    }
+}
+
+/// Answer a single HTTP request on `stream`.
+async fn respond(mut stream: TcpStream) {
+    let mut buf = [0; 1024];
+    let n = match stream.read(&mut buf).await {
+        Ok(n) => n,
+        Err(e) => {
+            warn!("Error reading metrics request: {e}");
+            return;
+        }
+    };
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let (status, content_type, body) = if request.starts_with("GET /metrics ") {
+        let encoder = TextEncoder::new();
+        let mut body = Vec::new();
+        if let Err(e) = encoder.encode(&prometheus::gather(), &mut body) {
+            error!("Error encoding metrics: {e}");
+            return;
+        }
+        ("200 OK", encoder.format_type().to_string(), body)
+    } else {
+        (
+            "404 Not Found",
+            "text/plain".to_string(),
+            b"Not Found".to_vec(),
+        )
+    };
+    let header = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    if let Err(e) = async {
+        stream.write_all(header.as_bytes()).await?;
+        stream.write_all(&body).await?;
+        stream.shutdown().await
+    }
+    .await
+    {
+        warn!("Error writing metrics response: {e}");
+    }
+}
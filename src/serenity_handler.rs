@@ -1,10 +1,12 @@
-use crate::command::OptionType;
-use crate::config::Config;
+use crate::audit::{AuditEntry, AuditLog};
+use crate::command::{CommandKind, OptionType, NUM_SELECTABLES};
 use crate::subsystems;
+use chrono::Utc;
 use log::{error, info, trace, warn};
 use serenity::all::{
-    ActivityData, Command, CommandDataOptionValue, CommandOptionType, GuildMemberUpdateEvent,
-    Interaction,
+    ActivityData, Command, CommandDataOption, CommandDataOptionValue, CommandInteraction,
+    CommandOptionType, CommandType, CreateAutocompleteResponse, CreateInteractionResponse,
+    GuildMemberUpdateEvent, Interaction,
 };
 use serenity::builder::{CreateCommand, CreateCommandOption};
 #[cfg(debug_assertions)]
@@ -22,6 +24,9 @@ use crate::command::notify_subscribers;
 #[cfg(feature = "events")]
 use crate::subsystems::events::Event;
 
+/// The maximum length of an autocomplete suggestion.
+const MAX_CHOICE_LENGTH: usize = 100;
+
 // guild to use for testing purposes.
 #[cfg(debug_assertions)]
 const DEBUG_GUILD_ID: &str = env!("LOKI_DEBUG_GUILD_ID");
@@ -29,6 +34,7 @@ const DEBUG_GUILD_ID: &str = env!("LOKI_DEBUG_GUILD_ID");
 /// Core implementation logic for [serenity] events.
 pub struct SerenityHandler<'a> {
     commands: Vec<crate::command::Command<'a>>,
+    audit_log: Option<AuditLog>,
 }
 
 #[async_trait]
@@ -41,6 +47,18 @@ impl EventHandler for SerenityHandler<'_> {
         // creates the global application commands
         self.create_commands(&ctx).await;
 
+        // Each shard is only told about its own guilds, so only a lone shard sees them all.
+        if ready.shard.is_none_or(|shard| shard.total == 1) {
+            let active_guilds: Vec<_> = ready.guilds.iter().map(|g| g.id).collect();
+            let mut data = crate::acquire_data_handle!(write ctx);
+            let pruned = data.prune_guilds(&active_guilds);
+            if pruned > 0 {
+                info!("Pruned config for {pruned} guild(s) which Loki is no longer in.");
+                data.save().await;
+            }
+            crate::drop_data_handle!(data);
+        }
+
         for s in subsystems() {
             s.ready(&ctx, &ready).await;
         }
@@ -50,7 +68,7 @@ impl EventHandler for SerenityHandler<'_> {
         info!("Guild Creation event for {} (new: {is_new:?})", g.id);
         trace!("Guild Creation data: {g:?}");
         let mut data = crate::acquire_data_handle!(write ctx);
-        let config = data.get_mut::<Config>().unwrap();
+        let config = &mut *data;
         let guild = config.guild_mut(&g.id);
         let started = guild.threads_started();
         guild.set_threads_started();
@@ -95,88 +113,100 @@ impl EventHandler for SerenityHandler<'_> {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         trace!("Handling Interaction: {:?}", interaction);
         if let Interaction::Command(mut command) = interaction {
-            for cmd in self.commands.iter() {
-                if cmd.name() == command.data.name {
-                    let mut cmd = cmd;
-                    let mut options = command.data.options.clone();
-                    if !command.data.options.is_empty()
-                        && matches!(
-                            command.data.options[0].kind(),
-                            CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup
-                        )
+            if let Some((cmd, options)) = self.find_command(&command) {
+                if let Some(guild_id) = command.guild_id {
+                    let name = command.data.name.clone();
+                    if !crate::with_guild!(ctx, &guild_id, |guild| guild.command_enabled(&name))
+                        .unwrap_or(true)
                     {
-                        // TODO: This is a little... unpleasant.
-                        // At some point it'd be good to refactor this to be recursive, like how we generate these group structures in the first place.
-                        for subcmd in cmd.variants() {
-                            if subcmd.name() == command.data.options[0].name {
-                                cmd = subcmd;
-                                if let CommandDataOptionValue::SubCommandGroup(os) =
-                                    &command.data.options[0].value
-                                {
-                                    options.clone_from(os);
-                                    for subcmd in cmd.variants() {
-                                        if subcmd.name() == os[0].name {
-                                            cmd = subcmd;
-                                            if let CommandDataOptionValue::SubCommand(os) =
-                                                &os[0].value
-                                            {
-                                                options.clone_from(os);
-                                            } else {
-                                                error!("Failed to extract subcommand options from {command:?}");
-                                            }
-                                            break;
-                                        }
-                                    }
-                                } else if let CommandDataOptionValue::SubCommand(os) =
-                                    &command.data.options[0].value
-                                {
-                                    options.clone_from(os);
-                                } else {
-                                    error!("Failed to extract subcommand options from {command:?}");
-                                }
-                                break;
-                            }
-                        }
-                    };
-                    match cmd.run(&ctx, &mut command, &options).await {
-                        Ok(e) => {
-                            if let Some(e) = e {
-                                let ephemeral = e.ephemeral();
-                                crate::command::create_response_from_embed(
-                                    &ctx.http,
-                                    &mut command,
-                                    e.embed(),
-                                    ephemeral,
-                                )
-                                .await;
-                            }
-                        }
-                        Err(e) => {
-                            error!("Error running '{}': {e:?}", cmd.name());
-                            #[cfg(feature = "events")]
-                            notify_subscribers(
-                                &ctx,
-                                Event::Error,
-                                &format!(
-                                    "**Error running '{}':**
-{e}",
-                                    cmd.name()
-                                ),
-                            )
-                            .await;
-                            crate::command::create_response(
+                        crate::command::create_response(
+                            &ctx.http,
+                            &mut command,
+                            &format!("`/{name}` is disabled in this server."),
+                            true,
+                        )
+                        .await;
+                        return;
+                    }
+                }
+                if let Err(remaining) = cmd.try_use(command.user.id) {
+                    crate::command::create_response(
+                        &ctx.http,
+                        &mut command,
+                        &format!(
+                            "**Slow down!** You can use this command again in {}s.",
+                            remaining.as_millis().div_ceil(1000)
+                        ),
+                        true,
+                    )
+                    .await;
+                    return;
+                }
+                let saves = config_saves(&ctx).await;
+                match cmd.run(&ctx, &mut command, &options).await {
+                    Ok(e) => {
+                        #[cfg(feature = "metrics")]
+                        crate::metrics::record_command(&command.data.name, "ok");
+                        self.audit(&command, true);
+                        if let Some(mut e) = e {
+                            let ephemeral = e.ephemeral();
+                            let attachments = e.take_attachments();
+                            crate::command::create_response_from_embed(
                                 &ctx.http,
                                 &mut command,
-                                &format!("{e}"),
-                                false,
+                                e.embed(),
+                                ephemeral,
+                                attachments,
                             )
                             .await;
                         }
+                        // Only commands which saved the config can have changed it.
+                        if let Some(guild_id) = command.guild_id {
+                            if config_saves(&ctx).await != saves {
+                                subsystems::config_changed(&ctx, &guild_id).await;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        #[cfg(feature = "metrics")]
+                        crate::metrics::record_command(&command.data.name, "error");
+                        self.audit(&command, false);
+                        error!("Error running '{}': {e:?}", cmd.name());
+                        #[cfg(feature = "events")]
+                        notify_subscribers(
+                            &ctx,
+                            Event::Error,
+                            &format!(
+                                "**Error running '{}':**
+{e}",
+                                cmd.name()
+                            ),
+                        )
+                        .await;
+                        crate::command::create_response(
+                            &ctx.http,
+                            &mut command,
+                            &format!("{e}"),
+                            false,
+                        )
+                        .await;
                     }
-                    break;
                 }
+                return;
             }
-        };
+            // No matching command; it's likely been registered by a previous run with different features enabled.
+            warn!("Received unknown command '{}'", command.data.name);
+            crate::command::create_response(
+                &ctx.http,
+                &mut command,
+                &"This command is no longer available. Commands may need to be re-registered."
+                    .to_string(),
+                true,
+            )
+            .await;
+        } else if let Interaction::Autocomplete(command) = interaction {
+            self.autocomplete(&ctx, command).await;
+        }
     }
 
     async fn message(&self, ctx: Context, message: Message) {
@@ -218,10 +248,76 @@ impl EventHandler for SerenityHandler<'_> {
     }
 }
 
+/// How many times the config has been saved (see [crate::config::Config::saves]).
+async fn config_saves(ctx: &Context) -> u64 {
+    let data = crate::acquire_data_handle!(read ctx);
+    let saves = data.saves();
+    crate::drop_data_handle!(data);
+    saves
+}
+
+/// Check that no two options (including variants) of `cmd`, or of any of its variants, share a
+/// name, as Discord rejects such commands.
+///
+/// Panics with the offending command and option name if a duplicate is found.
+fn validate_command_options(cmd: &crate::command::Command) {
+    let mut names = std::collections::HashSet::new();
+    let option_names = cmd.options().iter().map(|opt| opt.name());
+    let variant_names = cmd.variants().iter().map(|variant| variant.name());
+    for name in option_names.chain(variant_names) {
+        assert!(
+            names.insert(name),
+            "Duplicate option name '{name}'. (Command: {})",
+            cmd.name()
+        );
+    }
+    for variant in cmd.variants() {
+        validate_command_options(variant);
+    }
+}
+
+/// The full name of the invoked `command`, including any subcommand group and subcommand
+/// (e.g. `config export`).
+fn command_path(command: &CommandInteraction) -> String {
+    let mut path = command.data.name.clone();
+    let mut options = &command.data.options;
+    while let Some(CommandDataOption {
+        name,
+        value: CommandDataOptionValue::SubCommandGroup(os) | CommandDataOptionValue::SubCommand(os),
+        ..
+    }) = options.first()
+    {
+        path.push(' ');
+        path.push_str(name);
+        options = os;
+    }
+    path
+}
+
 pub fn construct_command(cmd: &crate::command::Command) -> CreateCommand {
+    assert!(
+        !cmd.stub(),
+        "Stub commands are routing-only and must never be submitted to Discord. (Command: {})",
+        cmd.name()
+    );
     let mut command = CreateCommand::new(cmd.name())
-        .description(cmd.description())
         .dm_permission(*cmd.permissions() == crate::command::PermissionType::Universal);
+    let kind = match cmd.kind() {
+        CommandKind::SlashCommand => CommandType::ChatInput,
+        CommandKind::UserContextMenu => CommandType::User,
+        CommandKind::MessageContextMenu => CommandType::Message,
+    };
+    if kind == CommandType::ChatInput {
+        validate_command_options(cmd);
+        command = command.description(cmd.description());
+    } else {
+        assert!(
+            cmd.description().is_empty() && cmd.options().is_empty() && cmd.variants().is_empty(),
+            "Context menu commands cannot have a description, options or variants. (Command: {})",
+            cmd.name()
+        );
+        command = command.kind(kind);
+    }
     if let crate::command::PermissionType::ServerPerms(permissions) = *cmd.permissions() {
         command = command.default_member_permissions(permissions);
     }
@@ -245,6 +341,7 @@ pub fn construct_option(opt: &crate::command::Option) -> CreateCommandOption {
             if let Some(max) = max {
                 option = option.clone().max_length(max);
             }
+            option = option.set_autocomplete(opt.autocomplete().is_some());
         }
         OptionType::StringSelect(options) => {
             options.iter().for_each(|s| {
@@ -287,7 +384,7 @@ pub fn construct_option(opt: &crate::command::Option) -> CreateCommandOption {
         }
         OptionType::Channel(types) => {
             if let Some(types) = types {
-                option = option.clone().channel_types(types);
+                option = option.clone().channel_types(types.to_vec());
             }
         }
         OptionType::Boolean
@@ -302,13 +399,123 @@ pub fn construct_option(opt: &crate::command::Option) -> CreateCommandOption {
 impl<'a> SerenityHandler<'a> {
     /// Construct a new handler from a populated config.
     pub fn new(commands: Vec<crate::command::Command<'a>>) -> Self {
-        Self { commands }
+        Self {
+            commands,
+            audit_log: AuditLog::from_env(),
+        }
+    }
+
+    /// Find the [crate::command::Command] (or variant) which `command` invokes, along with the
+    /// options passed to it.
+    fn find_command(
+        &self,
+        command: &CommandInteraction,
+    ) -> Option<(&crate::command::Command<'a>, Vec<CommandDataOption>)> {
+        let mut cmd = self
+            .commands
+            .iter()
+            .find(|cmd| cmd.name() == command.data.name)?;
+        let mut options = command.data.options.clone();
+        if !command.data.options.is_empty()
+            && matches!(
+                command.data.options[0].kind(),
+                CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup
+            )
+        {
+            // TODO: This is a little... unpleasant.
+            // At some point it'd be good to refactor this to be recursive, like how we generate these group structures in the first place.
+            for subcmd in cmd.variants() {
+                if subcmd.name() == command.data.options[0].name {
+                    cmd = subcmd;
+                    if let CommandDataOptionValue::SubCommandGroup(os) =
+                        &command.data.options[0].value
+                    {
+                        options.clone_from(os);
+                        for subcmd in cmd.variants() {
+                            if subcmd.name() == os[0].name {
+                                cmd = subcmd;
+                                if let CommandDataOptionValue::SubCommand(os) = &os[0].value {
+                                    options.clone_from(os);
+                                } else {
+                                    error!("Failed to extract subcommand options from {command:?}");
+                                }
+                                break;
+                            }
+                        }
+                    } else if let CommandDataOptionValue::SubCommand(os) =
+                        &command.data.options[0].value
+                    {
+                        options.clone_from(os);
+                    } else {
+                        error!("Failed to extract subcommand options from {command:?}");
+                    }
+                    break;
+                }
+            }
+        }
+        Some((cmd, options))
+    }
+
+    /// Respond to an autocomplete `interaction` with the suggestions from the focused option's
+    /// autocomplete routine (see [crate::command::Option::with_autocomplete]).
+    async fn autocomplete(&self, ctx: &Context, interaction: CommandInteraction) {
+        let Some(focused) = interaction.data.autocomplete() else {
+            return;
+        };
+        let Some(routine) = self
+            .find_command(&interaction)
+            .and_then(|(cmd, _)| cmd.options().iter().find(|opt| opt.name() == focused.name))
+            .and_then(|opt| opt.autocomplete())
+        else {
+            warn!(
+                "Received autocomplete for unknown option '{}' of '{}'",
+                focused.name,
+                command_path(&interaction)
+            );
+            return;
+        };
+        let response = routine(ctx, &interaction, focused.value)
+            .await
+            .into_iter()
+            // Discord rejects longer choices.
+            .filter(|s| s.chars().count() <= MAX_CHOICE_LENGTH)
+            .take(NUM_SELECTABLES)
+            .fold(CreateAutocompleteResponse::new(), |response, s| {
+                response.add_string_choice(s.clone(), s)
+            });
+        if let Err(e) = interaction
+            .create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response))
+            .await
+        {
+            warn!(
+                "Failed to respond to autocomplete for '{}': {e}",
+                command_path(&interaction)
+            );
+        }
+    }
+
+    /// Record the outcome of `command` in the audit log, if there is one.
+    fn audit(&self, command: &CommandInteraction, success: bool) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record(AuditEntry {
+                command: command_path(command),
+                user: command.user.id,
+                guild: command.guild_id,
+                timestamp: Utc::now().timestamp(),
+                success,
+            });
+        }
     }
 
     pub(crate) fn create_variant(
         variant: &crate::Command,
         allow_subcommands: bool,
     ) -> CreateCommandOption {
+        assert!(
+            !variant.stub(),
+            "Stub commands are routing-only and must never be submitted to Discord. (Command: {})",
+            variant.name()
+        );
         let mut subcmd = if allow_subcommands {
             if variant.variants().is_empty() {
                 CreateCommandOption::new(
@@ -378,3 +585,44 @@ impl<'a> SerenityHandler<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::construct_command;
+    use crate::command::{Command, CommandKind, Option, OptionType, PermissionType};
+
+    #[test]
+    #[should_panic(expected = "Duplicate option name 'user'. (Command: test)")]
+    fn duplicate_option_names_are_rejected() {
+        let command = Command::new(
+            "test",
+            "A test command.",
+            PermissionType::Universal,
+            None,
+            None,
+        )
+        .add_option(Option::new(
+            "user",
+            "The user.",
+            OptionType::StringInput(None, None),
+            true,
+        ))
+        .add_option(Option::new(
+            "user",
+            "The user, again.",
+            OptionType::StringInput(None, None),
+            false,
+        ));
+        let _ = construct_command(&command);
+    }
+
+    #[test]
+    fn context_menu_commands_have_their_kind() {
+        let command = Command::new("Test", "", PermissionType::Universal, None, None)
+            .with_kind(CommandKind::UserContextMenu);
+        let command = serde_json::to_value(construct_command(&command)).unwrap();
+        assert_eq!(command["type"], 2);
+        assert_eq!(command["name"], "Test");
+        assert_eq!(command.get("description"), None);
+    }
+}
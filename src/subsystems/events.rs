@@ -1,62 +1,130 @@
-use std::{fmt::Display, str::FromStr};
-
 use serde::{Deserialize, Serialize};
-use serenity::{async_trait, model::prelude::Ready, prelude::Context};
+use serenity::{
+    async_trait,
+    model::{id::UserId, prelude::Ready, Permissions},
+    prelude::{Context, Mentionable},
+};
+use strum::IntoEnumIterator;
 use tinyvec::ArrayVec;
 
 use crate::{
-    command::{notify_subscribers, Command, Option, OptionType, PermissionType},
-    config::Config,
+    command::{
+        notify_subscribers, require_permissions, ChannelTypes, Command, Option, OptionType,
+        PermissionType,
+    },
     create_raw_embed, ActionResponse, Error,
 };
 
 use super::Subsystem;
 
-const EVENTS: [Event; 3] = [Event::Startup, Event::Stream, Event::Error];
-
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
+/// An event which users may subscribe to be notified of.
+///
+/// [Event]s are used as map keys (e.g. for the subscriber lists in [crate::config::Config]),
+/// so every variant must remain hashable, totally ordered, and [Copy]; any future variant must
+/// not carry data which would prevent these from being derived.
+///
+/// The [std::fmt::Display] representation is what users see (and select from) in commands.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    strum::EnumIter,
+    strum::Display,
+    strum::EnumString,
+)]
 pub enum Event {
     Startup,
+    #[strum(serialize = "Streaming")]
     Stream,
     Error,
+    /// A user has been timed out.
+    Timeout,
+    /// The nickname lottery has changed a user's nickname.
+    #[strum(serialize = "Nickname Change")]
+    NicknameChange,
+    /// A weekly meme contest has been won.
+    #[strum(serialize = "Memes Winner")]
+    MemesWinner,
 }
 
-impl Display for Event {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Self::Startup => "Startup",
-                Self::Stream => "Streaming",
-                Self::Error => "Error",
-            }
-        )
-    }
+#[cfg(test)]
+static_assertions::assert_impl_all!(Event: std::hash::Hash, Eq, Ord, Copy);
+
+/// Restrictions on who may subscribe to an [Event].
+pub struct EventMeta {
+    /// Whether only the bot's manager may subscribe.
+    manager_only: bool,
+    /// The permission a subscriber must have in the server they subscribe from, if any.
+    required_permission: std::option::Option<PermissionType>,
 }
 
-impl FromStr for Event {
-    type Err = Error;
+impl EventMeta {
+    /// No restrictions; anyone may subscribe.
+    const UNRESTRICTED: Self = Self {
+        manager_only: false,
+        required_permission: None,
+    };
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some(event) = EVENTS.iter().find(|e| e.to_string() == s) {
-            Ok(*event)
-        } else {
-            Err(Error::InvalidEvent(format!(
-                "Unknown string representation of Event: {s}"
-            )))
+    /// Whether `user`, with the given `permissions` in the server they're subscribing from (or
+    /// [None] if they're not in one), may subscribe to the event.
+    pub fn may_subscribe(
+        &self,
+        user: UserId,
+        manager: UserId,
+        permissions: std::option::Option<Permissions>,
+    ) -> bool {
+        if self.manager_only && user != manager {
+            return false;
+        }
+        match &self.required_permission {
+            Some(PermissionType::ServerPerms(required)) => {
+                permissions.is_some_and(|permissions| permissions.contains(*required))
+            }
+            Some(PermissionType::Universal) | None => true,
         }
     }
 }
 
+/// Subscription restrictions for each [Event] which has any; unlisted events are unrestricted.
+pub const EVENT_META: [(Event, EventMeta); 2] = [
+    (
+        Event::Error,
+        EventMeta {
+            manager_only: true,
+            required_permission: None,
+        },
+    ),
+    (
+        Event::Stream,
+        EventMeta {
+            manager_only: false,
+            required_permission: Some(PermissionType::ServerPerms(Permissions::MANAGE_GUILD)),
+        },
+    ),
+];
+
+/// The subscription restrictions for `event`.
+pub fn event_meta(event: Event) -> &'static EventMeta {
+    EVENT_META
+        .iter()
+        .find(|(e, _)| *e == event)
+        .map_or(&EventMeta::UNRESTRICTED, |(_, meta)| meta)
+}
+
 pub struct Events;
 
 #[async_trait]
 impl Subsystem for Events {
     fn generate_commands(&self) -> Vec<Command<'static>> {
         let options = Box::new(
-            EVENTS
-                .iter()
+            Event::iter()
                 .map(|e| e.to_string())
                 .collect::<ArrayVec<[String; 25]>>(),
         );
@@ -66,22 +134,44 @@ impl Subsystem for Events {
             "Manage subscriptions to notifications for specific bot events.",
             PermissionType::Universal,
             None,
+            None,
         )
         .add_variant(
             Command::new(
                 "subscribe",
                 "Subscribe to a bot event. Some events may be restricted.",
                 PermissionType::Universal,
+                None,
                 Some(Box::new(move |ctx, command, params| {
                     Box::pin(async {
                         let event = get_param!(params, String, "event");
-                        let event = Event::from_str(event)?;
+                        let event = event.parse::<Event>().map_err(|_| {
+                            Error::InvalidEvent(format!(
+                                "Unknown string representation of Event: {event}"
+                            ))
+                        })?;
                         let mut data = crate::acquire_data_handle!(write ctx);
-                        let config = data.get_mut::<Config>().unwrap();
+                        let permissions = command
+                            .member
+                            .as_ref()
+                            .and_then(|member| member.permissions);
+                        if !event_meta(event).may_subscribe(
+                            command.user.id,
+                            data.get_manager(),
+                            permissions,
+                        ) {
+                            crate::drop_data_handle!(data);
+                            return Ok(Some(ActionResponse::new(
+                                create_raw_embed(
+                                    "Insufficient permissions to subscribe to this event.",
+                                ),
+                                true,
+                            )));
+                        }
+                        let config = &mut *data;
                         let subscribers = config.subscribers_mut(event);
-                        Ok(Some(if !subscribers.contains(&command.user.id) {
-                            subscribers.push(command.user.id);
-                            config.save();
+                        Ok(Some(if subscribers.insert(command.user.id) {
+                            config.save().await;
                             ActionResponse::new(
                                 create_raw_embed(format!("Successfully subscribed to {event}.")),
                                 true,
@@ -107,16 +197,19 @@ impl Subsystem for Events {
                 "unsubscribe",
                 "Unsubscribe from a bot event.",
                 PermissionType::Universal,
+                None,
                 Some(Box::new(move |ctx, command, params| {
                     Box::pin(async {
                         let event = get_param!(params, String, "event");
-                        let event = Event::from_str(event)?;
+                        let event = event.parse::<Event>().map_err(|_| {
+                            Error::InvalidEvent(format!(
+                                "Unknown string representation of Event: {event}"
+                            ))
+                        })?;
                         let mut data = crate::acquire_data_handle!(write ctx);
-                        let config = data.get_mut::<Config>().unwrap();
-                        let subscribers = config.subscribers_mut(event);
-                        Ok(Some(if subscribers.contains(&command.user.id) {
-                            subscribers.retain(|u| *u != command.user.id);
-                            config.save();
+                        let config = &mut *data;
+                        Ok(Some(if config.remove_subscriber(event, command.user.id) {
+                            config.save().await;
                             ActionResponse::new(
                                 create_raw_embed(format!(
                                     "Successfully unsubscribed from {event}."
@@ -138,7 +231,71 @@ impl Subsystem for Events {
                 OptionType::StringSelect(options),
                 true,
             )),
-        )]
+        )
+        .add_variant(
+            Command::new(
+                "set_channel",
+                "Also post this server's events to a channel in it.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_CHANNELS)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let channel = *get_param!(params, Channel, "channel");
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        config
+                            .guild_mut(&command.guild_id.unwrap())
+                            .set_events_channel(Some(channel));
+                        config.save().await;
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(
+                            create_raw_embed(format!(
+                                "Events will also be posted to {}.",
+                                channel.mention()
+                            )),
+                            true,
+                        )))
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "channel",
+                "The channel which events are to be posted to.",
+                OptionType::Channel(Some(ChannelTypes::TEXT)),
+                true,
+            )),
+        )
+        .add_variant(Command::new(
+            "unset_channel",
+            "Stop posting events to this server's events channel.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    config
+                        .guild_mut(&command.guild_id.unwrap())
+                        .set_events_channel(None);
+                    config.save().await;
+                    crate::drop_data_handle!(data);
+                    Ok(Some(ActionResponse::new(
+                        create_raw_embed("Events channel unset."),
+                        true,
+                    )))
+                })
+            })),
+        ))]
     }
 
     async fn ready(&self, ctx: &Context, _ready: &Ready) {
@@ -157,3 +314,37 @@ I'm starting up with version [{}]({}/releases/tag/v{}). 😁",
         .await;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serenity::model::{id::UserId, Permissions};
+    use strum::IntoEnumIterator;
+
+    use super::{event_meta, Event};
+
+    #[test]
+    fn string_representation_round_trips() {
+        for event in Event::iter() {
+            assert_eq!(event.to_string().parse::<Event>().unwrap(), event);
+        }
+        assert_eq!(Event::Stream.to_string(), "Streaming");
+        assert!("Stream".parse::<Event>().is_err());
+        assert_eq!(Event::NicknameChange.to_string(), "Nickname Change");
+        assert_eq!(Event::MemesWinner.to_string(), "Memes Winner");
+    }
+
+    #[test]
+    fn subscription_permissions() {
+        let (manager, user) = (UserId::new(1), UserId::new(2));
+        let error = event_meta(Event::Error);
+        assert!(error.may_subscribe(manager, manager, None));
+        assert!(!error.may_subscribe(user, manager, Some(Permissions::all())));
+
+        let stream = event_meta(Event::Stream);
+        assert!(stream.may_subscribe(user, manager, Some(Permissions::MANAGE_GUILD)));
+        assert!(!stream.may_subscribe(user, manager, Some(Permissions::SEND_MESSAGES)));
+        assert!(!stream.may_subscribe(user, manager, None));
+
+        assert!(event_meta(Event::Startup).may_subscribe(user, manager, None));
+    }
+}
@@ -1,13 +1,13 @@
 use std::{collections::HashMap, time::Duration};
 
-use chrono::{Days, Utc};
+use chrono::{DateTime, Days, Utc};
 use log::{debug, error, info, trace, warn};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serenity::{
     all::{
-        ChannelId, ChannelType, CreateEmbed, CreateMessage, EditMessage, GetMessages, Guild,
-        Message, MessageFlags, MessageId,
+        ChannelId, CommandDataOptionValue, CreateEmbed, CreateEmbedFooter, EditMessage, EmojiId,
+        Guild, GuildId, Message, MessageFlags, MessageId, ReactionType, Timestamp,
     },
     async_trait, futures,
     model::{id::UserId, Permissions},
@@ -15,21 +15,32 @@ use serenity::{
 };
 
 use crate::{
-    command::{create_embed, Command, PermissionType},
-    config::get_memes,
-    create_raw_embed, ActionResponse, Error,
+    backend::{Backend, SerenityBackend},
+    command::{
+        confirm_action, create_embed, require_permissions, ChannelTypes, Command, PermissionType,
+    },
+    config::{get_memes, validate_id_keys},
+    create_embed_fields, create_raw_embed, ActionResponse, Error,
 };
 use crate::{
-    command::{notify_subscribers, OptionType},
-    config::Config,
+    command::{notify_subscribers, notify_subscribers_in, OptionType},
     subsystems::events::Event,
 };
 
 use super::Subsystem;
 
 const REACTION_CHANCE: f64 = 0.1;
+/// The number of entries shown by `/memes standings`.
+const STANDINGS_SIZE: usize = 10;
 const REACTION_EMOTE: char = '🤖';
 const NO_MEMES_GIF: &str = "https://media.tenor.com/ve60xH3hKrcAAAAC/no.gif";
+/// The length of each contest, in days, unless a guild has chosen otherwise.
+const DEFAULT_RESET_PERIOD_DAYS: u32 = 7;
+/// The shortest and longest contests a guild may choose, in days.
+const MIN_RESET_PERIOD_DAYS: u32 = 1;
+const MAX_RESET_PERIOD_DAYS: u32 = 28;
+/// The longest submission cooldown a guild may choose, in minutes (one week).
+const MAX_SUBMISSION_COOLDOWN_MINS: i64 = 7 * 24 * 60;
 
 pub struct MemesVoting;
 
@@ -41,14 +52,21 @@ impl Subsystem for MemesVoting {
             "Commands for the meme-voting system.",
             PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
             None,
+            None,
         )
         .add_variant(
             Command::new(
                 "set_channel",
                 "Sets the memes channel for this server and initialises the meme subsystem.",
                 PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
                 Some(Box::new(move |ctx, command, params| {
                     Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_CHANNELS)
+                        {
+                            return Ok(Some(refusal));
+                        }
                         let channel_id = *get_param!(params, Channel, "channel");
                         let channel =
                             if let Some(channel) = channel_id.to_channel(&ctx).await?.guild() {
@@ -65,11 +83,11 @@ impl Subsystem for MemesVoting {
                             )
                             .await?;
                         let mut data = crate::acquire_data_handle!(write ctx);
-                        let config = data.get_mut::<Config>().unwrap();
+                        let config = &mut *data;
                         let guild_config = config.guild_mut(&command.guild_id.unwrap());
                         guild_config.set_memes_channel(Some((channel_id, initial_message.id)));
                         let reset_time = guild_config.memes().unwrap().next_reset();
-                        config.save();
+                        config.save().await;
                         crate::drop_data_handle!(data);
                         let resp = format!("Memes channel set to {}.", channel);
                         initial_message
@@ -90,7 +108,7 @@ The post with the most total reactions by <t:{}:F> wins!",
             .add_option(crate::command::Option::new(
                 "channel",
                 "The channel which is to be used for memes.",
-                OptionType::Channel(Some(vec![ChannelType::Text])),
+                OptionType::Channel(Some(ChannelTypes::TEXT)),
                 true,
             )),
         )
@@ -98,10 +116,27 @@ The post with the most total reactions by <t:{}:F> wins!",
             "unset_channel",
             "Unsets the memes channel for this server, resetting the meme subsystem.",
             PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
             Some(Box::new(move |ctx, command, _params| {
                 Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    if !confirm_action(
+                        ctx,
+                        command,
+                        "**Unset the memes channel?**
+This resets the meme subsystem for this server.",
+                        true,
+                    )
+                    .await
+                    {
+                        return Ok(None);
+                    }
                     let mut data = crate::acquire_data_handle!(write ctx);
-                    let config = data.get_mut::<Config>().unwrap();
+                    let config = &mut *data;
                     let channel = config
                         .guild_mut(&command.guild_id.unwrap())
                         .memes()
@@ -109,7 +144,7 @@ The post with the most total reactions by <t:{}:F> wins!",
                     config
                         .guild_mut(&command.guild_id.unwrap())
                         .set_memes_channel(None);
-                    config.save();
+                    config.save().await;
                     crate::drop_data_handle!(data);
                     let resp = "Memes channel unset.".to_string();
                     if let Some(channel) = channel {
@@ -130,47 +165,363 @@ I won't see them anymore. :("
                 })
             })),
         ))
+        .add_variant(
+            Command::new(
+                "set_period",
+                "Sets how many days each meme contest lasts for in this server.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_CHANNELS)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let days = *get_param!(params, Integer, "days");
+                        let days = match u32::try_from(days) {
+                            Ok(days)
+                                if (MIN_RESET_PERIOD_DAYS..=MAX_RESET_PERIOD_DAYS)
+                                    .contains(&days) =>
+                            {
+                                days
+                            }
+                            _ => {
+                                return Ok(Some(ActionResponse::new(
+                                    create_raw_embed(format!(
+                                        "**Invalid period:** contests must last between \
+{MIN_RESET_PERIOD_DAYS} and {MAX_RESET_PERIOD_DAYS} days."
+                                    )),
+                                    true,
+                                )))
+                            }
+                        };
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let resp = if let Some(memes) =
+                            config.guild_mut(&command.guild_id.unwrap()).memes_mut()
+                        {
+                            memes.set_reset_period_days(days);
+                            config.save().await;
+                            format!(
+                                "Meme contests will now last {days} day(s), from the next contest."
+                            )
+                        } else {
+                            "You must set a memes channel first!".to_string()
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(crate::command::Option::new(
+                "days",
+                "How many days each contest lasts for.",
+                OptionType::IntegerInput(
+                    Some(MIN_RESET_PERIOD_DAYS.into()),
+                    Some(MAX_RESET_PERIOD_DAYS.into()),
+                ),
+                true,
+            )),
+        )
         .add_variant(Command::new(
-            "leaderboard",
-            "Display the leaderboard for meme voting victories.",
-            PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+            "standings",
+            "Display the entries with the most votes in the current meme contest.",
+            PermissionType::Universal,
+            None,
             Some(Box::new(move |ctx, command, _params| {
                 Box::pin(async move {
-                    let mut users = String::new();
-                    let mut counts = String::new();
+                    let guild_id = command.guild_id.unwrap();
                     let data = crate::acquire_data_handle!(read ctx);
-                    if let Some(memes) = get_memes(&data, &command.guild_id.unwrap()) {
-                        let mut entries = memes
-                            .victors()
-                            .iter()
-                            .map(|(uid, count)| (uid.clone(), *count))
-                            .collect::<Vec<(String, u32)>>();
-                        entries.sort_unstable_by(|(_, cnt_a), (_, cnt_b)| cnt_b.cmp(cnt_a));
-                        let iter = entries.iter().take(10);
-                        users = futures::future::try_join_all(iter.clone().map(|(uid, _)| async {
-                            Ok::<String, crate::Error>(
-                                UserId::from(uid.parse::<u64>().unwrap())
-                                    .to_user(&ctx)
-                                    .await?
-                                    .mention()
-                                    .to_string(),
-                            )
-                        }))
-                        .await?
-                        .join("\n");
-                        counts = iter
-                            .clone()
-                            .map(|(_, cnt)| cnt.to_string())
-                            .collect::<Vec<String>>()
-                            .join("\n");
+                    let memes = get_memes(&data, &guild_id).cloned();
+                    crate::drop_data_handle!(data);
+                    let Some(memes) = memes else {
+                        return Ok(Some(ActionResponse::new(
+                            create_raw_embed("There's no meme contest running in this server."),
+                            true,
+                        )));
+                    };
+                    let mut entries =
+                        Self::get_messages(ctx, &SerenityBackend::from(ctx), &guild_id).await?;
+                    memes.discard_uncounted_reactions(&mut entries);
+                    Ok(Some(ActionResponse::new(
+                        Self::standings_embed(entries),
+                        false,
+                    )))
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "set_reaction",
+                "Only count the given reaction (alongside any others set) as a vote.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_CHANNELS)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let emoji = get_param!(params, String, "emoji");
+                        let Some(reaction) = parse_reaction(emoji) else {
+                            return Ok(Some(ActionResponse::new(
+                                create_raw_embed(format!(
+                                    "**`{emoji}` isn't an emoji I recognise.**
+Use a single emoji (e.g. 👍), a custom emoji from this server, or a custom emoji's ID."
+                                )),
+                                true,
+                            )));
+                        };
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let resp = match config.guild_mut(&command.guild_id.unwrap()).memes_mut() {
+                            Some(memes) => {
+                                let resp = if memes.allow_reaction(reaction.clone()) {
+                                    format!("{reaction} reactions now count as votes.")
+                                } else {
+                                    format!("{reaction} reactions already count as votes.")
+                                };
+                                let allowed = memes
+                                    .allowed_reactions()
+                                    .iter()
+                                    .map(ReactionType::to_string)
+                                    .collect::<Vec<_>>()
+                                    .join(" ");
+                                config.save().await;
+                                format!("{resp}\nOnly these reactions are counted: {allowed}")
+                            }
+                            None => "You must set a memes channel first!".to_string(),
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(crate::command::Option::new(
+                "emoji",
+                "The emoji to count as a vote.",
+                OptionType::StringInput(Some(1), Some(100)),
+                true,
+            )),
+        )
+        .add_variant(Command::new(
+            "clear_reactions",
+            "Count every reaction as a vote again.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
                     }
-                    let resp = create_raw_embed("**Top 10 Memesters**".to_string())
-                        .field("User", users, true)
-                        .field("Victories", counts, true);
-                    Ok(Some(ActionResponse::new(resp, false)))
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    let resp = match config.guild_mut(&command.guild_id.unwrap()).memes_mut() {
+                        Some(memes) => {
+                            memes.clear_allowed_reactions();
+                            config.save().await;
+                            "Every reaction now counts as a vote."
+                        }
+                        None => "You must set a memes channel first!",
+                    };
+                    crate::drop_data_handle!(data);
+                    Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
                 })
             })),
-        ))]
+        ))
+        .add_variant(Command::new(
+            "force_reset",
+            "End the current meme contest now, declaring a winner and starting the next.",
+            PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    if let Some(refusal) = require_permissions(command, Permissions::ADMINISTRATOR)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let guild_id = command.guild_id.unwrap();
+                    let data = crate::acquire_data_handle!(read ctx);
+                    let running = get_memes(&data, &guild_id).is_some();
+                    crate::drop_data_handle!(data);
+                    let guild = ctx.cache.guild(guild_id).map(|guild| guild.clone());
+                    let Some(guild) = guild.filter(|_| running) else {
+                        return Ok(Some(ActionResponse::new(
+                            create_raw_embed("There's no meme contest running in this server."),
+                            true,
+                        )));
+                    };
+                    if !confirm_action(
+                        ctx,
+                        command,
+                        "**End the meme contest now?**
+The current contest will be tallied and its winner declared, and a new contest will start.",
+                        true,
+                    )
+                    .await
+                    {
+                        return Ok(None);
+                    }
+                    info!("[Guild: {guild_id}] Forcing a memes reset.");
+                    let ctx = ctx.clone();
+                    tokio::spawn(async move {
+                        let backend = SerenityBackend::from(&ctx);
+                        if let Err(e) = Self::process_memes(&ctx, &backend, &guild).await {
+                            error!("[Guild: {}] Error forcing a memes reset: {e:?}", guild.id);
+                        }
+                    });
+                    Ok(Some(ActionResponse::new(
+                        create_raw_embed("Reset triggered."),
+                        true,
+                    )))
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "set_submission_cooldown",
+                "Set how long each user must wait between submitting memes.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_CHANNELS)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let mins = *get_param!(params, Integer, "minutes");
+                        let Ok(mins) = u32::try_from(mins) else {
+                            return Err(Error::InvalidParam("minutes".to_string()));
+                        };
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let resp = match config.guild_mut(&command.guild_id.unwrap()).memes_mut() {
+                            Some(memes) => {
+                                memes.set_submission_cooldown_mins(mins);
+                                config.save().await;
+                                format!(
+                                    "Users must now wait {mins} minute(s) between submitting memes."
+                                )
+                            }
+                            None => "You must set a memes channel first!".to_string(),
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(crate::command::Option::new(
+                "minutes",
+                "How many minutes each user must wait between memes.",
+                OptionType::IntegerInput(Some(1), Some(MAX_SUBMISSION_COOLDOWN_MINS)),
+                true,
+            )),
+        )
+        .add_variant(Command::new(
+            "clear_submission_cooldown",
+            "Let users submit memes as often as they like.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    let resp = match config.guild_mut(&command.guild_id.unwrap()).memes_mut() {
+                        Some(memes) => {
+                            memes.set_submission_cooldown_mins(0);
+                            config.save().await;
+                            "Users may now submit memes as often as they like."
+                        }
+                        None => "You must set a memes channel first!",
+                    };
+                    crate::drop_data_handle!(data);
+                    Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "leaderboard",
+                "Display the leaderboard for meme voting victories.",
+                PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let show_ids = params
+                            .iter()
+                            .find(|opt| opt.name == "show_ids")
+                            .map(|opt| matches!(opt.value, CommandDataOptionValue::Boolean(true)))
+                            .unwrap_or(false);
+                        let data = crate::acquire_data_handle!(read ctx);
+                        let victors = get_memes(&data, &command.guild_id.unwrap())
+                            .map(|memes| memes.victors().clone())
+                            .unwrap_or_default();
+                        crate::drop_data_handle!(data);
+                        let (users, counts) =
+                            Self::top_victors(&SerenityBackend::from(ctx), victors, show_ids).await;
+                        let resp = create_embed_fields(
+                            "**Top 10 Memesters**",
+                            &[("User", users, true), ("Victories", counts, true)],
+                        );
+                        Ok(Some(ActionResponse::new(resp, false)))
+                    })
+                })),
+            )
+            .add_option(show_ids_option()),
+        )
+        .add_variant(
+            Command::new(
+                "hall_of_fame",
+                "Display the all-time top meme voting victors, and the best meme ever.",
+                PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let show_ids = params
+                            .iter()
+                            .find(|opt| opt.name == "show_ids")
+                            .map(|opt| matches!(opt.value, CommandDataOptionValue::Boolean(true)))
+                            .unwrap_or(false);
+                        let guild_id = command.guild_id.unwrap();
+                        let data = crate::acquire_data_handle!(read ctx);
+                        let (victors, best_ever) = get_memes(&data, &guild_id)
+                            .map(|memes| (memes.victors().clone(), memes.best_ever()))
+                            .unwrap_or_default();
+                        crate::drop_data_handle!(data);
+                        let (users, counts) =
+                            Self::top_victors(&SerenityBackend::from(ctx), victors, show_ids).await;
+                        let best_ever = match best_ever {
+                            Some((message, channel, author, votes)) => format!(
+                                "[This meme]({}) by {}, with {votes} votes.",
+                                message.link(channel, Some(guild_id)),
+                                author.mention()
+                            ),
+                            None => "No contest has been won yet.".to_string(),
+                        };
+                        let resp = create_embed_fields(
+                            "**Hall of Fame**",
+                            &[
+                                ("User", users, true),
+                                ("Victories", counts, true),
+                                ("Best Meme Ever", best_ever, false),
+                            ],
+                        );
+                        Ok(Some(ActionResponse::new(resp, false)))
+                    })
+                })),
+            )
+            .add_option(show_ids_option()),
+        )]
     }
 
     async fn message(&self, ctx: &Context, message: &Message) {
@@ -181,17 +532,28 @@ I won't see them anymore. :("
         }
         if let Some(guild) = message.guild_id {
             let mut data = crate::acquire_data_handle!(write ctx);
-            let config = data.get_mut::<Config>().unwrap();
+            let config = &mut *data;
             let guild = config.guild_mut(&guild);
             if let Some(memes) = guild.memes_mut() {
-                if message.channel_id == memes.channel() && !message.is_own(&ctx.cache) {
+                if message.channel_id == memes.channel()
+                    && message.author.id != ctx.cache.current_user().id
+                {
+                    let backend = SerenityBackend::from(ctx);
+                    if let Err(until) = memes.try_submit(message.author.id, Utc::now()) {
+                        crate::drop_data_handle!(data);
+                        Self::reject_submission(&backend, message, until).await;
+                        return;
+                    }
                     if !memes.has_reacted()
                         && rand::thread_rng().gen_bool(REACTION_CHANCE)
-                        && message.react(&ctx, REACTION_EMOTE).await.is_ok()
+                        && backend
+                            .add_reaction(message.channel_id, message.id, REACTION_EMOTE.into())
+                            .await
+                            .is_ok()
                     {
                         memes.reacted();
                     }
-                    config.save()
+                    config.save().await;
                 }
             }
             crate::drop_data_handle!(data);
@@ -199,63 +561,248 @@ I won't see them anymore. :("
     }
 }
 
+/// The `show_ids` option for commands which show users who may no longer be resolvable.
+fn show_ids_option() -> crate::command::Option<'static> {
+    crate::command::Option::new(
+        "show_ids",
+        "Show the raw user ID next to any users who could not be found.",
+        OptionType::Boolean,
+        false,
+    )
+}
+
 impl MemesVoting {
-    pub async fn get_messages(ctx: &Context, g: &Guild) -> Result<Vec<Message>, Error> {
+    /// Remove a `message` submitted too soon after its author's last, and tell them when they
+    /// may next submit one.
+    async fn reject_submission(backend: &dyn Backend, message: &Message, until: DateTime<Utc>) {
+        info!(
+            "Removing meme {} by {} ({}), who's on cooldown until {until}",
+            message.id, message.author.name, message.author.id
+        );
+        if let Err(e) = backend.delete_message(message.channel_id, message.id).await {
+            warn!("Unable to remove meme {} on cooldown: {e:?}", message.id);
+            return;
+        }
+        if let Err(e) = backend
+            .send_direct_message(
+                message.author.id,
+                format!(
+                    "**Slow down!**
+Your meme in {} was removed, as you've submitted one too recently. You can submit another <t:{}:R>.",
+                    message.channel_id.mention(),
+                    until.timestamp()
+                ),
+            )
+            .await
+        {
+            warn!(
+                "Unable to tell {} ({}) about their meme on cooldown: {e:?}",
+                message.author.name, message.author.id
+            );
+        }
+    }
+
+    /// The mentions and victory counts, one per line, of the 10 users with the most `victors`.
+    async fn top_victors(
+        backend: &dyn Backend,
+        victors: HashMap<String, u32>,
+        show_ids: bool,
+    ) -> (String, String) {
+        let mut entries = victors
+            .into_iter()
+            .filter_map(|(uid, count)| match uid.parse::<u64>() {
+                Ok(id) if id != 0 => Some((UserId::new(id), count)),
+                _ => {
+                    warn!("Ignoring memes victor with invalid user ID {uid:?} (victories: {count}); the config may be corrupted.");
+                    None
+                }
+            })
+            .collect::<Vec<(UserId, u32)>>();
+        entries.sort_unstable_by(|(_, cnt_a), (_, cnt_b)| cnt_b.cmp(cnt_a));
+        let iter = entries.iter().take(10);
+        let users = futures::future::join_all(iter.clone().map(|(uid, _)| async move {
+            // Users who have since left (or been deleted) can't be resolved;
+            // don't let them break the whole leaderboard.
+            match backend.get_user(*uid).await {
+                Ok(user) => user.mention().to_string(),
+                Err(e) => {
+                    warn!("User {uid} could not be resolved: {e:?}");
+                    if show_ids {
+                        format!("[Unknown User] (`{uid}`)")
+                    } else {
+                        "[Unknown User]".to_string()
+                    }
+                }
+            }
+        }))
+        .await
+        .join("\n");
+        let counts = iter
+            .map(|(_, cnt)| cnt.to_string())
+            .collect::<Vec<String>>()
+            .join("\n");
+        (users, counts)
+    }
+
+    pub async fn get_messages(
+        ctx: &Context,
+        backend: &dyn Backend,
+        g: &GuildId,
+    ) -> Result<Vec<Message>, Error> {
         // Retrieve all meme messages for the week
         let data = crate::acquire_data_handle!(read ctx);
         let mut message_list = Vec::new();
-        if let Some(memes) = get_memes(&data, &g.id) {
+        if let Some(memes) = get_memes(&data, g) {
             let channel = memes.channel();
             let initial_message = *memes.initial_message();
             crate::drop_data_handle!(data);
 
-            message_list.push(channel.message(&ctx, initial_message).await?);
-            let mut finished = true;
-            loop {
-                if let Some(last_message) = message_list.last() {
-                    let mut messages: Vec<Message> = channel
-                        .messages(&ctx, GetMessages::default().after(last_message).limit(100))
-                        .await?;
-                    // The returned messages are **most recent first**.
-                    // It's easier for us the other way around, so reverse it.
-                    messages.reverse();
-                    finished = messages.is_empty();
-                    message_list.append(&mut messages);
-                };
-                if finished {
-                    break;
-                }
-                trace!(
-                    "Entries: {} (last: {:?})",
-                    message_list.len(),
-                    message_list.last().map(|m| m.id)
-                );
+            // Only messages after the initial message are fetched, so it's never an entry, even if
+            // the cache doesn't know it's our own yet (e.g. just after a restart).
+            message_list = Self::fetch_messages_after(backend, channel, initial_message).await?;
+        }
+        let own_id = ctx.cache.current_user().id;
+        message_list.retain(|m| m.author.id != own_id);
+        Ok(message_list)
+    }
+
+    /// Get every message in `channel` sent after the message `after`, **oldest first**.
+    async fn fetch_messages_after(
+        backend: &dyn Backend,
+        channel: ChannelId,
+        after: MessageId,
+    ) -> Result<Vec<Message>, Error> {
+        let mut message_list: Vec<Message> = Vec::new();
+        let mut after = after;
+        loop {
+            let mut messages = backend.get_messages(channel, after, 100).await?;
+            if messages.is_empty() {
+                break;
             }
+            // The returned messages are **most recent first**.
+            // It's easier for us the other way around, so reverse it.
+            messages.reverse();
+            after = messages.last().unwrap().id;
+            message_list.append(&mut messages);
+            trace!("Entries: {} (last: {:?})", message_list.len(), after);
         }
-        message_list.retain(|m| !m.is_own(&ctx.cache));
         Ok(message_list)
     }
 
-    pub async fn process_memes(ctx: &Context, g: &Guild) -> Result<(), Error> {
+    /// Sort `entries` by their number of votes (reactions), most first.
+    ///
+    /// Returns the number of votes for the winning entry (now first), if any entry received a vote.
+    fn rank_entries(entries: &mut [Message]) -> Option<u64> {
+        // Unstable sorting means that if two memes have the same number of votes, then it is not generally predictable which meme will win (it is not 'first one wins').
+        // However, order of votes should be accurate nonetheless.
+        entries.sort_unstable_by_key(|m| std::cmp::Reverse(Self::votes(m)));
+        entries.first().map(Self::votes).filter(|votes| *votes > 0)
+    }
+
+    /// Rank the contest's `entries` as they'll be judged under the `counted` configuration: only
+    /// allowed reactions count as votes, and self-votes are discounted.
+    ///
+    /// Returns the number of votes for the winning entry (now first), if any entry received a vote.
+    async fn select_winner(
+        backend: &dyn Backend,
+        counted: &Memes,
+        entries: &mut [Message],
+    ) -> Option<u64> {
+        counted.discard_uncounted_reactions(entries);
+        Self::discount_self_votes(backend, entries).await;
+        Self::rank_entries(entries)
+    }
+
+    /// Stop counting each entry's author's reactions to it as votes, as people can't vote for
+    /// their own memes.
+    async fn discount_self_votes(backend: &dyn Backend, entries: &mut [Message]) {
+        for entry in entries {
+            let author = entry.author.id;
+            debug!("Checking entry {} by {author} for self-votes", entry.id);
+            for reaction in &mut entry.reactions {
+                match backend
+                    .has_reacted(entry.channel_id, entry.id, &reaction.reaction_type, author)
+                    .await
+                {
+                    Ok(true) => reaction.count = reaction.count.saturating_sub(1),
+                    Ok(false) => (),
+                    // Count the vote rather than fail the contest (e.g. if we're rate limited).
+                    Err(Error::SerenityError(serenity::Error::Http(e))) => warn!(
+                        "Unable to check for self-votes on entry {} by {author}: {e}",
+                        entry.id
+                    ),
+                    Err(e) => error!(
+                        "Error checking for self-votes on entry {} by {author}: {e}",
+                        entry.id
+                    ),
+                }
+            }
+        }
+    }
+
+    /// The number of votes (reactions) for an entry.
+    fn votes(entry: &Message) -> u64 {
+        entry.reactions.iter().map(|r| r.count).sum()
+    }
+
+    /// The current standings of the contest, given its `entries`: the top [STANDINGS_SIZE] entries
+    /// by number of votes, ranked.
+    fn standings_embed(mut entries: Vec<Message>) -> CreateEmbed {
+        if entries.is_empty() {
+            return create_raw_embed(
+                "**No memes posted yet!**
+Be the first to enter this contest.",
+            );
+        }
+        Self::rank_entries(&mut entries);
+        let top = &entries[..entries.len().min(STANDINGS_SIZE)];
+        let ranks = (1..=top.len())
+            .map(|rank| format!("#{rank}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let memes = top
+            .iter()
+            .map(|m| format!("{} ([entry]({}))", m.author.mention(), m.link()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let votes = top
+            .iter()
+            .map(|m| Self::votes(m).to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        create_embed_fields(
+            "**Current Standings**",
+            &[
+                ("Rank", ranks, true),
+                ("Meme", memes, true),
+                ("Votes", votes, true),
+            ],
+        )
+        .footer(CreateEmbedFooter::new("Last updated"))
+        .timestamp(Timestamp::now())
+    }
+
+    pub async fn process_memes(
+        ctx: &Context,
+        backend: &dyn Backend,
+        g: &Guild,
+    ) -> Result<(), Error> {
         let time = Utc::now();
-        let mut meme_list = Self::get_messages(ctx, g).await?;
+        let mut meme_list = Self::get_messages(ctx, backend, &g.id).await?;
         let mut data = crate::acquire_data_handle!(write ctx);
-        let config = data.get_mut::<Config>().unwrap();
+        let config = &mut *data;
         let guild = config.guild_mut(&g.id);
         if let Some(memes) = guild.memes_mut() {
-            let channel = memes.channel().to_channel(&ctx).await?;
-            let channel = channel.guild().unwrap();
+            let channel = memes.channel();
             let reacted = memes.has_reacted();
+            let counted = memes.clone();
             crate::drop_data_handle!(data);
             info!("[Guild: {}] Processing {} entries.", &g.id, meme_list.len());
             debug!("[Guild: {}] Entries: {:?}", &g.id, meme_list);
-            let mut initial_message = channel
+            let initial_message = backend
                 .send_message(
-                    &ctx,
-                    crate::command::create_embed(format!(
-                        "Processing {} results...",
-                        meme_list.len(),
-                    )),
+                    channel,
+                    format!("Processing {} results...", meme_list.len()),
                 )
                 .await?;
             if !reacted && !meme_list.is_empty() {
@@ -265,7 +812,10 @@ impl MemesVoting {
                     &g.id,
                     meme_list.get(i)
                 );
-                if let Err(e) = meme_list.get(i).unwrap().react(&ctx, REACTION_EMOTE).await {
+                if let Err(e) = backend
+                    .add_reaction(channel, meme_list[i].id, REACTION_EMOTE.into())
+                    .await
+                {
                     error!(
                         "[Guild: {}] Error reacting to random meme #{i} ({:?}): {e:?}",
                         &g.id,
@@ -282,52 +832,65 @@ impl MemesVoting {
                     .await;
                 } else {
                     let mut data = crate::acquire_data_handle!(write ctx);
-                    let config = data.get_mut::<Config>().unwrap();
+                    let config = &mut *data;
                     let guild = config.guild_mut(&g.id);
                     let memes = guild.memes_mut().unwrap();
                     memes.reacted();
-                    config.save();
+                    config.save().await;
                     crate::drop_data_handle!(data);
-                    meme_list = Self::get_messages(ctx, g).await?;
+                    meme_list = Self::get_messages(ctx, backend, &g.id).await?;
                 }
             }
+            let most_reactions = Self::select_winner(backend, &counted, &mut meme_list).await;
             let mut data = crate::acquire_data_handle!(write ctx);
-            let config = data.get_mut::<Config>().unwrap();
+            let config = &mut *data;
             let guild = config.guild_mut(&g.id);
             let memes = guild.memes_mut().unwrap();
-            memes.reset(time, initial_message.id);
+            memes.reset(time, initial_message);
             let next_reset = memes.next_reset().timestamp();
             crate::drop_data_handle!(data);
             let new_text = if !meme_list.is_empty() {
-                // Reverse sort the meme list by number of votes.
-                // Unstable sorting means that if two memes have the same number of votes, then it is not generally predictable which meme will win (it is not 'first one wins').
-                // However, order of votes should be accurate nonetheless.
-                meme_list.sort_unstable_by(|a, b| {
-                    b.reactions
-                        .iter()
-                        .map(|m| m.count)
-                        .sum::<u64>()
-                        .cmp(&a.reactions.iter().map(|m| m.count).sum::<u64>())
-                });
-                let victor = meme_list.first().unwrap();
-                let most_reactions: u64 = victor.reactions.iter().map(|m| m.count).sum();
-                if most_reactions > 0 {
+                if let Some(most_reactions) = most_reactions {
+                    let victor = meme_list.first().unwrap();
                     let mut data = crate::acquire_data_handle!(write ctx);
-                    let config = data.get_mut::<Config>().unwrap();
+                    let config = &mut *data;
                     let guild = config.guild_mut(&g.id);
                     let memes = guild.memes_mut().unwrap();
-                    memes.add_victory(victor.author.id);
+                    if memes.register_victory(victor, most_reactions) {
+                        info!(
+                            "[Guild: {}] New best-ever meme {} ({} votes)",
+                            &g.id, victor.id, most_reactions
+                        );
+                    }
                     crate::drop_data_handle!(data);
                     info!(
-                        "[Guild: {}] Registered victory for {} ({}) with message ID {} ({} votes)",
-                        &g.id, victor.author.name, victor.author.id, victor.id, most_reactions
+                        "[Guild: {}] Registered victory for {} ({}) with message ID {} ({} votes; {} entries)",
+                        &g.id,
+                        victor.author.name,
+                        victor.author.id,
+                        victor.id,
+                        most_reactions,
+                        Memes::entry_count_for_user(&meme_list, victor.author.id)
                     );
+                    notify_subscribers_in(
+                        ctx,
+                        &[g.id],
+                        Event::MemesWinner,
+                        &format!(
+                            "**Meme contest winner**
+{} won {}'s meme contest with [this entry]({}), with {most_reactions} votes.",
+                            victor.author.mention(),
+                            g.name,
+                            victor.link(),
+                        ),
+                    )
+                    .await;
                     format!(
                         "**Voting results**
 Congratulations {} for winning this week's meme contest, with \
 their entry [here]({})!
 
-It won with a resounding {most_reactions} votes.
+It won with a resounding {most_reactions} votes (not counting any self-votes).
 
 I've reset the entries, so post your best memes and perhaps next \
 week you'll win? 😉
@@ -359,12 +922,8 @@ You've got until <t:{next_reset}:F>.",
                 )
             };
             loop {
-                if initial_message
-                    .edit(
-                        &ctx,
-                        EditMessage::new()
-                            .embeds(vec![crate::command::create_raw_embed(&new_text)]),
-                    )
+                if backend
+                    .edit_message(channel, initial_message, new_text.clone())
                     .await
                     .is_ok()
                 {
@@ -377,13 +936,17 @@ You've got until <t:{next_reset}:F>.",
             crate::drop_data_handle!(data);
         }
         let mut data = crate::acquire_data_handle!(write ctx);
-        let config = data.get_mut::<Config>().unwrap();
-        config.save();
+        let config = &mut *data;
+        config.save().await;
         crate::drop_data_handle!(data);
         Ok(())
     }
 
-    pub async fn memes_process_iter(ctx: &Context, g: &Guild) -> Result<(), Error> {
+    pub async fn memes_process_iter(
+        ctx: &Context,
+        backend: &dyn Backend,
+        g: &Guild,
+    ) -> Result<(), Error> {
         let data = crate::acquire_data_handle!(read ctx);
         if let Some(memes) = get_memes(&data, &g.id) {
             let reset_time = memes.next_reset();
@@ -402,62 +965,121 @@ You've got until <t:{next_reset}:F>.",
                 );
                 tokio::time::sleep(time_until_ping.to_std().unwrap()).await;
                 let data = crate::acquire_data_handle!(read ctx);
-                if let Some(memes) = get_memes(&data, &g.id) {
-                    let channel = memes
-                        .channel()
-                        .to_channel(&ctx)
-                        .await
-                        .unwrap()
-                        .guild()
-                        .unwrap();
-                    crate::drop_data_handle!(data);
-                    if Self::get_messages(ctx, g).await?.is_empty() {
-                        channel
-                            .send_message(
-                                &ctx,
-                                CreateMessage::new().add_embed(
-                                    CreateEmbed::new()
-                                        .description(format!(
-                                            "**No memes?**
-<t:{}:R> left! Perhaps time to post some?",
-                                            reset_time.timestamp()
-                                        ))
-                                        .image(NO_MEMES_GIF)
-                                        .colour(crate::COLOUR),
-                                ),
-                            )
-                            .await?;
-                    }
-                } else {
-                    crate::drop_data_handle!(data);
+                let channel = get_memes(&data, &g.id).map(|memes| memes.channel());
+                crate::drop_data_handle!(data);
+                if let Some(channel) = channel {
+                    let entries = Self::get_messages(ctx, backend, &g.id).await?;
+                    Self::maybe_send_reminder(backend, channel, reset_time, &entries).await?;
                 }
             }
-            let now = Utc::now();
-            let time_until_reset = reset_time.signed_duration_since(now);
-            info!(
-                "[Guild: {}] Time until reset: {}",
-                &g.id,
-                time_until_reset.num_seconds()
-            );
-            if time_until_reset.num_seconds() > 0 {
-                info!(
-                    "[Guild: {}] Sleeping for {}s until it's time to reset",
-                    &g.id,
-                    time_until_reset.num_seconds()
-                );
-                tokio::time::sleep(time_until_reset.to_std().unwrap()).await;
+            Self::sleep_until_reset(&g.id, reset_time).await;
+            // The contest may have been reset (e.g. by `/memes force_reset`) whilst we slept.
+            let data = crate::acquire_data_handle!(read ctx);
+            let next_reset = get_memes(&data, &g.id).map(Memes::next_reset);
+            crate::drop_data_handle!(data);
+            if next_reset != Some(reset_time) {
+                return Ok(());
             }
-            Self::process_memes(ctx, g).await
+            Self::process_memes(ctx, backend, g).await
         } else {
             crate::drop_data_handle!(data);
             Ok(())
         }
     }
 
+    /// Remind `channel` that the contest ends at `reset_time`, if there are no `entries` yet.
+    ///
+    /// Returns whether a reminder was sent.
+    async fn maybe_send_reminder(
+        backend: &dyn Backend,
+        channel: ChannelId,
+        reset_time: DateTime<Utc>,
+        entries: &[Message],
+    ) -> Result<bool, Error> {
+        if !entries.is_empty() {
+            return Ok(false);
+        }
+        backend
+            .send_image_message(
+                channel,
+                format!(
+                    "**No memes?**
+<t:{}:R> left! Perhaps time to post some?",
+                    reset_time.timestamp()
+                ),
+                NO_MEMES_GIF,
+            )
+            .await?;
+        Ok(true)
+    }
+
+    /// Sleep until `reset_time`, returning immediately if it has already passed.
+    async fn sleep_until_reset(g: &GuildId, reset_time: DateTime<Utc>) {
+        let time_until_reset = reset_time.signed_duration_since(Utc::now());
+        info!(
+            "[Guild: {}] Time until reset: {}",
+            g,
+            time_until_reset.num_seconds()
+        );
+        if let Ok(time_until_reset) = time_until_reset.to_std() {
+            info!(
+                "[Guild: {}] Sleeping for {}s until it's time to reset",
+                g,
+                time_until_reset.as_secs()
+            );
+            tokio::time::sleep(time_until_reset).await;
+        }
+    }
+
+    /// Check whether the configured memes channel has been deleted, and if so, unset it so that
+    /// processing is not retried against a channel which no longer exists.
+    ///
+    /// Returns whether the channel was unset.
+    async fn clear_deleted_channel(ctx: &Context, backend: &dyn Backend, g: &Guild) -> bool {
+        let data = crate::acquire_data_handle!(read ctx);
+        let channel = get_memes(&data, &g.id).map(|memes| memes.channel());
+        crate::drop_data_handle!(data);
+        let Some(channel) = channel else {
+            return false;
+        };
+        match backend.get_channel(channel).await {
+            Err(Error::SerenityError(e)) if is_not_found(&e) => {
+                let mut data = crate::acquire_data_handle!(write ctx);
+                let config = &mut *data;
+                config.guild_mut(&g.id).set_memes_channel(None);
+                config.save().await;
+                crate::drop_data_handle!(data);
+                warn!(
+                    "[Guild: {}] Memes channel {channel} no longer exists; unset memes channel.",
+                    &g.id
+                );
+                notify_subscribers(
+                    ctx,
+                    Event::Error,
+                    &format!(
+                        "**[Guild: {}] Memes channel {channel} no longer exists.**
+The memes channel has been unset; set a new one to resume the meme contest.",
+                        &g.id
+                    ),
+                )
+                .await;
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub async fn guild_init(ctx: Context, g: Guild) {
+        let backend = SerenityBackend::from(&ctx);
         loop {
-            if let Err(e) = Self::memes_process_iter(&ctx, &g).await {
-                if let Error::SerenityError(serenity::Error::Http(
+            if let Err(e) = Self::memes_process_iter(&ctx, &backend, &g).await {
+                #[cfg(feature = "metrics")]
+                crate::metrics::record_background_error("memes", &g.id.to_string());
+                if matches!(&e, Error::SerenityError(e) if is_not_found(e))
+                    && Self::clear_deleted_channel(&ctx, &backend, &g).await
+                {
+                    // With the channel unset, subsequent iterations are no-ops until a new one is set.
+                } else if let Error::SerenityError(serenity::Error::Http(
                     serenity::all::HttpError::Request(_),
                 )) = e
                 {
@@ -483,6 +1105,15 @@ You've got until <t:{next_reset}:F>.",
     }
 }
 
+/// Whether `e` is the result of a request to a resource which doesn't exist (HTTP 404).
+fn is_not_found(e: &serenity::Error) -> bool {
+    matches!(
+        e,
+        serenity::Error::Http(serenity::all::HttpError::UnsuccessfulRequest(resp))
+            if resp.status_code == serenity::http::StatusCode::NOT_FOUND
+    )
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Memes {
     channel: ChannelId,
@@ -490,9 +1121,109 @@ pub struct Memes {
     initial_message: MessageId,
     times_won: HashMap<String, u32>,
     reacted: bool,
+    /// The length of each contest, in days.
+    #[serde(default = "default_reset_period_days")]
+    reset_period_days: u32,
+    /// The winning entry with the most votes of any contest, as (message, channel, author, votes).
+    #[serde(default)]
+    best_ever: Option<(MessageId, ChannelId, UserId, u64)>,
+    /// The reactions which count as votes, or [None] (or empty) if every reaction does.
+    #[serde(default, with = "reaction_strings")]
+    allowed_reactions: Option<Vec<ReactionType>>,
+    /// When each user (by ID) last submitted an entry, while there's a submission cooldown.
+    #[serde(default)]
+    last_submitted: HashMap<String, DateTime<Utc>>,
+    /// The minimum gap between each user's entries, in minutes; 0 for no cooldown.
+    #[serde(default)]
+    submission_cooldown_mins: u32,
+}
+
+/// Parse a reaction given by a user (or from the config): a unicode emoji, a custom emoji as
+/// Discord formats it (e.g. `<:name:id>`), or a custom emoji's ID.
+pub fn parse_reaction(s: &str) -> Option<ReactionType> {
+    let s = s.trim();
+    if s.starts_with('<') {
+        return ReactionType::try_from(s).ok();
+    }
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+        let id = s.parse().ok().filter(|id| *id > 0)?;
+        return Some(ReactionType::Custom {
+            animated: false,
+            id: EmojiId::new(id),
+            name: None,
+        });
+    }
+    // Unicode emoji are short, and (aside from keycaps such as 1️⃣) made of non-ASCII characters.
+    let looks_like_emoji = s.chars().count() <= 16
+        && !s.is_ascii()
+        && !s
+            .chars()
+            .any(|c| c.is_ascii_alphabetic() || c.is_whitespace());
+    looks_like_emoji.then(|| ReactionType::Unicode(s.to_string()))
+}
+
+/// Whether `a` and `b` are the same emoji; custom emoji are compared by ID alone, as their names
+/// may change (or be unknown).
+fn same_reaction(a: &ReactionType, b: &ReactionType) -> bool {
+    match (a, b) {
+        (ReactionType::Custom { id: a, .. }, ReactionType::Custom { id: b, .. }) => a == b,
+        (ReactionType::Unicode(a), ReactionType::Unicode(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// (De)serialise a list of reactions as strings: unicode emoji as themselves, and custom emoji
+/// by their ID.
+mod reaction_strings {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use serenity::all::ReactionType;
+
+    pub fn serialize<S: Serializer>(
+        reactions: &Option<Vec<ReactionType>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        reactions
+            .as_ref()
+            .map(|reactions| {
+                reactions
+                    .iter()
+                    .map(|reaction| match reaction {
+                        ReactionType::Custom { id, .. } => id.to_string(),
+                        reaction => reaction.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<ReactionType>>, D::Error> {
+        Option::<Vec<String>>::deserialize(deserializer)?
+            .map(|reactions| {
+                reactions
+                    .iter()
+                    .map(|s| {
+                        super::parse_reaction(s)
+                            .ok_or_else(|| D::Error::custom(format!("invalid reaction `{s}`")))
+                    })
+                    .collect()
+            })
+            .transpose()
+    }
+}
+
+fn default_reset_period_days() -> u32 {
+    DEFAULT_RESET_PERIOD_DAYS
 }
 
 impl Memes {
+    /// Check that the user IDs which key the victories and submission times are valid.
+    pub(crate) fn validate_ids(&self) -> crate::Result<()> {
+        validate_id_keys("memes victories", self.times_won.keys())?;
+        validate_id_keys("memes submissions", self.last_submitted.keys())
+    }
+
     pub fn new(channel: ChannelId, initial_message: MessageId) -> Self {
         Self {
             channel,
@@ -500,11 +1231,26 @@ impl Memes {
             initial_message,
             times_won: HashMap::new(),
             reacted: false,
+            reset_period_days: DEFAULT_RESET_PERIOD_DAYS,
+            best_ever: None,
+            allowed_reactions: None,
+            last_submitted: HashMap::new(),
+            submission_cooldown_mins: 0,
         }
     }
 
     pub fn next_reset(&self) -> chrono::DateTime<Utc> {
-        self.last_reset.checked_add_days(Days::new(7)).unwrap()
+        self.last_reset
+            .checked_add_days(Days::new(self.reset_period_days.into()))
+            .unwrap()
+    }
+
+    pub fn reset_period_days(&self) -> u32 {
+        self.reset_period_days
+    }
+
+    pub fn set_reset_period_days(&mut self, days: u32) {
+        self.reset_period_days = days;
     }
 
     pub fn reset(&mut self, time: chrono::DateTime<Utc>, initial_message: MessageId) {
@@ -533,7 +1279,397 @@ impl Memes {
         *self.times_won.entry(uid.to_string()).or_insert(0) += 1;
     }
 
+    /// Credit the author of the winning `entry` with a victory, recording it as the best ever if
+    /// its `votes` beat the previous record; returns whether they did.
+    pub fn register_victory(&mut self, entry: &Message, votes: u64) -> bool {
+        self.add_victory(entry.author.id);
+        self.record_winner(entry, votes)
+    }
+
+    pub fn best_ever(&self) -> Option<(MessageId, ChannelId, UserId, u64)> {
+        self.best_ever
+    }
+
+    /// Record the winning `entry` of a contest, with its number of `votes`, as the best ever if
+    /// it beat the previous record; returns whether it did.
+    pub fn record_winner(&mut self, entry: &Message, votes: u64) -> bool {
+        if self.best_ever.is_some_and(|(_, _, _, best)| best >= votes) {
+            return false;
+        }
+        self.best_ever = Some((entry.id, entry.channel_id, entry.author.id, votes));
+        true
+    }
+
+    pub fn allowed_reactions(&self) -> &[ReactionType] {
+        self.allowed_reactions.as_deref().unwrap_or_default()
+    }
+
+    /// Count `reaction` as a vote, alongside any others already allowed; returns whether it
+    /// wasn't already allowed.
+    pub fn allow_reaction(&mut self, reaction: ReactionType) -> bool {
+        let allowed = self.allowed_reactions.get_or_insert_with(Vec::new);
+        if allowed.iter().any(|r| same_reaction(r, &reaction)) {
+            return false;
+        }
+        allowed.push(reaction);
+        true
+    }
+
+    /// Count every reaction as a vote.
+    pub fn clear_allowed_reactions(&mut self) {
+        self.allowed_reactions = None;
+    }
+
+    /// Whether `reaction` counts as a vote.
+    pub fn counts_reaction(&self, reaction: &ReactionType) -> bool {
+        let allowed = self.allowed_reactions();
+        allowed.is_empty() || allowed.iter().any(|r| same_reaction(r, reaction))
+    }
+
+    /// Remove any reactions which don't count as votes from `entries`.
+    pub fn discard_uncounted_reactions(&self, entries: &mut [Message]) {
+        for entry in entries {
+            entry
+                .reactions
+                .retain(|r| self.counts_reaction(&r.reaction_type));
+        }
+    }
+
+    pub fn submission_cooldown_mins(&self) -> u32 {
+        self.submission_cooldown_mins
+    }
+
+    /// Set the minimum gap between each user's entries, or with 0, remove it.
+    pub fn set_submission_cooldown_mins(&mut self, mins: u32) {
+        self.submission_cooldown_mins = mins;
+        if mins == 0 {
+            self.last_submitted.clear();
+        }
+    }
+
+    /// Record an entry submitted by `user` at `now`, unless they're still on cooldown from their
+    /// last, in which case the time at which they may next submit one is returned instead.
+    pub fn try_submit(&mut self, user: UserId, now: DateTime<Utc>) -> Result<(), DateTime<Utc>> {
+        if self.submission_cooldown_mins == 0 {
+            return Ok(());
+        }
+        let cooldown = chrono::Duration::minutes(self.submission_cooldown_mins.into());
+        // Forget any users whose cooldowns have expired, so the map doesn't grow indefinitely.
+        self.last_submitted.retain(|_, t| now - *t < cooldown);
+        if let Some(last) = self.last_submitted.get(&user.to_string()) {
+            return Err(*last + cooldown);
+        }
+        self.last_submitted.insert(user.to_string(), now);
+        Ok(())
+    }
+
     pub fn initial_message(&self) -> &MessageId {
         &self.initial_message
     }
+
+    /// Count the entries in `messages` which were posted by `user_id`.
+    pub fn entry_count_for_user(messages: &[Message], user_id: UserId) -> usize {
+        messages.iter().filter(|m| m.author.id == user_id).count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use serenity::all::{ChannelId, GuildId, Message, MessageId, UserId};
+
+    use serenity::all::ReactionType;
+
+    use super::{parse_reaction, same_reaction, Memes, MemesVoting};
+    use crate::backend::mock::MockBackend;
+
+    fn entry(author: u64) -> Message {
+        let mut message = Message::default();
+        message.author.id = UserId::new(author);
+        message
+    }
+
+    #[test]
+    fn count_entries_for_user() {
+        let messages = [entry(1), entry(2), entry(1), entry(3), entry(1)];
+        assert_eq!(Memes::entry_count_for_user(&messages, UserId::new(1)), 3);
+        assert_eq!(Memes::entry_count_for_user(&messages, UserId::new(2)), 1);
+        assert_eq!(Memes::entry_count_for_user(&messages, UserId::new(4)), 0);
+    }
+
+    #[test]
+    fn reset_period_defaults_and_applies() {
+        let mut memes = Memes::new(ChannelId::new(1), MessageId::new(2));
+        assert_eq!(
+            memes.next_reset() - memes.last_reset,
+            chrono::Duration::days(7)
+        );
+        memes.set_reset_period_days(1);
+        assert_eq!(
+            memes.next_reset() - memes.last_reset,
+            chrono::Duration::days(1)
+        );
+
+        // Configs from before the period was configurable use the default.
+        let mut table = toml::Table::try_from(&memes).unwrap();
+        table.remove("reset_period_days");
+        let memes: Memes = table.try_into().unwrap();
+        assert_eq!(memes.reset_period_days(), 7);
+    }
+
+    #[test]
+    fn best_ever_only_replaced_by_a_new_record() {
+        let mut memes = Memes::new(ChannelId::new(1), MessageId::new(2));
+        let (first, second, third) = (entry(1), entry(2), entry(3));
+        assert!(memes.record_winner(&first, 5));
+        assert!(!memes.record_winner(&second, 5));
+        assert!(!memes.record_winner(&second, 4));
+        assert!(memes.record_winner(&third, 6));
+        assert_eq!(memes.best_ever().unwrap().2, UserId::new(3));
+        assert_eq!(memes.best_ever().unwrap().3, 6);
+    }
+
+    #[test]
+    fn reactions_parse_from_user_input() {
+        assert_eq!(
+            parse_reaction(" 👍 "),
+            Some(ReactionType::Unicode("👍".to_string()))
+        );
+        assert_eq!(
+            parse_reaction("1️⃣"),
+            Some(ReactionType::Unicode("1️⃣".to_string()))
+        );
+        let custom = parse_reaction("<:loki:600404340292059257>").unwrap();
+        assert!(same_reaction(
+            &custom,
+            &parse_reaction("600404340292059257").unwrap()
+        ));
+        for invalid in ["", "thumbsup", ":thumbsup:", "0", "👍 👎"] {
+            assert_eq!(parse_reaction(invalid), None, "{invalid}");
+        }
+    }
+
+    #[tokio::test]
+    async fn only_allowed_reactions_are_counted() {
+        let mut memes = Memes::new(ChannelId::new(1), MessageId::new(2));
+        assert!(memes.allow_reaction(parse_reaction("👍").unwrap()));
+        assert!(memes.allow_reaction(parse_reaction("<:loki:3>").unwrap()));
+        assert!(!memes.allow_reaction(parse_reaction("3").unwrap()));
+
+        // The allowed reactions survive a round trip through the config.
+        let table = toml::Table::try_from(&memes).unwrap();
+        assert_eq!(
+            table["allowed_reactions"],
+            toml::Value::from(vec!["👍", "3"])
+        );
+        let memes: Memes = table.try_into().unwrap();
+
+        let backend = MockBackend::default();
+        let channel = ChannelId::new(1);
+        let initial_message = backend.inject_message(channel, UserId::new(100));
+        let entry_a = backend.inject_message(channel, UserId::new(1));
+        let entry_b = backend.inject_message(channel, UserId::new(2));
+        backend.inject_reactions(entry_a, '👍', 2);
+        backend.inject_reactions(entry_b, '👍', 1);
+        backend.inject_reactions(entry_b, '🇬', 5);
+        let mut meme_list = MemesVoting::fetch_messages_after(&backend, channel, initial_message)
+            .await
+            .unwrap();
+        // The initial message is never fetched as an entry.
+        assert!(meme_list.iter().all(|m| m.id != initial_message));
+        memes.discard_uncounted_reactions(&mut meme_list);
+        assert_eq!(MemesVoting::rank_entries(&mut meme_list), Some(2));
+        assert_eq!(meme_list[0].id, entry_a);
+
+        let mut memes = memes;
+        memes.clear_allowed_reactions();
+        assert!(memes.counts_reaction(&ReactionType::Unicode("🇬".to_string())));
+    }
+
+    #[test]
+    fn reset_starts_a_new_contest() {
+        let mut memes = Memes::new(ChannelId::new(1), MessageId::new(2));
+        memes.reacted();
+        let time = Utc::now() + chrono::Duration::days(2);
+        memes.reset(time, MessageId::new(3));
+        assert_eq!(memes.last_reset, time);
+        assert_eq!(memes.next_reset(), time + chrono::Duration::days(7));
+        assert_eq!(*memes.initial_message(), MessageId::new(3));
+        assert!(!memes.has_reacted());
+    }
+
+    #[test]
+    fn submission_cooldown_is_per_user_and_expires() {
+        let mut memes = Memes::new(ChannelId::new(1), MessageId::new(2));
+        let (a, b) = (UserId::new(1), UserId::new(2));
+        let now = Utc::now();
+        // No cooldown by default.
+        assert!(memes.try_submit(a, now).is_ok());
+        assert!(memes.try_submit(a, now).is_ok());
+
+        memes.set_submission_cooldown_mins(10);
+        assert!(memes.try_submit(a, now).is_ok());
+        let later = now + chrono::Duration::minutes(4);
+        assert_eq!(
+            memes.try_submit(a, later),
+            Err(now + chrono::Duration::minutes(10))
+        );
+        assert!(memes.try_submit(b, later).is_ok());
+        assert!(memes
+            .try_submit(a, now + chrono::Duration::minutes(10))
+            .is_ok());
+
+        memes.set_submission_cooldown_mins(0);
+        assert!(memes.try_submit(b, later).is_ok());
+    }
+
+    #[test]
+    fn count_entries_for_user_without_entries() {
+        assert_eq!(Memes::entry_count_for_user(&[], UserId::new(1)), 0);
+    }
+
+    #[tokio::test]
+    async fn reminder_sent_without_entries() {
+        let backend = MockBackend::default();
+        let channel = ChannelId::new(1);
+        let sent = MemesVoting::maybe_send_reminder(&backend, channel, Utc::now(), &[])
+            .await
+            .unwrap();
+        assert!(sent);
+        let messages = backend.sent();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].0, channel);
+        assert!(messages[0].1.starts_with("**No memes?**"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn contest_selects_most_voted_entry() {
+        let backend = MockBackend::default();
+        let channel = ChannelId::new(1);
+        let initial_message = backend.inject_message(channel, UserId::new(100));
+        let mut memes = Memes::new(channel, initial_message);
+
+        let entries: Vec<MessageId> = (1..=5)
+            .map(|author| backend.inject_message(channel, UserId::new(author)))
+            .collect();
+        backend.inject_reactions(entries[0], '👍', 1);
+        backend.inject_reactions(entries[2], '👍', 2);
+        backend.inject_reactions(entries[2], '😂', 2);
+        backend.inject_reactions(entries[4], '😂', 3);
+        // None of these count: the author's votes for their own entry, nor a disallowed reaction.
+        backend.inject_reaction_from(entries[4], '😂', UserId::new(5));
+        backend.inject_reaction_from(entries[4], '👍', UserId::new(5));
+        backend.inject_reactions(entries[1], '🔥', 10);
+        memes.allow_reaction(ReactionType::Unicode("👍".to_string()));
+        memes.allow_reaction(ReactionType::Unicode("😂".to_string()));
+
+        // The clock is paused, so this advances it past the reset time without actually waiting.
+        let reset_time = memes.next_reset();
+        let until_reset = reset_time
+            .signed_duration_since(Utc::now())
+            .to_std()
+            .unwrap();
+        let start = tokio::time::Instant::now();
+        MemesVoting::sleep_until_reset(&GuildId::new(1), reset_time).await;
+        assert!(start.elapsed() >= until_reset - Duration::from_secs(1));
+
+        let mut meme_list = MemesVoting::fetch_messages_after(&backend, channel, initial_message)
+            .await
+            .unwrap();
+        assert_eq!(meme_list.len(), 5);
+        let most_reactions = MemesVoting::select_winner(&backend, &memes, &mut meme_list).await;
+        assert_eq!(most_reactions, Some(4));
+        let victor = meme_list.first().unwrap();
+        assert_eq!(victor.id, entries[2]);
+        assert!(memes.register_victory(victor, 4));
+        assert_eq!(memes.victors().get("3"), Some(&1));
+        assert_eq!(memes.victors().len(), 1);
+        assert_eq!(
+            memes.best_ever(),
+            Some((entries[2], channel, UserId::new(3), 4))
+        );
+    }
+
+    #[tokio::test]
+    async fn standings_show_top_entries_by_votes() {
+        let empty = serde_json::to_value(MemesVoting::standings_embed(Vec::new())).unwrap();
+        assert!(empty["description"]
+            .as_str()
+            .unwrap()
+            .contains("No memes posted yet!"));
+
+        let backend = MockBackend::default();
+        let channel = ChannelId::new(1);
+        let initial_message = backend.inject_message(channel, UserId::new(100));
+        for author in 1..=12 {
+            let entry = backend.inject_message(channel, UserId::new(author));
+            backend.inject_reactions(entry, '👍', author);
+        }
+        let entries = MemesVoting::fetch_messages_after(&backend, channel, initial_message)
+            .await
+            .unwrap();
+        let standings = serde_json::to_value(MemesVoting::standings_embed(entries)).unwrap();
+        let column = |i: usize| {
+            standings["fields"][i]["value"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(column(0).lines().count(), 10);
+        assert!(column(1).starts_with("<@12>"));
+        let votes: Vec<_> = column(2).lines().map(str::to_string).collect();
+        assert_eq!(votes.first().unwrap(), "12");
+        assert_eq!(votes.last().unwrap(), "3");
+        assert!(standings["timestamp"].is_string());
+    }
+
+    #[tokio::test]
+    async fn self_votes_are_not_counted() {
+        let backend = MockBackend::default();
+        let channel = ChannelId::new(1);
+        let initial_message = backend.inject_message(channel, UserId::new(100));
+        let (a, b) = (UserId::new(1), UserId::new(2));
+        let entry_a = backend.inject_message(channel, a);
+        let entry_b = backend.inject_message(channel, b);
+        backend.inject_reactions(entry_a, '👍', 2);
+        backend.inject_reaction_from(entry_a, '👍', a);
+        backend.inject_reaction_from(entry_a, '😂', a);
+        backend.inject_reactions(entry_b, '👍', 2);
+        backend.inject_reaction_from(entry_b, '👍', a);
+
+        let mut meme_list = MemesVoting::fetch_messages_after(&backend, channel, initial_message)
+            .await
+            .unwrap();
+        MemesVoting::discount_self_votes(&backend, &mut meme_list).await;
+        assert_eq!(MemesVoting::rank_entries(&mut meme_list), Some(3));
+        assert_eq!(meme_list[0].id, entry_b);
+        assert_eq!(MemesVoting::votes(&meme_list[1]), 2);
+    }
+
+    #[tokio::test]
+    async fn contest_without_votes_has_no_victor() {
+        let backend = MockBackend::default();
+        let channel = ChannelId::new(1);
+        let initial_message = backend.inject_message(channel, UserId::new(100));
+        backend.inject_message(channel, UserId::new(1));
+        backend.inject_message(channel, UserId::new(2));
+        let mut meme_list = MemesVoting::fetch_messages_after(&backend, channel, initial_message)
+            .await
+            .unwrap();
+        assert_eq!(meme_list.len(), 2);
+        assert_eq!(MemesVoting::rank_entries(&mut meme_list), None);
+    }
+
+    #[tokio::test]
+    async fn reminder_not_sent_with_entries() {
+        let backend = MockBackend::default();
+        let sent =
+            MemesVoting::maybe_send_reminder(&backend, ChannelId::new(1), Utc::now(), &[entry(1)])
+                .await
+                .unwrap();
+        assert!(!sent);
+        assert!(backend.sent().is_empty());
+    }
 }
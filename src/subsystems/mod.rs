@@ -1,10 +1,10 @@
 use serenity::{
     async_trait,
-    model::prelude::{GuildChannel, Member, Message, Presence, Ready},
-    prelude::Context,
+    model::prelude::{GuildChannel, GuildId, Member, Message, Presence, Ready},
+    prelude::{Context, GatewayIntents},
 };
 
-use crate::command::Command;
+use crate::{command::Command, Config};
 
 macro_rules! get_param {
     ($params:ident, $variant:ident, $name:expr) => {
@@ -29,9 +29,9 @@ pub mod scoreboard;
 #[cfg(feature = "status-meaning")]
 mod status_meaning;
 #[cfg(feature = "stream-indicator")]
-mod stream_indicator;
+pub mod stream_indicator;
 #[cfg(feature = "text-response")]
-mod text_response;
+pub mod text_response;
 #[cfg(feature = "thread-reviver")]
 pub mod thread_reviver;
 #[cfg(feature = "timeout-monitor")]
@@ -60,13 +60,29 @@ pub fn subsystems() -> Vec<Box<dyn Subsystem>> {
     ]
 }
 
+/// Notify every [Subsystem] that the configuration for `guild_id` may have changed.
+pub async fn config_changed(ctx: &Context, guild_id: &GuildId) {
+    for s in subsystems() {
+        s.config_changed(ctx, guild_id).await;
+    }
+}
+
 #[async_trait]
 pub trait Subsystem: Send + Sync {
     fn generate_commands(&self) -> Vec<Command<'static>>;
 
+    /// The gateway intents this [Subsystem] needs beyond [GatewayIntents::non_privileged], given
+    /// the `config` Loki started with.
+    fn required_intents(&self, _config: &Config) -> GatewayIntents {
+        GatewayIntents::empty()
+    }
+
     async fn ready(&self, _ctx: &Context, _ready: &Ready) {}
     async fn message(&self, _ctx: &Context, _message: &Message) {}
     async fn presence(&self, _ctx: &Context, _new_data: &Presence) {}
     async fn thread(&self, _ctx: &Context, _thread: &GuildChannel) {}
     async fn member(&self, _ctx: &Context, _old: &Option<Member>, _new: &Member) {}
+    /// Called after a command which saved the config has been run in a guild, as it may have changed that guild's configuration.
+    /// No config handle is held while this runs.
+    async fn config_changed(&self, _ctx: &Context, _guild_id: &GuildId) {}
 }
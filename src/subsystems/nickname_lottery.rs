@@ -1,5 +1,6 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt::Display,
     str::FromStr,
     time::Duration,
 };
@@ -7,26 +8,36 @@ use std::{
 use chrono::{DateTime, Datelike, TimeZone, Utc};
 use log::{error, info, trace, warn};
 use rand::{
-    distributions::Distribution,
-    seq::{IteratorRandom, SliceRandom},
+    distributions::{Distribution, WeightedIndex},
+    seq::IteratorRandom,
 };
 use serde::{Deserialize, Serialize};
 use serenity::{
-    all::{CommandDataOptionValue, CreateModal, Guild, Mentionable as _, UserId},
+    all::{
+        CommandDataOptionValue, CommandInteraction, CreateModal, Guild, Mentionable as _, User,
+        UserId,
+    },
     async_trait,
-    model::{channel::ChannelType, id::ChannelId, Permissions},
-    prelude::Context,
+    model::{id::ChannelId, Permissions},
+    prelude::{Context, GatewayIntents},
 };
 
 #[cfg(feature = "events")]
-use crate::{command::notify_subscribers, subsystems::events::Event};
+use crate::{
+    command::{notify_subscribers, notify_subscribers_in},
+    subsystems::events::Event,
+};
 
 use crate::{
-    command::OptionType, config::Config, create_embed, create_raw_embed,
-    notify_subscribers_with_handle, ActionResponse,
+    backend::{Backend, SerenityBackend},
+    command::{
+        confirm_action, require_permissions, target_user, ChannelTypes, CommandKind, OptionType,
+    },
+    create_raw_embed, ActionResponse, PaginatedEmbed,
 };
 use crate::{
     command::{Command, PermissionType},
+    config::validate_id_keys,
     get_guild,
 };
 
@@ -34,12 +45,30 @@ use super::Subsystem;
 
 /// (30 mins, 5 days) in seconds.
 const DEFAULT_REFRESH_INTERVAL: (u64, u64) = (1_800, 432_000);
+/// The number of nicknames shown on each page of a user's nickname list.
+const NICKNAMES_PER_PAGE: usize = 20;
+/// The relative likelihood of a nickname being drawn, unless set otherwise.
+const DEFAULT_WEIGHT: u32 = 1;
+/// The least and most likely a nickname may be made to be drawn.
+const MIN_WEIGHT: u32 = 1;
+const MAX_WEIGHT: u32 = 100;
+/// The longest a nickname may be, in characters.
+const MAX_NICKNAME_LENGTH: usize = 30;
+/// The largest file accepted by `/nickname_lottery user_nicknames bulk_import`, in bytes.
+const MAX_BULK_IMPORT_SIZE: u32 = 64 * 1024;
+/// How many times a user's nickname is redrawn when it's the same as their current one, unless
+/// set otherwise.
+const DEFAULT_MAX_REROLL_ATTEMPTS: u32 = 5;
+/// The most times a guild may have nicknames redrawn.
+const MAX_REROLL_ATTEMPTS: u32 = 20;
+/// The most skipped nicknames listed in a bulk import's summary.
+const MAX_SKIPPED_SHOWN: usize = 20;
 
 #[derive(Default)]
 pub struct NicknameLottery;
 
 /// A [Guild]'s collective nickname lottery data, including configuration.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize)]
 pub struct NicknameLotteryGuildData {
     /// HashMap of stringified [UserId]s to their respective list of specific nicknames, or [None] if they are excluded from the system.
     user_specific_nicknames: HashMap<String, Vec<NicknameData>>,
@@ -50,9 +79,104 @@ pub struct NicknameLotteryGuildData {
     title_override: Option<String>,
     /// An override for the refresh interval for this guild. Uses [DEFAULT_REFRESH_INTERVAL] if [None].
     refresh_interval: Option<(u64, u64)>,
+    /// The year for which the upcoming April Fool's acceleration was last announced, if ever.
+    notified_april_fools: Option<u32>,
+    /// Stringified [UserId]s of users who've been excluded from the lottery, whether or not they
+    /// have any nicknames.
+    #[serde(default)]
+    excluded_users: HashSet<String>,
+    /// Whether bulk-imported nicknames longer than [MAX_NICKNAME_LENGTH] are truncated, rather
+    /// than rejected.
+    #[serde(default)]
+    truncate_imported_nicknames: bool,
+    /// HashMap of stringified [UserId]s to nicknames submitted for them which await approval.
+    #[serde(default)]
+    pending_nicknames: HashMap<String, Vec<NicknameData>>,
+    /// Whether new nicknames must be approved by an administrator before they can be drawn.
+    #[serde(default)]
+    approval_required: bool,
+    /// How many times to redraw a user's nickname when it's the same as their current one, before
+    /// giving up on changing it this round.
+    #[serde(default = "default_max_reroll_attempts")]
+    max_reroll_attempts: u32,
+    /// Nicknames which may be drawn for any user who has none of their own.
+    #[serde(default)]
+    global_nicknames: Vec<NicknameData>,
+}
+
+/// Where a user's nicknames are drawn from, as by [NicknameLotteryGuildData::nickname_source].
+#[derive(Debug, PartialEq)]
+pub enum NicknameSource {
+    /// The user's own nicknames.
+    Personal,
+    /// The guild's global pool, as the user has no nicknames of their own.
+    Global,
+    /// Nowhere: the user has no nicknames of their own, and the global pool is empty.
+    None,
+}
+
+fn default_max_reroll_attempts() -> u32 {
+    DEFAULT_MAX_REROLL_ATTEMPTS
+}
+
+impl Default for NicknameLotteryGuildData {
+    fn default() -> Self {
+        Self {
+            user_specific_nicknames: HashMap::new(),
+            channel: None,
+            title_override: None,
+            refresh_interval: None,
+            notified_april_fools: None,
+            excluded_users: HashSet::new(),
+            truncate_imported_nicknames: false,
+            pending_nicknames: HashMap::new(),
+            approval_required: false,
+            max_reroll_attempts: DEFAULT_MAX_REROLL_ATTEMPTS,
+            global_nicknames: Vec::new(),
+        }
+    }
+}
+
+/// The outcome of [NicknameLotteryGuildData::import_user_nicknames].
+#[derive(Debug, Default, PartialEq)]
+pub struct NicknameImport {
+    /// The number of nicknames added.
+    pub added: usize,
+    /// The nicknames which weren't added, with the reason why.
+    pub skipped: Vec<(String, &'static str)>,
+}
+
+impl NicknameImport {
+    /// A summary of the skipped nicknames, listing up to [MAX_SKIPPED_SHOWN] of them (each cut
+    /// to [MAX_NICKNAME_LENGTH] characters, so the list fits in an embed), or [None] if there
+    /// were none.
+    pub fn skipped_summary(&self) -> Option<String> {
+        if self.skipped.is_empty() {
+            return None;
+        }
+        let mut summary = format!("Skipped {}:", self.skipped.len());
+        for (nickname, reason) in self.skipped.iter().take(MAX_SKIPPED_SHOWN) {
+            let mut shown: String = nickname.chars().take(MAX_NICKNAME_LENGTH).collect();
+            if shown.len() < nickname.len() {
+                shown.push('…');
+            }
+            summary += &format!("\n- `{shown}`: {reason}");
+        }
+        if self.skipped.len() > MAX_SKIPPED_SHOWN {
+            summary += &format!("\n...and {} more.", self.skipped.len() - MAX_SKIPPED_SHOWN);
+        }
+        Some(summary)
+    }
 }
 
 impl NicknameLotteryGuildData {
+    /// Check that the user IDs which key the nicknames and exclusions are valid.
+    pub(crate) fn validate_ids(&self) -> crate::Result<()> {
+        validate_id_keys("nicknames", self.user_specific_nicknames.keys())?;
+        validate_id_keys("excluded users", &self.excluded_users)?;
+        validate_id_keys("pending nicknames", self.pending_nicknames.keys())
+    }
+
     /// Returns the list of specific nicknames for a given [UserId], or [None] if the user does not have any.
     pub fn user_nicknames(&self, user: &UserId) -> Option<&Vec<NicknameData>> {
         self.user_specific_nicknames.get(&user.to_string())
@@ -72,15 +196,248 @@ impl NicknameLotteryGuildData {
             - 1
     }
 
-    pub fn set_user_nickname_context(&mut self, user: &UserId, n: usize, context: String) {
+    /// Whether the [UserId] has `nickname`, either live or awaiting approval.
+    pub fn has_nickname(&self, user: &UserId, nickname: &str) -> bool {
+        [&self.user_specific_nicknames, &self.pending_nicknames]
+            .iter()
+            .filter_map(|nicknames| nicknames.get(&user.to_string()))
+            .any(|nicknames| nicknames.iter().any(|nd| nd.nickname() == nickname))
+    }
+
+    /// Submit a [NicknameData] for a [UserId]: added straight away, unless
+    /// [Self::approval_required], in which case it awaits approval.
+    pub fn submit_user_nickname(&mut self, user: &UserId, nickname: NicknameData) {
+        if self.approval_required {
+            trace!("Submitting nickname for approval for {user:?}: {nickname:?}");
+            self.pending_nicknames
+                .entry(user.to_string())
+                .or_default()
+                .push(nickname);
+        } else {
+            self.add_user_nickname(user, nickname);
+        }
+    }
+
+    /// The nicknames awaiting approval, by [UserId], in no particular order.
+    pub fn pending_nicknames(&self) -> impl Iterator<Item = (UserId, &Vec<NicknameData>)> {
+        self.pending_nicknames
+            .iter()
+            .map(|(id, nicknames)| (UserId::new(u64::from_str(id).unwrap()), nicknames))
+    }
+
+    /// Remove the `n`th pending [NicknameData] from a [UserId], returning it.
+    ///
+    /// Returns [crate::Error::InvalidParam] if the [UserId] has no `n`th pending nickname.
+    fn take_pending_nickname(&mut self, user: &UserId, n: usize) -> crate::Result<NicknameData> {
+        let Entry::Occupied(mut entry) = self.pending_nicknames.entry(user.to_string()) else {
+            return Err(crate::Error::InvalidParam(
+                "no pending nicknames for this user".to_string(),
+            ));
+        };
+        if n == 0 || n > entry.get().len() {
+            return Err(crate::Error::InvalidParam(format!(
+                "no pending nickname #{n} for this user"
+            )));
+        }
+        let nickname = entry.get_mut().remove(n - 1);
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        Ok(nickname)
+    }
+
+    /// Approve the `n`th pending [NicknameData] for a [UserId], making it eligible to be drawn,
+    /// and returning the nickname.
+    pub fn approve_pending_nickname(&mut self, user: &UserId, n: usize) -> crate::Result<String> {
+        let nickname = self.take_pending_nickname(user, n)?;
+        let name = nickname.nickname().clone();
+        self.add_user_nickname(user, nickname);
+        Ok(name)
+    }
+
+    /// Reject the `n`th pending [NicknameData] for a [UserId], discarding it, and returning the
+    /// nickname.
+    pub fn reject_pending_nickname(&mut self, user: &UserId, n: usize) -> crate::Result<String> {
+        Ok(self.take_pending_nickname(user, n)?.nickname)
+    }
+
+    /// Whether new nicknames must be approved before they can be drawn.
+    pub fn approval_required(&self) -> bool {
+        self.approval_required
+    }
+
+    /// Set whether new nicknames must be approved before they can be drawn.
+    pub fn set_approval_required(&mut self, required: bool) {
+        self.approval_required = required;
+    }
+
+    /// Submit each of `nicknames` for a [UserId], on behalf of `author`, as by
+    /// [Self::submit_user_nickname].
+    ///
+    /// Nicknames are trimmed, and skipped if they're empty or the user already has them. Those
+    /// longer than [MAX_NICKNAME_LENGTH] are truncated or skipped, depending on
+    /// [Self::truncate_imported_nicknames].
+    pub fn import_user_nicknames(
+        &mut self,
+        user: &UserId,
+        nicknames: Vec<String>,
+        author: UserId,
+    ) -> NicknameImport {
+        let mut import = NicknameImport::default();
+        for nickname in nicknames {
+            let mut nickname = nickname.trim().to_string();
+            if nickname.is_empty() {
+                import.skipped.push((nickname, "empty"));
+                continue;
+            }
+            if nickname.chars().count() > MAX_NICKNAME_LENGTH {
+                if !self.truncate_imported_nicknames {
+                    import.skipped.push((nickname, "too long"));
+                    continue;
+                }
+                nickname = nickname.chars().take(MAX_NICKNAME_LENGTH).collect();
+            }
+            if self.has_nickname(user, &nickname) {
+                import.skipped.push((nickname, "duplicate"));
+                continue;
+            }
+            self.submit_user_nickname(user, NicknameData::new(nickname, author));
+            import.added += 1;
+        }
+        import
+    }
+
+    /// Whether bulk-imported nicknames which are too long are truncated, rather than rejected.
+    pub fn truncate_imported_nicknames(&self) -> bool {
+        self.truncate_imported_nicknames
+    }
+
+    /// Set whether bulk-imported nicknames which are too long are truncated, rather than rejected.
+    pub fn set_truncate_imported_nicknames(&mut self, truncate: bool) {
+        self.truncate_imported_nicknames = truncate;
+    }
+
+    /// Set the context of the `n`th [NicknameData] for a [UserId].
+    ///
+    /// Returns [crate::Error::InvalidParam] if the [UserId] has no `n`th nickname.
+    pub fn set_user_nickname_context(
+        &mut self,
+        user: &UserId,
+        n: usize,
+        context: String,
+    ) -> crate::Result<()> {
         trace!("Adding context for {user:?} nickname #{n}: {context}");
-        assert!(n > 0);
-        self.user_specific_nicknames
-            .entry(user.to_string())
-            .and_modify(|nicknames| {
-                assert!(n <= nicknames.len());
-                nicknames.get_mut(n - 1).unwrap().set_context(context);
+        if n == 0 {
+            return Err(crate::Error::InvalidParam(
+                "nickname numbers start at 1".to_string(),
+            ));
+        }
+        match self
+            .user_specific_nicknames
+            .get_mut(&user.to_string())
+            .and_then(|nicknames| nicknames.get_mut(n - 1))
+        {
+            Some(nickname) => {
+                nickname.set_context(context);
+                Ok(())
+            }
+            None => Err(crate::Error::InvalidParam(format!(
+                "no nickname #{n} for this user"
+            ))),
+        }
+    }
+
+    /// Set the weight of the `n`th [NicknameData] for a [UserId].
+    ///
+    /// Returns [crate::Error::InvalidParam] if the [UserId] has no `n`th nickname, or the weight is
+    /// out of range.
+    pub fn set_user_nickname_weight(
+        &mut self,
+        user: &UserId,
+        n: usize,
+        weight: u32,
+    ) -> crate::Result<()> {
+        trace!("Setting weight for {user:?} nickname #{n}: {weight}");
+        if !(MIN_WEIGHT..=MAX_WEIGHT).contains(&weight) {
+            return Err(crate::Error::InvalidParam(format!(
+                "weights must be between {MIN_WEIGHT} and {MAX_WEIGHT}"
+            )));
+        }
+        if n == 0 {
+            return Err(crate::Error::InvalidParam(
+                "nickname numbers start at 1".to_string(),
+            ));
+        }
+        match self
+            .user_specific_nicknames
+            .get_mut(&user.to_string())
+            .and_then(|nicknames| nicknames.get_mut(n - 1))
+        {
+            Some(nickname) => {
+                nickname.weight = weight;
+                Ok(())
+            }
+            None => Err(crate::Error::InvalidParam(format!(
+                "no nickname #{n} for this user"
+            ))),
+        }
+    }
+
+    /// Set the expiry time of the `n`th [NicknameData] for a [UserId].
+    ///
+    /// Returns [crate::Error::InvalidParam] if the [UserId] has no `n`th nickname.
+    pub fn set_user_nickname_expiry(
+        &mut self,
+        user: &UserId,
+        n: usize,
+        expires_at: DateTime<Utc>,
+    ) -> crate::Result<()> {
+        trace!("Setting expiry for {user:?} nickname #{n}: {expires_at}");
+        if n == 0 {
+            return Err(crate::Error::InvalidParam(
+                "nickname numbers start at 1".to_string(),
+            ));
+        }
+        match self
+            .user_specific_nicknames
+            .get_mut(&user.to_string())
+            .and_then(|nicknames| nicknames.get_mut(n - 1))
+        {
+            Some(nickname) => {
+                nickname.expires_at = Some(expires_at);
+                Ok(())
+            }
+            None => Err(crate::Error::InvalidParam(format!(
+                "no nickname #{n} for this user"
+            ))),
+        }
+    }
+
+    /// Remove every [NicknameData] which has expired by `now`, from users' lists and the global
+    /// pool, returning those removed along with the user they were for ([None] for the pool).
+    pub fn prune_expired(&mut self, now: DateTime<Utc>) -> Vec<(Option<UserId>, NicknameData)> {
+        let mut pruned = Vec::new();
+        self.user_specific_nicknames.retain(|user, nicknames| {
+            let user = UserId::new(u64::from_str(user).unwrap());
+            nicknames.retain(|nickname| {
+                if nickname.is_expired(now) {
+                    pruned.push((Some(user), nickname.clone()));
+                    false
+                } else {
+                    true
+                }
             });
+            !nicknames.is_empty()
+        });
+        self.global_nicknames.retain(|nickname| {
+            if nickname.is_expired(now) {
+                pruned.push((None, nickname.clone()));
+                false
+            } else {
+                true
+            }
+        });
+        pruned
     }
 
     /// Remove the `n`th [NicknameData] from a [UserId].
@@ -101,20 +458,149 @@ impl NicknameLotteryGuildData {
         }
     }
 
-    /// Select a nickname for the given [UserId], or [None] if the user is excluded.
+    /// Select a nickname for the given [UserId], weighted by each nickname's weight, or [None] if
+    /// the user is excluded.
+    ///
+    /// Users with no nicknames of their own draw from the global pool instead.
     pub fn get_nickname_for_user(&self, user: &UserId) -> Option<&String> {
-        self.user_specific_nicknames
+        if self.is_excluded(user) {
+            return None;
+        }
+        let nicknames = self
+            .user_specific_nicknames
             .get(&user.to_string())
-            .map(|n| n.choose(&mut rand::thread_rng()))
-            .unwrap_or_default()
-            .map(|s| s.nickname())
+            .unwrap_or(&self.global_nicknames);
+        let index = WeightedIndex::new(nicknames.iter().map(NicknameData::weight)).ok()?;
+        Some(nicknames[index.sample(&mut rand::thread_rng())].nickname())
     }
 
-    /// Select a [UserId] to change the nickname of.
-    pub fn get_random_user(&self) -> Option<UserId> {
-        self.user_specific_nicknames
-            .keys()
+    /// Draw again for the [UserId], as by [Self::get_nickname_for_user], up to
+    /// [Self::max_reroll_attempts] times until the nickname isn't `current`, returning [None] if
+    /// every draw was.
+    pub fn reroll_nickname_for_user(&self, user: &UserId, current: &str) -> Option<&String> {
+        (0..self.max_reroll_attempts).find_map(|attempt| {
+            trace!("Rerolling nickname for {user:?} (attempt {})", attempt + 1);
+            self.get_nickname_for_user(user)
+                .filter(|nickname| *nickname != current)
+        })
+    }
+
+    /// How many times a nickname is redrawn when it's the same as the user's current one.
+    pub fn max_reroll_attempts(&self) -> u32 {
+        self.max_reroll_attempts
+    }
+
+    /// Set how many times a nickname is redrawn when it's the same as the user's current one.
+    ///
+    /// Returns [crate::Error::InvalidParam] if this is more than [MAX_REROLL_ATTEMPTS].
+    pub fn set_max_reroll_attempts(&mut self, attempts: u32) -> crate::Result<()> {
+        if attempts > MAX_REROLL_ATTEMPTS {
+            return Err(crate::Error::InvalidParam(format!(
+                "nicknames may be redrawn at most {MAX_REROLL_ATTEMPTS} times"
+            )));
+        }
+        self.max_reroll_attempts = attempts;
+        Ok(())
+    }
+
+    /// Record that `nickname` was drawn and applied to `user`, incrementing its use count.
+    pub fn record_nickname_use(&mut self, user: &UserId, nickname: &str) {
+        if let Some(data) = self
+            .user_specific_nicknames
+            .get_mut(&user.to_string())
+            .unwrap_or(&mut self.global_nicknames)
+            .iter_mut()
+            .find(|n| n.nickname() == nickname)
+        {
+            data.use_count += 1;
+        }
+    }
+
+    /// Where the [UserId]'s nicknames are drawn from.
+    pub fn nickname_source(&self, user: &UserId) -> NicknameSource {
+        if self.user_specific_nicknames.contains_key(&user.to_string()) {
+            NicknameSource::Personal
+        } else if self.global_nicknames.is_empty() {
+            NicknameSource::None
+        } else {
+            NicknameSource::Global
+        }
+    }
+
+    /// The nicknames in the global pool.
+    pub fn global_nicknames(&self) -> &Vec<NicknameData> {
+        &self.global_nicknames
+    }
+
+    /// Add a [NicknameData] to the global pool, returning the index of the added nickname.
+    ///
+    /// Returns [crate::Error::InvalidParam] if the pool already has the nickname.
+    pub fn add_global_nickname(&mut self, nickname: NicknameData) -> crate::Result<usize> {
+        trace!("Adding global nickname: {nickname:?}");
+        if self
+            .global_nicknames
+            .iter()
+            .any(|nd| nd.nickname() == nickname.nickname())
+        {
+            return Err(crate::Error::InvalidParam(format!(
+                "`{}` is already in the global pool",
+                nickname.nickname()
+            )));
+        }
+        self.global_nicknames.push(nickname);
+        Ok(self.global_nicknames.len() - 1)
+    }
+
+    /// Remove the `n`th [NicknameData] from the global pool, returning it.
+    ///
+    /// Returns [crate::Error::InvalidParam] if there's no `n`th nickname in the pool.
+    pub fn remove_global_nickname(&mut self, n: usize) -> crate::Result<NicknameData> {
+        trace!("Removing global nickname #{n}");
+        if n == 0 || n > self.global_nicknames.len() {
+            return Err(crate::Error::InvalidParam(format!(
+                "no nickname #{n} in the global pool"
+            )));
+        }
+        Ok(self.global_nicknames.remove(n - 1))
+    }
+
+    /// Select a [UserId] to change the nickname of, from those who aren't excluded: any user with
+    /// nicknames of their own, or if the global pool isn't empty, any of the guild's `members`.
+    pub fn get_random_user(&self, members: &[UserId]) -> Option<UserId> {
+        let mut candidates: HashSet<UserId> = self
+            .user_specific_nicknames
+            .iter()
+            .filter(|(_, nicknames)| !nicknames.is_empty())
+            .map(|(id, _)| UserId::new(u64::from_str(id).unwrap()))
+            .collect();
+        if !self.global_nicknames.is_empty() {
+            candidates.extend(members);
+        }
+        candidates
+            .into_iter()
+            .filter(|user| !self.is_excluded(user))
             .choose(&mut rand::thread_rng())
+    }
+
+    /// Whether the [UserId] has been excluded from the lottery.
+    pub fn is_excluded(&self, user: &UserId) -> bool {
+        self.excluded_users.contains(&user.to_string())
+    }
+
+    /// Exclude the [UserId] from the lottery, returning whether they weren't already excluded.
+    pub fn exclude_user(&mut self, user: &UserId) -> bool {
+        self.excluded_users.insert(user.to_string())
+    }
+
+    /// Include the [UserId] in the lottery again, returning whether they were excluded.
+    pub fn unexclude_user(&mut self, user: &UserId) -> bool {
+        self.excluded_users.remove(&user.to_string())
+    }
+
+    /// The [UserId]s excluded from the lottery, in no particular order.
+    pub fn excluded_users(&self) -> impl Iterator<Item = UserId> + '_ {
+        self.excluded_users
+            .iter()
             .map(|id| UserId::new(u64::from_str(id).unwrap()))
     }
 
@@ -148,13 +634,36 @@ impl NicknameLotteryGuildData {
     }
 
     /// Set a custom refresh interval for this guild, or reset back to the default if [None].
-    pub fn set_refresh_interval(&mut self, refresh_interval: Option<(u64, u64)>) {
+    ///
+    /// The minimum must be strictly less than the maximum, or [crate::Error::InvalidParam] is returned.
+    pub fn set_refresh_interval(
+        &mut self,
+        refresh_interval: Option<(u64, u64)>,
+    ) -> crate::Result<()> {
+        if let Some((min, max)) = refresh_interval {
+            if min >= max {
+                return Err(crate::Error::InvalidParam(
+                    "min must be less than max".to_string(),
+                ));
+            }
+        }
         self.refresh_interval = refresh_interval;
+        Ok(())
+    }
+
+    /// Get the year for which the upcoming April Fool's acceleration was last announced, if ever.
+    pub fn notified_april_fools(&self) -> Option<u32> {
+        self.notified_april_fools
+    }
+
+    /// Record that the upcoming April Fool's acceleration has been announced for the given year.
+    pub fn set_notified_april_fools(&mut self, year: u32) {
+        self.notified_april_fools = Some(year);
     }
 }
 
 /// Data for a single nickname, including metadata.
-#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NicknameData {
     /// The nickname itself.
     nickname: String,
@@ -164,6 +673,19 @@ pub struct NicknameData {
     time: Option<DateTime<Utc>>,
     /// Context for the nickname, if any.
     context: Option<String>,
+    /// The number of times this nickname has been drawn and applied.
+    #[serde(default)]
+    use_count: u32,
+    /// How likely this nickname is to be drawn, relative to the user's other nicknames.
+    #[serde(default = "default_weight")]
+    weight: u32,
+    /// The time after which this nickname is removed, if ever.
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+fn default_weight() -> u32 {
+    DEFAULT_WEIGHT
 }
 
 impl NicknameData {
@@ -174,6 +696,31 @@ impl NicknameData {
             author: Some(author),
             time: Some(Utc::now()),
             context: None,
+            use_count: 0,
+            weight: DEFAULT_WEIGHT,
+            expires_at: None,
+        }
+    }
+
+    /// Create a new nickname whose author isn't known. `time` will be set to the current time.
+    pub fn new_with_unknown_author(nickname: String) -> Self {
+        Self {
+            nickname,
+            author: None,
+            time: Some(Utc::now()),
+            context: None,
+            use_count: 0,
+            weight: DEFAULT_WEIGHT,
+            expires_at: None,
+        }
+    }
+
+    /// Create a nickname migrated from a pre-v0.11 list of nicknames, for which neither the
+    /// author nor the creation time are known.
+    pub fn from_legacy(nickname: String) -> Self {
+        Self {
+            time: None,
+            ..Self::new_with_unknown_author(nickname)
         }
     }
 
@@ -203,6 +750,47 @@ impl NicknameData {
     pub fn set_context(&mut self, context: String) {
         self.context = Some(context);
     }
+
+    /// Get the number of times this nickname has been drawn and applied.
+    pub fn use_count(&self) -> u32 {
+        self.use_count
+    }
+
+    /// Get how likely this nickname is to be drawn, relative to the user's other nicknames.
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// Get the time after which this nickname is removed, if ever.
+    pub fn expires_at(&self) -> Option<&DateTime<Utc>> {
+        self.expires_at.as_ref()
+    }
+
+    /// Whether this nickname has expired by `now`.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at < now)
+    }
+}
+
+/// Renders a user's nicknames as a numbered list, one per line, numbered from 1.
+pub struct NicknameListDisplay<'a>(pub &'a Vec<NicknameData>);
+
+impl Display for NicknameListDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, nickname) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{}. {} (weight {})",
+                i + 1,
+                nickname.nickname(),
+                nickname.weight()
+            )?;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -213,6 +801,7 @@ impl Subsystem for NicknameLottery {
             "Controls for the nickname lottery.",
             PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
             None,
+            None,
         )
         .add_variant(
             Command::new(
@@ -220,14 +809,21 @@ impl Subsystem for NicknameLottery {
                 "Manage individual users' nicknames.",
                 PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
                 None,
+                None,
             )
             .add_variant(
                 Command::new(
                     "add",
                     "Add a new nickname for a user.",
                     PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             let user = get_param!(params, User, "user");
                             let user = command.data.resolved.users.get(user).unwrap();
                             let nickname = get_param!(params, String, "nickname").clone();
@@ -242,7 +838,7 @@ impl Subsystem for NicknameLottery {
                             let guild = get_guild(&data, &guild_id).unwrap();
                             let nickname_lottery_data = guild.nickname_lottery_data();
 
-                            if nickname_lottery_data.user_nicknames(&user.id).map(|nicknames| nicknames.iter().any(|nd| *nd.nickname() == nickname)).unwrap_or(false) {
+                            if nickname_lottery_data.has_nickname(&user.id, &nickname) {
                                 info!(
                                     "[Guild: {}] Nickname {nickname} for {} ({}) already exists; ignoring.",
                                     guild_id, user.name, user.id
@@ -257,13 +853,22 @@ impl Subsystem for NicknameLottery {
                             let nd = NicknameData::new(nickname.clone(), command.user.id);
 
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
                             let guild = config.guild_mut(&guild_id.clone());
                             let nickname_lottery_data = guild.nickname_lottery_data_mut();
 
+                            if nickname_lottery_data.approval_required() {
+                                nickname_lottery_data.submit_user_nickname(&user.id, nd);
+                                config.save().await;
+                                crate::drop_data_handle!(data);
+                                return Ok(Some(ActionResponse::new(
+                                    create_raw_embed(format!("**Submitted nickname {nickname} for {} for approval.**", user.mention())),
+                                    true,
+                                )));
+                            }
                             let n = nickname_lottery_data.add_user_nickname(&user.id, nd);
 
-                            config.save();
+                            config.save().await;
                             crate::drop_data_handle!(data);
 
                             let input_context = serenity::builder::CreateInputText::new(
@@ -301,7 +906,7 @@ impl Subsystem for NicknameLottery {
                                 .await
                             {
                                 let mut data = crate::acquire_data_handle!(write ctx);
-                                let config = data.get_mut::<Config>().unwrap();
+                                let config = &mut *data;
                                 let guild = config.guild_mut(&guild_id.clone());
                                 let nickname_lottery_data = guild.nickname_lottery_data_mut();
 
@@ -317,14 +922,14 @@ impl Subsystem for NicknameLottery {
                                         if it.custom_id == "nickname_context" {
                                             if let Some(it) = &it.value {
                                                 if !it.is_empty() {
-                                                    nickname_lottery_data.set_user_nickname_context(&user.id, n + 1, it.to_string());
+                                                    nickname_lottery_data.set_user_nickname_context(&user.id, n + 1, it.to_string())?;
                                                 }
                                             }
                                         }
                                     }
                                 }
 
-                                config.save();
+                                config.save().await;
 
                                 crate::drop_data_handle!(data);
 
@@ -349,7 +954,138 @@ impl Subsystem for NicknameLottery {
                 .add_option(crate::Option::new(
                     "nickname",
                     "The nickname to add for the user.",
-                    OptionType::StringInput(Some(1), Some(30)),
+                    OptionType::StringInput(Some(1), Some(MAX_NICKNAME_LENGTH as u16)),
+                    true,
+                )),
+            )
+            .add_variant(
+                Command::new(
+                    "bulk_import",
+                    "Add many nicknames for a user at once, from a JSON array of strings.",
+                    PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let user = get_param!(params, User, "user");
+                            let user = command.data.resolved.users.get(user).unwrap();
+                            let guild_id = command.guild_id.unwrap();
+                            let attachment = params
+                                .iter()
+                                .find(|opt| opt.name == "file")
+                                .and_then(|opt| opt.value.as_attachment_id())
+                                .and_then(|id| command.data.resolved.attachments.get(&id))
+                                .ok_or_else(|| crate::Error::InvalidParam("file".to_string()))?;
+                            if attachment.size > MAX_BULK_IMPORT_SIZE {
+                                return Ok(Some(ActionResponse::new(
+                                    create_raw_embed(format!(
+                                        "**Unable to import `{}`:** the file is larger than {} KiB.",
+                                        attachment.filename,
+                                        MAX_BULK_IMPORT_SIZE / 1024
+                                    )),
+                                    true,
+                                )));
+                            }
+                            let bytes = attachment.download().await?;
+                            let nicknames: Vec<String> = match serde_json::from_slice(&bytes) {
+                                Ok(nicknames) => nicknames,
+                                Err(e) => {
+                                    return Ok(Some(ActionResponse::new(
+                                        create_raw_embed(format!(
+                                            "**Unable to import `{}`:** the file isn't a JSON array of strings ({e}).",
+                                            attachment.filename
+                                        )),
+                                        true,
+                                    )));
+                                }
+                            };
+
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let nickname_lottery_data = config.guild_mut(&guild_id).nickname_lottery_data_mut();
+                            let import = nickname_lottery_data.import_user_nicknames(&user.id, nicknames, command.user.id);
+                            let verb = if nickname_lottery_data.approval_required() {
+                                "Submitted"
+                            } else {
+                                "Added"
+                            };
+                            config.save().await;
+                            crate::drop_data_handle!(data);
+                            info!(
+                                "[Guild: {}] Imported {} nicknames for {} ({}) from `{}` (author: {} ({})); skipped {}",
+                                guild_id, import.added, user.name, user.id, attachment.filename, command.user.name, command.user.id, import.skipped.len()
+                            );
+
+                            let mut resp = format!(
+                                "**{verb} {} nicknames for {}.**",
+                                import.added,
+                                user.mention()
+                            );
+                            if verb == "Submitted" {
+                                resp += " They'll need to be approved before they can be drawn.";
+                            }
+                            if let Some(skipped) = import.skipped_summary() {
+                                resp += "\n";
+                                resp += &skipped;
+                            }
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "user",
+                    "The user to add the nicknames for.",
+                    OptionType::User,
+                    true,
+                ))
+                .add_option(crate::Option::new(
+                    "file",
+                    "A JSON file containing an array of nicknames.",
+                    OptionType::Attachment,
+                    true,
+                )),
+            )
+            .add_variant(
+                Command::new(
+                    "import_truncation",
+                    "Choose whether bulk-imported nicknames which are too long are truncated or rejected.",
+                    PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let truncate = *get_param!(params, Boolean, "truncate");
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            config
+                                .guild_mut(&guild_id)
+                                .nickname_lottery_data_mut()
+                                .set_truncate_imported_nicknames(truncate);
+                            config.save().await;
+                            crate::drop_data_handle!(data);
+                            info!("[Guild: {}] Set bulk import truncation: {truncate}", guild_id);
+                            let resp = if truncate {
+                                format!("**Bulk-imported nicknames longer than {MAX_NICKNAME_LENGTH} characters will be truncated.**")
+                            } else {
+                                format!("**Bulk-imported nicknames longer than {MAX_NICKNAME_LENGTH} characters will be rejected.**")
+                            };
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "truncate",
+                    "Whether to truncate (rather than reject) nicknames which are too long.",
+                    OptionType::Boolean,
                     true,
                 )),
             )
@@ -358,8 +1094,14 @@ impl Subsystem for NicknameLottery {
                     "remove",
                     "Remove a nickname from a user.",
                     PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             let user = get_param!(params, User, "user");
                             let user = command.data.resolved.users.get(user).unwrap();
                             let n = *get_param!(params, Integer, "number");
@@ -397,14 +1139,34 @@ Consider checking their nickname list for valid number to remove.",
                             let nickname = &nickname_lottery_data.user_nicknames(&user.id).unwrap()[n as usize - 1].clone();
                             crate::drop_data_handle!(data);
 
+                            if !confirm_action(
+                                ctx,
+                                command,
+                                &format!("**Remove nickname '{}' for {}?**", nickname.nickname(), user.mention()),
+                                true,
+                            ).await {
+                                return Ok(None);
+                            }
+
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
                             let guild = config.guild_mut(&guild_id.clone());
                             let nickname_lottery_data = guild.nickname_lottery_data_mut();
 
+                            // the list may have changed whilst awaiting confirmation
+                            if nickname_lottery_data.user_nicknames(&user.id).and_then(|nicknames| nicknames.get(n as usize - 1)).map(|current| current.nickname()) != Some(nickname.nickname()) {
+                                crate::drop_data_handle!(data);
+                                return Ok(Some(ActionResponse::new(
+                                    create_raw_embed(format!("**{}'s nicknames changed whilst confirming**
+Nothing was removed; check their nickname list and try again.",
+                                        user.mention())),
+                                    true,
+                                )));
+                            }
+
                             nickname_lottery_data.remove_user_nickname(&user.id, n as usize);
 
-                            config.save();
+                            config.save().await;
 
                             crate::drop_data_handle!(data);
 
@@ -412,6 +1174,7 @@ Consider checking their nickname list for valid number to remove.",
                                 create_raw_embed(
                                     format!("**Removed nickname '{}' for {}**
 Originally added by {} ({})
+Drawn {} time(s)
 **Context:**
 {}",
                                     nickname.nickname(), user.mention(),
@@ -421,6 +1184,7 @@ Originally added by {} ({})
                                     nickname.time()
                                             .map(|time| format!("<t:{}:F>", time.timestamp()))
                                             .unwrap_or("`time not known`".to_string()),
+                                    nickname.use_count(),
                                     nickname.context()
                                             .unwrap_or(&"No context provided.".to_string()),
                                     )
@@ -448,8 +1212,14 @@ Originally added by {} ({})
                     "set_context",
                     "Set context for a user's nickname.",
                     PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             let user = get_param!(params, User, "user");
                             let user = command.data.resolved.users.get(user).unwrap();
                             let n = *get_param!(params, Integer, "number");
@@ -523,7 +1293,7 @@ Consider checking their nickname list for valid number to remove.",
                                 .await
                             {
                                 let mut data = crate::acquire_data_handle!(write ctx);
-                                let config = data.get_mut::<Config>().unwrap();
+                                let config = &mut *data;
                                 let guild = config.guild_mut(&guild_id.clone());
                                 let nickname_lottery_data = guild.nickname_lottery_data_mut();
 
@@ -539,14 +1309,14 @@ Consider checking their nickname list for valid number to remove.",
                                         if it.custom_id == "nickname_context" {
                                             if let Some(it) = &it.value {
                                                 if !it.is_empty() {
-                                                    nickname_lottery_data.set_user_nickname_context(&user.id, n as usize, it.to_string());
+                                                    nickname_lottery_data.set_user_nickname_context(&user.id, n as usize, it.to_string())?;
                                                 }
                                             }
                                         }
                                     }
                                 }
 
-                                config.save();
+                                config.save().await;
 
                                 crate::drop_data_handle!(data);
 
@@ -576,29 +1346,271 @@ Consider checking their nickname list for valid number to remove.",
             )
             .add_variant(
                 Command::new(
-                    "info",
-                    "Get more information about a nickname for a user.",
-                    PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                    "set_weight",
+                    "Set how likely a user's nickname is to be drawn, relative to their others.",
+                    PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             let user = get_param!(params, User, "user");
                             let user = command.data.resolved.users.get(user).unwrap();
                             let n = *get_param!(params, Integer, "number");
+                            let weight = *get_param!(params, Integer, "weight");
                             let guild_id = command.guild_id.unwrap();
 
-                            if n < 1 {
+                            let (Ok(n), Ok(weight)) = (usize::try_from(n), u32::try_from(weight)) else {
                                 return Ok(Some(ActionResponse::new(
-                                        create_raw_embed("**`number` must be greater than 0**
-Check the user's nickname list for valid numbers!"),
-                                        true,
-                                    )))
-                            }
-
-                            let data = crate::acquire_data_handle!(read ctx);
-                            let guild = get_guild(&data, &guild_id).unwrap();
-                            let nickname_lottery_data = guild.nickname_lottery_data();
+                                    create_raw_embed(format!("**`number` must be greater than 0, and `weight` between {MIN_WEIGHT} and {MAX_WEIGHT}.**")),
+                                    true,
+                                )));
+                            };
 
-                            if nickname_lottery_data.user_nicknames(&user.id).map(|nicknames| n as usize > nicknames.len()).unwrap_or(true) {
+                            info!(
+                                "[Guild: {}] Setting weight of nickname #{n} for {} ({}) to {weight}",
+                                guild_id, user.name, user.id,
+                            );
+
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let nickname_lottery_data = config.guild_mut(&guild_id).nickname_lottery_data_mut();
+                            let resp = match nickname_lottery_data.set_user_nickname_weight(&user.id, n, weight) {
+                                Ok(()) => {
+                                    let nickname = nickname_lottery_data.user_nicknames(&user.id).unwrap()[n - 1].nickname().clone();
+                                    config.save().await;
+                                    format!("**Set the weight of nickname '{nickname}' for {} to {weight}.**", user.mention())
+                                }
+                                Err(crate::Error::InvalidParam(e)) => format!("**Unable to set the weight:** {e}.
+Check the user's nickname list for valid numbers!"),
+                                Err(e) => return Err(e),
+                            };
+                            crate::drop_data_handle!(data);
+
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "user",
+                    "The user whose nickname to set the weight of.",
+                    OptionType::User,
+                    true,
+                ))
+                .add_option(crate::Option::new(
+                    "number",
+                    "The number of the nickname, as reported in the user's nickname list.",
+                    OptionType::IntegerInput(Some(1), None),
+                    true,
+                ))
+                .add_option(crate::Option::new(
+                    "weight",
+                    "How likely the nickname is to be drawn, relative to the user's others (default 1).",
+                    OptionType::IntegerInput(Some(MIN_WEIGHT.into()), Some(MAX_WEIGHT.into())),
+                    true,
+                )),
+            )
+            .add_variant(
+                Command::new(
+                    "set_expiry",
+                    "Set when a user's nickname is removed, for nicknames only appropriate for a while.",
+                    PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let user = get_param!(params, User, "user");
+                            let user = command.data.resolved.users.get(user).unwrap();
+                            let n = *get_param!(params, Integer, "number");
+                            let timestamp = *get_param!(params, Integer, "timestamp");
+                            let guild_id = command.guild_id.unwrap();
+
+                            let (Ok(n), Some(expires_at)) = (usize::try_from(n), DateTime::from_timestamp(timestamp, 0)) else {
+                                return Ok(Some(ActionResponse::new(
+                                    create_raw_embed("**`number` must be greater than 0, and `timestamp` a valid Unix timestamp.**"),
+                                    true,
+                                )));
+                            };
+
+                            info!(
+                                "[Guild: {}] Setting expiry of nickname #{n} for {} ({}) to {expires_at}",
+                                guild_id, user.name, user.id,
+                            );
+
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let nickname_lottery_data = config.guild_mut(&guild_id).nickname_lottery_data_mut();
+                            let resp = match nickname_lottery_data.set_user_nickname_expiry(&user.id, n, expires_at) {
+                                Ok(()) => {
+                                    let nickname = nickname_lottery_data.user_nicknames(&user.id).unwrap()[n - 1].nickname().clone();
+                                    config.save().await;
+                                    format!("**Nickname '{nickname}' for {} will be removed after <t:{timestamp}:F>.**", user.mention())
+                                }
+                                Err(crate::Error::InvalidParam(e)) => format!("**Unable to set the expiry:** {e}.
+Check the user's nickname list for valid numbers!"),
+                                Err(e) => return Err(e),
+                            };
+                            crate::drop_data_handle!(data);
+
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "user",
+                    "The user whose nickname to set the expiry of.",
+                    OptionType::User,
+                    true,
+                ))
+                .add_option(crate::Option::new(
+                    "number",
+                    "The number of the nickname, as reported in the user's nickname list.",
+                    OptionType::IntegerInput(Some(1), None),
+                    true,
+                ))
+                .add_option(crate::Option::new(
+                    "timestamp",
+                    "When to remove the nickname, as a Unix timestamp (in seconds).",
+                    OptionType::IntegerInput(Some(0), None),
+                    true,
+                )),
+            )
+            .add_variant(
+                Command::new(
+                    "exclude",
+                    "Exclude a user from the nickname lottery, keeping their nicknames.",
+                    PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let user = get_param!(params, User, "user");
+                            let user = command.data.resolved.users.get(user).unwrap();
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let nickname_lottery_data = config.guild_mut(&guild_id).nickname_lottery_data_mut();
+                            let mut resp = if nickname_lottery_data.exclude_user(&user.id) {
+                                info!("[Guild: {}] Excluding {} ({}) from the lottery", guild_id, user.name, user.id);
+                                format!("**Excluded {} from the nickname lottery.**", user.mention())
+                            } else {
+                                format!("{} is already excluded from the nickname lottery.", user.mention())
+                            };
+                            if nickname_lottery_data.user_nicknames(&user.id).is_none() {
+                                resp += "\nNote that they have no nicknames anyway.";
+                            }
+                            config.save().await;
+                            crate::drop_data_handle!(data);
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "user",
+                    "The user to exclude.",
+                    OptionType::User,
+                    true,
+                )),
+            )
+            .add_variant(
+                Command::new(
+                    "unexclude",
+                    "Include a previously excluded user in the nickname lottery again.",
+                    PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let user = get_param!(params, User, "user");
+                            let user = command.data.resolved.users.get(user).unwrap();
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let resp = if config.guild_mut(&guild_id).nickname_lottery_data_mut().unexclude_user(&user.id) {
+                                info!("[Guild: {}] Including {} ({}) in the lottery again", guild_id, user.name, user.id);
+                                config.save().await;
+                                format!("**{} is back in the nickname lottery.**", user.mention())
+                            } else {
+                                format!("{} isn't excluded from the nickname lottery.", user.mention())
+                            };
+                            crate::drop_data_handle!(data);
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "user",
+                    "The user to include again.",
+                    OptionType::User,
+                    true,
+                )),
+            )
+            .add_variant(Command::new(
+                "list_excluded",
+                "List the users excluded from the nickname lottery.",
+                PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                None,
+                Some(Box::new(move |ctx, command, _params| {
+                    Box::pin(async move {
+                        let excluded = crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| {
+                            let mut excluded: Vec<UserId> = guild.nickname_lottery_data().excluded_users().collect();
+                            excluded.sort();
+                            excluded
+                        })
+                        .unwrap_or_default();
+                        let resp = if excluded.is_empty() {
+                            "Nobody is excluded from the nickname lottery.".to_string()
+                        } else {
+                            format!(
+                                "**Excluded from the nickname lottery:**\n{}",
+                                excluded.iter().map(|user| user.mention().to_string()).collect::<Vec<_>>().join("\n")
+                            )
+                        };
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            ))
+            .add_variant(
+                Command::new(
+                    "info",
+                    "Get more information about a nickname for a user.",
+                    PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            let user = get_param!(params, User, "user");
+                            let user = command.data.resolved.users.get(user).unwrap();
+                            let n = *get_param!(params, Integer, "number");
+                            let guild_id = command.guild_id.unwrap();
+
+                            if n < 1 {
+                                return Ok(Some(ActionResponse::new(
+                                        create_raw_embed("**`number` must be greater than 0**
+Check the user's nickname list for valid numbers!"),
+                                        true,
+                                    )))
+                            }
+
+                            let data = crate::acquire_data_handle!(read ctx);
+                            let guild = get_guild(&data, &guild_id).unwrap();
+                            let nickname_lottery_data = guild.nickname_lottery_data();
+
+                            if nickname_lottery_data.user_nicknames(&user.id).map(|nicknames| n as usize > nicknames.len()).unwrap_or(true) {
                                 info!(
                                     "[Guild: {}] Nickname #{n} does not exist for {} ({}); ignoring.",
                                     guild_id, user.name, user.id
@@ -616,7 +1628,7 @@ Consider checking their nickname list for valid number to remove.",
                             Ok(Some(ActionResponse::new(
                                 create_raw_embed(
                                     format!("**Nickname '{}' for {}**
-Originally added by {} ({})
+Originally added by {} ({}){}
 **Context:**
 {}",
                                     nickname.nickname(), user.mention(),
@@ -626,6 +1638,9 @@ Originally added by {} ({})
                                     nickname.time()
                                             .map(|time| format!("<t:{}:F>", time.timestamp()))
                                             .unwrap_or("`time not known`".to_string()),
+                                    nickname.expires_at()
+                                            .map(|time| format!("\nExpires <t:{}:F>", time.timestamp()))
+                                            .unwrap_or_default(),
                                     nickname.context()
                                             .unwrap_or(&"No context provided.".to_string()),
                                     )
@@ -653,32 +1668,13 @@ Originally added by {} ({})
                     "list",
                     "List all nicknames set for the user.",
                     PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                    None,
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async {
                             let user = get_param!(params, User, "user");
                             let user = command.data.resolved.users.get(user).unwrap();
-                            let data = crate::acquire_data_handle!(read ctx);
-                            if let Some(guild) = get_guild(&data, &command.guild_id.unwrap()) {
-                                let lottery_data = guild.nickname_lottery_data();
-                                if let Some(nicknames) = lottery_data.user_nicknames(&user.id) {
-                                    let mut list = format!("**Nicknames for {}**", user.mention());
-                                    for (i, nickname) in nicknames.iter().enumerate() {
-                                        list += &format!("\n{}. {}", i + 1, nickname.nickname());
-                                    }
-                                    Ok(Some(ActionResponse::new(
-                                        create_raw_embed(list),
-                                        true,
-                                    )))
-                                } else {
-                                    Ok(Some(ActionResponse::new(
-                                        create_raw_embed(format!("{} has no nicknames in this server.", user.mention())),
-                                        true,
-                                    )))
-                                }
-                            } else {
-                                error!("Guild command called in an unitialised guild {}", command.guild_id.unwrap());
-                                Ok(None)
-                            }
+                            send_nickname_list(ctx, command, user).await?;
+                            Ok(None)
                         })
                     })),
                 )
@@ -696,23 +1692,37 @@ Originally added by {} ({})
                 "The frequency at which the nickname lottery can occur, changing a single random user's nickname.",
                 PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
                 None,
+                None,
             )
             .add_variant(
                 Command::new(
                     "set",
                     "Set a custom interval range for this server. Does not affect April Fool's day.",
                     PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             let min = get_param!(params, Integer, "min");
                             let max = get_param!(params, Integer, "max");
 
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
                             let guild = config.guild_mut(&command.guild_id.unwrap());
                             let nickname_lottery_data = guild.nickname_lottery_data_mut();
-                            nickname_lottery_data.set_refresh_interval(Some((*min as u64, *max as u64)));
-                            config.save();
+                            if let Err(e) = nickname_lottery_data.set_refresh_interval(Some((*min as u64, *max as u64))) {
+                                crate::drop_data_handle!(data);
+                                return Ok(Some(ActionResponse::new(
+                                    create_raw_embed(format!("**Could not update refresh interval**
+{e}")),
+                                    true,
+                                )));
+                            }
+                            config.save().await;
                             crate::drop_data_handle!(data);
 
                             let format_time = |secs| -> String {
@@ -752,14 +1762,20 @@ Maximum time between lotteries: {}",
                     "reset",
                     "Revert back to the default interval.",
                     PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
                     Some(Box::new(move |ctx, command, _params| {
                         Box::pin(async {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
                             let guild = config.guild_mut(&command.guild_id.unwrap());
                             let lottery_data = guild.nickname_lottery_data_mut();
-                            lottery_data.set_refresh_interval(None);
-                            config.save();
+                            lottery_data.set_refresh_interval(None)?;
+                            config.save().await;
                             crate::drop_data_handle!(data);
 
                             Ok(Some(ActionResponse::new(
@@ -777,20 +1793,27 @@ Maximum time between lotteries: {}",
                 "Commands to manage nickname lottery announcements.",
                 PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
                 None,
+                None,
             )
             .add_variant(
                 Command::new(
                     "configure",
                     "Configure announcements when the bot fails to change a user's nickname.",
                     PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                    None,
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_CHANNELS)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             // Set announcement channel if it's been supplied.
                             if let Some(channel_opt) =
                                 params.iter().find(|opt| opt.name == "channel")
                             {
                                 let mut data = crate::acquire_data_handle!(write ctx);
-                                let config = data.get_mut::<Config>().unwrap();
+                                let config = &mut *data;
                                 let guild = config.guild_mut(&command.guild_id.unwrap());
                                 if let CommandDataOptionValue::Channel(channel) = &channel_opt.value
                                 {
@@ -798,7 +1821,7 @@ Maximum time between lotteries: {}",
                                     guild
                                         .nickname_lottery_data_mut()
                                         .set_channel(Some(channel.id()));
-                                    config.save();
+                                    config.save().await;
                                 }
                             };
 
@@ -807,7 +1830,7 @@ Maximum time between lotteries: {}",
                                 params.iter().find(|opt| opt.name == "title_override")
                             {
                                 let mut data = crate::acquire_data_handle!(write ctx);
-                                let config = data.get_mut::<Config>().unwrap();
+                                let config = &mut *data;
                                 let guild = config.guild_mut(&command.guild_id.unwrap());
                                 let lottery_data = guild.nickname_lottery_data_mut();
                                 if let CommandDataOptionValue::String(title_override) =
@@ -815,7 +1838,7 @@ Maximum time between lotteries: {}",
                                 {
                                     lottery_data
                                         .set_title_override(Some(title_override.to_owned()));
-                                    config.save();
+                                    config.save().await;
                                 }
                             };
 
@@ -840,7 +1863,7 @@ Title text: {}",
                 .add_option(crate::command::Option::new(
                     "channel",
                     "The channel to announce timeouts in.",
-                    OptionType::Channel(Some(vec![ChannelType::Text])),
+                    OptionType::Channel(Some(ChannelTypes::TEXT)),
                     false,
                 ))
                 .add_option(crate::command::Option::new(
@@ -854,15 +1877,21 @@ Title text: {}",
                 "stop",
                 "Stop all announcements. Unsets all configuration values.",
                 PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
                 Some(Box::new(move |ctx, command, _params| {
                     Box::pin(async {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_CHANNELS)
+                        {
+                            return Ok(Some(refusal));
+                        }
                         let mut data = crate::acquire_data_handle!(write ctx);
-                        let config = data.get_mut::<Config>().unwrap();
+                        let config = &mut *data;
                         let guild = config.guild_mut(&command.guild_id.unwrap());
                         let lottery_data = guild.nickname_lottery_data_mut();
                         lottery_data.set_channel(None);
                         lottery_data.set_title_override(None);
-                        config.save();
+                        config.save().await;
                         crate::drop_data_handle!(data);
 
                         Ok(Some(ActionResponse::new(
@@ -872,201 +1901,941 @@ Title text: {}",
                     })
                 })),
             )),
-        )]
-    }
-}
-
-impl NicknameLottery {
-    pub async fn guild_init(ctx: Context, g: Guild) {
-        // between 30 minutes and 5 days
-        let mut interval = DEFAULT_REFRESH_INTERVAL;
-        let mut between = rand::distributions::Uniform::from(
-            DEFAULT_REFRESH_INTERVAL.0..DEFAULT_REFRESH_INTERVAL.1,
-        );
-        loop {
-            // Use a different distribution if the guild's set a different refresh interval.
-            let data = crate::acquire_data_handle!(read ctx);
-            if let Some(guild) = get_guild(&data, &g.id) {
-                let lottery_data = guild.nickname_lottery_data();
-                let i = if let Some(i) = lottery_data.refresh_interval() {
-                    *i
-                } else {
-                    DEFAULT_REFRESH_INTERVAL
-                };
-                if interval.0 != i.0 || interval.1 != i.1 {
-                    interval = i;
-                    between = rand::distributions::Uniform::from(interval.0..interval.1);
-                }
-            }
-            crate::drop_data_handle!(data);
-            let now = chrono::Utc::now();
-            let is_april_fools = now.month() == 4 && now.day() == 1;
-            if cfg!(not(debug_assertions)) {
-                let mut tts = Duration::from_secs(between.sample(&mut rand::thread_rng()));
-                // It's April Fool's! Force the minimum refresh interval.
-                if is_april_fools {
-                    tts = Duration::from_secs(1_800);
-                } else if now.month() < 4 {
-                    let ctts = chrono::Duration::from_std(tts);
-                    match ctts {
-                        Ok(ctts) => {
-                            if (now + ctts).month() >= 4 {
-                                // Current reset timer will either cross into, or completely skip, April Fool's.
-                                // Clamp to time until April Fool's.
-                                tts = match chrono::Utc
-                                    .with_ymd_and_hms(now.year(), 4, 1, 0, 0, 0)
-                                    .unwrap()
-                                    .signed_duration_since(now)
-                                    .to_std()
-                                {
-                                    Ok(tts) => tts,
-                                    Err(e) => {
-                                        #[cfg(feature = "events")]
-                                        notify_subscribers(
-                                            &ctx,
-                                            Event::Error,
-                                            &format!(
-                                                "**[Guild: {}] Error calculating time until next nickname change:**
-{e}
-
-_Nickname changes are disabled for this guild until next initialisation._",
-                                                g.id
-                                            ),
-                                        )
-                                        .await;
-                                        panic!("OutOfRangeError during reset time calculation.");
-                                    }
-                                };
-                            }
-                        }
-                        Err(e) => {
-                            #[cfg(feature = "events")]
-                            notify_subscribers(
-                                &ctx,
-                                Event::Error,
-                                &format!(
-                                    "**[Guild: {}] Error calculating time until next nickname change:**
-{e}
-
-_Nickname changes are disabled for this guild until next initialisation._",
-                                    g.id
-                                ),
-                            )
-                            .await;
-                            panic!("OutOfRangeError during reset time calculation.");
-                        }
-                    }
-                }
-                info!(
-                    "[Guild: {}] Next nickname change in {} minutes.",
-                    g.id,
-                    (tts.as_secs() / 60)
-                );
-                tokio::time::sleep(tts).await;
-            } else {
-                info!(
-                    "[Guild: {}] Running nickname lottery immediately once, for debugging.",
-                    g.id
-                );
-            }
-            // Time to update a user's nickname!
-            let data = crate::acquire_data_handle!(read ctx);
-            if let Some(guild) = get_guild(&data, &g.id) {
-                let lottery_data = guild.nickname_lottery_data();
-                if let Some(user) = lottery_data.get_random_user() {
-                    if let Ok(member) = g.member(&ctx, user).await {
-                        let user = &member.user;
-                        if let Some(mut new_nick) =
-                            lottery_data.get_nickname_for_user(&user.id).cloned()
+        )
+        .add_variant(
+            Command::new(
+                "force_change",
+                "Change a user's nickname to one of theirs now, rather than waiting for the lottery.",
+                PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_NICKNAMES)
                         {
-                            let old_nick = member.display_name();
-                            // If feature `stream-indicator` is enabled, we want to preserve any applied streaming prefix, in case we're changing the nickname mid-stream.
-                            #[cfg(feature = "stream-indicator")]
-                            if old_nick
-                                .starts_with(crate::subsystems::stream_indicator::STREAMING_PREFIX)
-                            {
-                                new_nick = crate::subsystems::stream_indicator::STREAMING_PREFIX
-                                    .to_string()
-                                    + &new_nick;
-                            }
-                            if old_nick == new_nick {
-                                info!("[Guild: {}] Skipping nickname change for {} ({}) as they pulled the same as current: {}.", &g.id, &user.id, &old_nick, &new_nick);
-                                continue;
-                            }
-                            info!(
-                                "[Guild: {}] Updating {}'s nickname to {} (current: {})",
-                                &g.id, &user.id, &new_nick, &old_nick
-                            );
-                            let mut post_name_change = is_april_fools;
-                            if let Err(e) = g
-                                .edit_member(
-                                    &ctx,
-                                    user.id,
-                                    serenity::all::EditMember::new().nickname(&new_nick),
-                                )
-                                .await
+                            return Ok(Some(refusal));
+                        }
+                        let user = get_param!(params, User, "user");
+                        let user = command.data.resolved.users.get(user).unwrap();
+                        let guild_id = command.guild_id.unwrap();
+                        let Some(g) = ctx.cache.guild(guild_id).map(|g| g.clone()) else {
+                            return Err(crate::Error::InvalidParam("guild".to_string()));
+                        };
+                        info!(
+                            "[Guild: {}] Forcing a nickname change for {} ({})",
+                            guild_id, user.name, user.id
+                        );
+                        let resp = NicknameLottery::force_change(ctx, &g, user.id).await;
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(crate::Option::new(
+                "user",
+                "The user whose nickname to change.",
+                OptionType::User,
+                true,
+            )),
+        )
+        .add_variant(
+            Command::new(
+                "global_pool",
+                "Manage the nicknames drawn for users who have none of their own.",
+                PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                None,
+                None,
+            )
+            .add_variant(
+                Command::new(
+                    "add",
+                    "Add a nickname to the global pool.",
+                    PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
                             {
-                                post_name_change = true;
-                                warn!(
-                                    "[Guild: {}] Error changing {}'s nickname:
-{e}",
-                                    g.id, user.id
-                                );
+                                return Ok(Some(refusal));
                             }
-                            if post_name_change {
-                                if let Some(channel_id) = lottery_data.channel() {
-                                    let channel = match channel_id.to_channel(&ctx).await {
-                                        Ok(channel) => channel.guild(),
-                                        Err(_) => None,
-                                    };
-                                    if let Some(channel) = channel {
-                                        channel
-                                            .send_message(
-                                                &ctx,
-                                                create_embed(format!(
-                                                    "**{}**
-{} won/lost the lottery! From now on, they are to be named: `{}`",
-                                                    lottery_data.title(),
-                                                    user.mention(),
-                                                    new_nick,
-                                                )),
-                                            )
-                                            .await
-                                            .unwrap();
-                                    } else {
-                                        #[cfg(feature = "events")]
-                                        notify_subscribers_with_handle(
-                                            &ctx,
-                                            &data,
-                                            Event::Error,
-                                            &format!(
-                                                "**[Guild: {}] Invalid complaints channel.**",
-                                                g.id,
-                                            ),
-                                        )
-                                        .await;
-                                        error!("[Guild: {}] Invalid complaints channel.", g.id);
-                                        continue;
-                                    }
+                            let nickname = get_param!(params, String, "nickname").clone();
+                            let weight = params
+                                .iter()
+                                .find(|opt| opt.name == "weight")
+                                .and_then(|opt| opt.value.as_i64())
+                                .map_or(DEFAULT_WEIGHT, |weight| weight as u32);
+                            let guild_id = command.guild_id.unwrap();
+                            let mut nd = NicknameData::new(nickname.clone(), command.user.id);
+                            nd.weight = weight;
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let resp = match config.guild_mut(&guild_id).nickname_lottery_data_mut().add_global_nickname(nd) {
+                                Ok(n) => {
+                                    config.save().await;
+                                    info!(
+                                        "[Guild: {}] Adding global nickname {nickname} (author: {} ({}))",
+                                        guild_id, command.user.name, command.user.id
+                                    );
+                                    format!("**Added nickname #{}, `{nickname}`, to the global pool.**", n + 1)
                                 }
+                                Err(e) => e.to_string(),
+                            };
+                            crate::drop_data_handle!(data);
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "nickname",
+                    "The nickname to add.",
+                    OptionType::StringInput(Some(1), Some(MAX_NICKNAME_LENGTH as u16)),
+                    true,
+                ))
+                .add_option(crate::Option::new(
+                    "weight",
+                    "How likely the nickname is to be drawn, relative to the others (default 1).",
+                    OptionType::IntegerInput(Some(MIN_WEIGHT.into()), Some(MAX_WEIGHT.into())),
+                    false,
+                )),
+            )
+            .add_variant(
+                Command::new(
+                    "remove",
+                    "Remove a nickname from the global pool.",
+                    PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                            {
+                                return Ok(Some(refusal));
                             }
+                            let n = *get_param!(params, Integer, "number") as usize;
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let resp = match config.guild_mut(&guild_id).nickname_lottery_data_mut().remove_global_nickname(n) {
+                                Ok(nickname) => {
+                                    config.save().await;
+                                    info!("[Guild: {}] Removing global nickname {}", guild_id, nickname.nickname());
+                                    format!("**Removed `{}` from the global pool.**", nickname.nickname())
+                                }
+                                Err(e) => e.to_string(),
+                            };
+                            crate::drop_data_handle!(data);
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "number",
+                    "The number of the nickname to remove, as in `/nickname_lottery global_pool list`.",
+                    OptionType::IntegerInput(Some(1), None),
+                    true,
+                )),
+            )
+            .add_variant(Command::new(
+                "list",
+                "List the nicknames in the global pool.",
+                PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                None,
+                Some(Box::new(move |ctx, command, _params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                        {
+                            return Ok(Some(refusal));
                         }
-                    }
-                }
+                        let list = crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| {
+                            NicknameListDisplay(guild.nickname_lottery_data().global_nicknames()).to_string()
+                        })
+                        .unwrap_or_default();
+                        let paginated = if list.is_empty() {
+                            PaginatedEmbed::new(vec![create_raw_embed("The global pool is empty.")])
+                        } else {
+                            PaginatedEmbed::paginate(list.lines().map(String::from), NICKNAMES_PER_PAGE)
+                                .title("Global nickname pool")
+                        };
+                        paginated.send(ctx, command, true).await?;
+                        Ok(None)
+                    })
+                })),
+            )),
+        )
+        .add_variant(
+            Command::new(
+                "max_rerolls",
+                "Set how many times a nickname is redrawn when it's the same as the user's current one.",
+                PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let attempts = *get_param!(params, Integer, "attempts") as u32;
+                        let guild_id = command.guild_id.unwrap();
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let resp = match config
+                            .guild_mut(&guild_id)
+                            .nickname_lottery_data_mut()
+                            .set_max_reroll_attempts(attempts)
+                        {
+                            Ok(()) => {
+                                config.save().await;
+                                info!("[Guild: {}] Set max reroll attempts: {attempts}", guild_id);
+                                format!("**Nicknames will be redrawn up to {attempts} times when they're the same as the user's current one.**")
+                            }
+                            Err(e) => e.to_string(),
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(crate::Option::new(
+                "attempts",
+                "How many times to redraw (0 to never redraw).",
+                OptionType::IntegerInput(Some(0), Some(MAX_REROLL_ATTEMPTS.into())),
+                true,
+            )),
+        )
+        .add_variant(
+            Command::new(
+                "pending",
+                "Manage nicknames awaiting approval.",
+                PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                None,
+                None,
+            )
+            .add_variant(Command::new(
+                "list",
+                "List the nicknames awaiting approval.",
+                PermissionType::ServerPerms(Permissions::MANAGE_NICKNAMES),
+                None,
+                Some(Box::new(move |ctx, command, _params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_NICKNAMES)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let lines = crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| {
+                            let mut pending: Vec<_> = guild.nickname_lottery_data().pending_nicknames().collect();
+                            pending.sort_by_key(|(user, _)| *user);
+                            pending
+                                .into_iter()
+                                .flat_map(|(user, nicknames)| {
+                                    nicknames.iter().enumerate().map(move |(i, nickname)| {
+                                        let author = nickname
+                                            .author()
+                                            .map(|author| format!(" (submitted by {})", author.mention()))
+                                            .unwrap_or_default();
+                                        format!("{} #{}: `{}`{author}", user.mention(), i + 1, nickname.nickname())
+                                    })
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default();
+                        let paginated = if lines.is_empty() {
+                            PaginatedEmbed::new(vec![create_raw_embed("No nicknames are awaiting approval.")])
+                        } else {
+                            PaginatedEmbed::paginate(lines.into_iter(), NICKNAMES_PER_PAGE).title("Pending nicknames")
+                        };
+                        paginated.send(ctx, command, true).await?;
+                        Ok(None)
+                    })
+                })),
+            ))
+            .add_variant(
+                Command::new(
+                    "approve",
+                    "Approve a pending nickname, so that it can be drawn.",
+                    PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::ADMINISTRATOR)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let user = get_param!(params, User, "user");
+                            let user = command.data.resolved.users.get(user).unwrap();
+                            let n = *get_param!(params, Integer, "number") as usize;
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let result = config
+                                .guild_mut(&guild_id)
+                                .nickname_lottery_data_mut()
+                                .approve_pending_nickname(&user.id, n);
+                            let resp = match result {
+                                Ok(nickname) => {
+                                    config.save().await;
+                                    info!(
+                                        "[Guild: {}] Approved pending nickname {nickname} for {} ({})",
+                                        guild_id, user.name, user.id
+                                    );
+                                    format!("**Approved nickname `{nickname}` for {}.**", user.mention())
+                                }
+                                Err(e) => e.to_string(),
+                            };
+                            crate::drop_data_handle!(data);
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "user",
+                    "The user the nickname was submitted for.",
+                    OptionType::User,
+                    true,
+                ))
+                .add_option(crate::Option::new(
+                    "number",
+                    "The number of the pending nickname to approve, as in `/nickname_lottery pending list`.",
+                    OptionType::IntegerInput(Some(1), None),
+                    true,
+                )),
+            )
+            .add_variant(
+                Command::new(
+                    "reject",
+                    "Reject a pending nickname, discarding it.",
+                    PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::ADMINISTRATOR)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let user = get_param!(params, User, "user");
+                            let user = command.data.resolved.users.get(user).unwrap();
+                            let n = *get_param!(params, Integer, "number") as usize;
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let result = config
+                                .guild_mut(&guild_id)
+                                .nickname_lottery_data_mut()
+                                .reject_pending_nickname(&user.id, n);
+                            let resp = match result {
+                                Ok(nickname) => {
+                                    config.save().await;
+                                    info!(
+                                        "[Guild: {}] Rejected pending nickname {nickname} for {} ({})",
+                                        guild_id, user.name, user.id
+                                    );
+                                    format!("**Rejected nickname `{nickname}` for {}.**", user.mention())
+                                }
+                                Err(e) => e.to_string(),
+                            };
+                            crate::drop_data_handle!(data);
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "user",
+                    "The user the nickname was submitted for.",
+                    OptionType::User,
+                    true,
+                ))
+                .add_option(crate::Option::new(
+                    "number",
+                    "The number of the pending nickname to reject, as in `/nickname_lottery pending list`.",
+                    OptionType::IntegerInput(Some(1), None),
+                    true,
+                )),
+            )
+            .add_variant(
+                Command::new(
+                    "require_approval",
+                    "Choose whether new nicknames must be approved before they can be drawn.",
+                    PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                    None,
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::ADMINISTRATOR)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let required = *get_param!(params, Boolean, "required");
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            config
+                                .guild_mut(&guild_id)
+                                .nickname_lottery_data_mut()
+                                .set_approval_required(required);
+                            config.save().await;
+                            crate::drop_data_handle!(data);
+                            info!("[Guild: {}] Set nickname approval required: {required}", guild_id);
+                            let resp = if required {
+                                "**New nicknames must now be approved before they can be drawn.**"
+                            } else {
+                                "**New nicknames no longer need approval.** Any already pending still do."
+                            };
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                        })
+                    })),
+                )
+                .add_option(crate::Option::new(
+                    "required",
+                    "Whether new nicknames must be approved.",
+                    OptionType::Boolean,
+                    true,
+                )),
+            ),
+        ),
+        Command::new(
+            "Get nickname list",
+            "",
+            PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async {
+                    let user = target_user(command).ok_or(crate::Error::InvalidUser)?;
+                    send_nickname_list(ctx, command, user).await?;
+                    Ok(None)
+                })
+            })),
+        )
+        .with_kind(CommandKind::UserContextMenu)]
+    }
+
+    fn required_intents(&self, config: &crate::Config) -> GatewayIntents {
+        // The guild's members are only needed to draw from the global pool, so the (privileged)
+        // intent is only requested if a guild has one.
+        if config
+            .guild_data()
+            .any(|guild| !guild.nickname_lottery_data().global_nicknames().is_empty())
+        {
+            GatewayIntents::GUILD_MEMBERS
+        } else {
+            GatewayIntents::empty()
+        }
+    }
+}
+
+/// Respond ephemerally to `command` with `user`'s nicknames in the guild it was run in, split
+/// into pages of [NICKNAMES_PER_PAGE].
+async fn send_nickname_list(
+    ctx: &Context,
+    command: &CommandInteraction,
+    user: &User,
+) -> crate::Result<()> {
+    let guild_id = command.guild_id.unwrap();
+    let list = crate::with_guild!(ctx, &guild_id, |guild| {
+        let lottery_data = guild.nickname_lottery_data();
+        (
+            lottery_data
+                .user_nicknames(&user.id)
+                .map(|nicknames| NicknameListDisplay(nicknames).to_string()),
+            lottery_data.nickname_source(&user.id),
+            !lottery_data.global_nicknames().is_empty(),
+        )
+    });
+    let paginated = match list {
+        Some((Some(list), _, has_global_pool)) => {
+            let mut lines: Vec<String> = list.lines().map(String::from).collect();
+            if has_global_pool {
+                lines.push("*These are drawn from instead of the global pool.*".to_string());
             }
+            PaginatedEmbed::paginate(lines.into_iter(), NICKNAMES_PER_PAGE)
+                .title(format!("Nicknames for {}", user.name))
+        }
+        Some((None, NicknameSource::Global, _)) => PaginatedEmbed::new(vec![create_raw_embed(format!(
+            "{} has no nicknames of their own, so draws from the global pool (see `/nickname_lottery global_pool list`).",
+            user.mention()
+        ))]),
+        Some((None, _, _)) => PaginatedEmbed::new(vec![create_raw_embed(format!(
+            "{} has no nicknames in this server.",
+            user.mention()
+        ))]),
+        None => {
+            error!("Guild command called in an unitialised guild {guild_id}");
+            return Ok(());
+        }
+    };
+    paginated.send(ctx, command, true).await
+}
+
+impl NicknameLottery {
+    /// Announce the upcoming April Fool's acceleration in the guild's announcement channel, if one is configured.
+    /// This is only posted once per year, regardless of how many times the refresh timer is clamped.
+    async fn announce_april_fools(
+        ctx: &Context,
+        backend: &dyn Backend,
+        g: &Guild,
+        april_fools: DateTime<Utc>,
+    ) {
+        let year = april_fools.year() as u32;
+        let mut data = crate::acquire_data_handle!(write ctx);
+        let config = &mut *data;
+        let lottery_data = config.guild_mut(&g.id).nickname_lottery_data_mut();
+        if lottery_data.notified_april_fools() == Some(year) {
+            crate::drop_data_handle!(data);
+            return;
+        }
+        let channel_id = if let Some(channel_id) = lottery_data.channel() {
+            channel_id
+        } else {
+            crate::drop_data_handle!(data);
+            return;
+        };
+        lottery_data.set_notified_april_fools(year);
+        config.save().await;
+        crate::drop_data_handle!(data);
+
+        info!(
+            "[Guild: {}] Announcing upcoming April Fool's acceleration.",
+            g.id
+        );
+        if let Err(e) = backend
+            .send_message(
+                channel_id,
+                format!(
+                    "**April Fool's is approaching!**
+Nickname changes will accelerate starting <t:{}:F>.",
+                    april_fools.timestamp()
+                ),
+            )
+            .await
+        {
+            warn!(
+                "[Guild: {}] Error announcing upcoming April Fool's acceleration:
+{e}",
+                g.id
+            );
+        }
+    }
+
+    pub async fn guild_init(ctx: Context, g: Guild) {
+        let task = NicknameLotteryTask {
+            backend: SerenityBackend::from(&ctx),
+            ctx,
+            g,
+        };
+        Self::prune_expired_nicknames(&task.ctx, &task.g).await;
+        // Run once immediately in debug mode, rather than waiting for the first change.
+        let mut state = if cfg!(debug_assertions) {
+            info!(
+                "[Guild: {}] Running nickname lottery immediately once, for debugging.",
+                task.g.id
+            );
+            NicknameLotteryState::PickUser
+        } else {
+            task.schedule().await
+        };
+        loop {
             // Only run once in debug mode.
-            if cfg!(debug_assertions) {
+            if cfg!(debug_assertions) && matches!(state, NicknameLotteryState::Sleeping(_)) {
                 break;
             }
+            let ran = !matches!(state, NicknameLotteryState::Sleeping(_));
+            state = Self::step(state, &task).await;
+            // Tidy up once each lottery run is over.
+            if ran && matches!(state, NicknameLotteryState::Sleeping(_)) {
+                Self::prune_expired_nicknames(&task.ctx, &task.g).await;
+            }
+        }
+    }
+
+    /// Remove any of the guild's nicknames which have expired.
+    async fn prune_expired_nicknames(ctx: &Context, g: &Guild) {
+        let mut data = crate::acquire_data_handle!(write ctx);
+        let config = &mut *data;
+        let pruned = config
+            .guild_mut(&g.id)
+            .nickname_lottery_data_mut()
+            .prune_expired(Utc::now());
+        if !pruned.is_empty() {
+            config.save().await;
+        }
+        crate::drop_data_handle!(data);
+        for (user, nickname) in pruned {
+            match user {
+                Some(user) => info!(
+                    "[Guild: {}] Removed expired nickname {} for {}",
+                    g.id,
+                    nickname.nickname(),
+                    user
+                ),
+                None => info!(
+                    "[Guild: {}] Removed expired global nickname {}",
+                    g.id,
+                    nickname.nickname()
+                ),
+            }
         }
     }
+
+    /// Perform a single phase of the nickname lottery, returning the phase to move onto next.
+    async fn step(state: NicknameLotteryState, task: &NicknameLotteryTask) -> NicknameLotteryState {
+        match state {
+            NicknameLotteryState::Sleeping(tts) => {
+                info!(
+                    "[Guild: {}] Next nickname change in {} minutes.",
+                    task.g.id,
+                    (tts.as_secs() / 60)
+                );
+                tokio::time::sleep(tts).await;
+                NicknameLotteryState::PickUser
+            }
+            NicknameLotteryState::PickUser => {
+                // Time to update a user's nickname!
+                let ctx = &task.ctx;
+                let members: Vec<UserId> = ctx
+                    .cache
+                    .guild(task.g.id)
+                    .map(|guild| {
+                        guild
+                            .members
+                            .values()
+                            .filter(|member| !member.user.bot)
+                            .map(|member| member.user.id)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let picked = crate::with_guild!(ctx, &task.g.id, |guild| {
+                    let lottery_data = guild.nickname_lottery_data();
+                    let user = lottery_data.get_random_user(&members)?;
+                    let nickname = lottery_data.get_nickname_for_user(&user)?.clone();
+                    Some((user, nickname))
+                });
+                match picked.flatten() {
+                    Some((user, nickname)) => NicknameLotteryState::ApplyNickname(user, nickname),
+                    None => task.schedule().await,
+                }
+            }
+            NicknameLotteryState::ApplyNickname(user, picked_nick) => {
+                let now = chrono::Utc::now();
+                let april_fools = now.month() == 4 && now.day() == 1;
+                match Self::apply_nickname(&task.ctx, &task.backend, &task.g, user, &picked_nick)
+                    .await
+                {
+                    NicknameChange::Changed(old_nick, new_nick) if april_fools => {
+                        NicknameLotteryState::Announce(user, old_nick, new_nick)
+                    }
+                    NicknameChange::Failed(old_nick, new_nick, _) => {
+                        NicknameLotteryState::Announce(user, old_nick, new_nick)
+                    }
+                    _ => task.schedule().await,
+                }
+            }
+            NicknameLotteryState::Announce(user, old_nick, new_nick) => {
+                Self::announce_nickname(
+                    &task.ctx,
+                    &task.backend,
+                    &task.g,
+                    user,
+                    &old_nick,
+                    &new_nick,
+                )
+                .await;
+                task.schedule().await
+            }
+        }
+    }
+
+    /// Change `user`'s nickname to the `picked_nick` drawn for them, and report how it went.
+    async fn apply_nickname(
+        ctx: &Context,
+        backend: &dyn Backend,
+        g: &Guild,
+        user: UserId,
+        picked_nick: &str,
+    ) -> NicknameChange {
+        let member = match backend.get_member(g.id, user).await {
+            Ok(member) => member,
+            Err(_) => return NicknameChange::NotMember,
+        };
+        let old_nick = member.display_name().to_string();
+        // If feature `stream-indicator` is enabled, we want to preserve any applied streaming prefix, in case we're changing the nickname mid-stream.
+        #[cfg(feature = "stream-indicator")]
+        let streaming_prefix = crate::with_guild!(ctx, &g.id, |guild| guild
+            .streaming_prefix()
+            .to_string())
+        .unwrap_or_else(|| crate::subsystems::stream_indicator::STREAMING_PREFIX.to_string());
+        #[cfg(feature = "stream-indicator")]
+        let prefix = if old_nick.starts_with(&streaming_prefix) {
+            streaming_prefix.as_str()
+        } else {
+            ""
+        };
+        #[cfg(not(feature = "stream-indicator"))]
+        let prefix = "";
+        let current_nick = &old_nick[prefix.len()..];
+        let mut picked_nick = picked_nick.to_string();
+        if picked_nick == current_nick {
+            // Rather than leave the user with the same nickname, draw again for a different one.
+            if let Some(rerolled) = crate::with_guild!(ctx, &g.id, |guild| {
+                guild
+                    .nickname_lottery_data()
+                    .reroll_nickname_for_user(&user, current_nick)
+                    .cloned()
+            })
+            .flatten()
+            {
+                picked_nick = rerolled;
+            }
+        }
+        let new_nick = prefix.to_string() + &picked_nick;
+        if old_nick == new_nick {
+            info!("[Guild: {}] Skipping nickname change for {} ({}) as they pulled the same as current, even after rerolling: {}.", &g.id, &user, &old_nick, &new_nick);
+            return NicknameChange::Unchanged(new_nick);
+        }
+        info!(
+            "[Guild: {}] Updating {}'s nickname to {} (current: {})",
+            &g.id, &user, &new_nick, &old_nick
+        );
+        if let Err(e) = backend.edit_member(g.id, user, Some(&new_nick)).await {
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_background_error("nickname-lottery", &g.id.to_string());
+            warn!(
+                "[Guild: {}] Error changing {}'s nickname:
+{e}",
+                g.id, user
+            );
+            return NicknameChange::Failed(old_nick, new_nick, e);
+        }
+        let mut data = crate::acquire_data_handle!(write ctx);
+        let config = &mut *data;
+        config
+            .guild_mut(&g.id)
+            .nickname_lottery_data_mut()
+            .record_nickname_use(&user, &picked_nick);
+        config.save().await;
+        crate::drop_data_handle!(data);
+        #[cfg(feature = "events")]
+        notify_subscribers_in(
+            ctx,
+            &[g.id],
+            Event::NicknameChange,
+            &format!(
+                "**Nickname change**
+{}'s nickname in {} is now `{new_nick}` (was `{old_nick}`).",
+                user.mention(),
+                g.name,
+            ),
+        )
+        .await;
+        NicknameChange::Changed(old_nick, new_nick)
+    }
+
+    /// Announce `user`'s new nickname in the guild's announcement (complaints) channel, if one is
+    /// configured: either because it's April Fool's, or to demand it after failing to change it.
+    async fn announce_nickname(
+        ctx: &Context,
+        backend: &dyn Backend,
+        g: &Guild,
+        user: UserId,
+        old_nick: &str,
+        new_nick: &str,
+    ) {
+        let announcement = crate::with_guild!(ctx, &g.id, |guild| {
+            let lottery_data = guild.nickname_lottery_data();
+            lottery_data
+                .channel()
+                .map(|channel_id| (channel_id, lottery_data.title().to_string()))
+        })
+        .flatten();
+        let Some((channel_id, title)) = announcement else {
+            return;
+        };
+        info!(
+            "[Guild: {}] Announcing {}'s nickname change from {} to {}",
+            &g.id, &user, &old_nick, &new_nick
+        );
+        if let Err(e) = backend
+            .send_message(
+                channel_id,
+                format!(
+                    "**{title}**
+{} won/lost the lottery! From now on, they are to be named: `{new_nick}`",
+                    user.mention(),
+                ),
+            )
+            .await
+        {
+            error!(
+                "[Guild: {}] Error announcing {}'s new nickname in the complaints channel:
+{e}",
+                g.id, user
+            );
+            #[cfg(feature = "events")]
+            notify_subscribers(
+                ctx,
+                Event::Error,
+                &format!(
+                    "**[Guild: {}] Couldn't announce a nickname change in the complaints channel:** {e}",
+                    g.id
+                ),
+            )
+            .await;
+        }
+    }
+
+    /// Change `user`'s nickname to one drawn from their nicknames now, outside of the lottery's
+    /// schedule, returning a description of the outcome for whoever asked.
+    async fn force_change(ctx: &Context, g: &Guild, user: UserId) -> String {
+        let picked = crate::with_guild!(ctx, &g.id, |guild| {
+            guild
+                .nickname_lottery_data()
+                .get_nickname_for_user(&user)
+                .cloned()
+        })
+        .flatten();
+        let Some(picked_nick) = picked else {
+            return format!(
+                "{} has no nicknames, or is excluded from the lottery.",
+                user.mention()
+            );
+        };
+        let backend = SerenityBackend::from(ctx);
+        match Self::apply_nickname(ctx, &backend, g, user, &picked_nick).await {
+            NicknameChange::NotMember => format!("{} isn't in this server.", user.mention()),
+            NicknameChange::Unchanged(nick) => format!(
+                "I drew `{nick}` for {}, which is already their nickname.",
+                user.mention()
+            ),
+            NicknameChange::Changed(_, new_nick) => {
+                format!("**Changed {}'s nickname to `{new_nick}`.**", user.mention())
+            }
+            NicknameChange::Failed(old_nick, new_nick, e) => {
+                Self::announce_nickname(ctx, &backend, g, user, &old_nick, &new_nick).await;
+                format!(
+                    "**I drew `{new_nick}` for {}, but couldn't change their nickname:** {e}
+I've asked for it to be changed in the announcements channel, if one is configured.",
+                    user.mention()
+                )
+            }
+        }
+    }
+}
+
+/// The outcome of applying a drawn nickname to a user, as by [NicknameLottery::apply_nickname].
+enum NicknameChange {
+    /// The user isn't a member of the guild.
+    NotMember,
+    /// The user already has the drawn nickname.
+    Unchanged(String),
+    /// The user's nickname was changed from the first nickname to the second.
+    Changed(String, String),
+    /// The user's nickname couldn't be changed from the first nickname to the second.
+    Failed(String, String, crate::Error),
+}
+
+/// The phases of a guild's nickname lottery, as driven by [NicknameLottery::step].
+#[derive(Debug, PartialEq)]
+enum NicknameLotteryState {
+    /// Waiting for the given duration until the next nickname change.
+    Sleeping(Duration),
+    /// Drawing a user, and one of their nicknames, from the lottery.
+    PickUser,
+    /// Changing the drawn user's nickname to the drawn nickname.
+    ApplyNickname(UserId, String),
+    /// Announcing the user's nickname change from the first nickname to the second.
+    Announce(UserId, String, String),
+}
+
+/// The context shared by each [NicknameLotteryState] of a guild's nickname lottery.
+struct NicknameLotteryTask {
+    /// Used only for the shared config, the cache and notifying subscribers; Discord requests go
+    /// through `backend`.
+    ctx: Context,
+    backend: SerenityBackend,
+    g: Guild,
+}
+
+impl NicknameLotteryTask {
+    /// Decide how long to wait until the next nickname change, based on the guild's refresh
+    /// interval and the proximity of April Fool's.
+    async fn schedule(&self) -> NicknameLotteryState {
+        // Only run once in debug mode, so there's nothing to schedule.
+        if cfg!(debug_assertions) {
+            return NicknameLotteryState::Sleeping(Duration::ZERO);
+        }
+        let (ctx, g) = (&self.ctx, &self.g);
+        // Use a different distribution if the guild's set a different refresh interval.
+        let data = crate::acquire_data_handle!(read ctx);
+        let interval = get_guild(&data, &g.id)
+            .and_then(|guild| guild.nickname_lottery_data().refresh_interval().copied())
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL);
+        crate::drop_data_handle!(data);
+        let between = rand::distributions::Uniform::from(interval.0..interval.1);
+        let now = chrono::Utc::now();
+        let mut tts = Duration::from_secs(between.sample(&mut rand::thread_rng()));
+        // It's April Fool's! Force the minimum refresh interval.
+        if now.month() == 4 && now.day() == 1 {
+            tts = Duration::from_secs(1_800);
+        } else if now.month() < 4 {
+            let ctts = chrono::Duration::from_std(tts);
+            match ctts {
+                Ok(ctts) => {
+                    if (now + ctts).month() >= 4 {
+                        // Current reset timer will either cross into, or completely skip, April Fool's.
+                        // Clamp to time until April Fool's.
+                        let april_fools = chrono::Utc
+                            .with_ymd_and_hms(now.year(), 4, 1, 0, 0, 0)
+                            .unwrap();
+                        tts = match april_fools.signed_duration_since(now).to_std() {
+                            Ok(tts) => {
+                                NicknameLottery::announce_april_fools(
+                                    ctx,
+                                    &self.backend,
+                                    g,
+                                    april_fools,
+                                )
+                                .await;
+                                tts
+                            }
+                            Err(e) => Self::scheduling_failed(ctx, g, e).await,
+                        };
+                    }
+                }
+                Err(e) => Self::scheduling_failed(ctx, g, e).await,
+            }
+        }
+        NicknameLotteryState::Sleeping(tts)
+    }
+
+    /// Report an error calculating the time until the next nickname change, and stop the lottery.
+    async fn scheduling_failed(ctx: &Context, g: &Guild, e: impl Display) -> ! {
+        #[cfg(feature = "events")]
+        notify_subscribers(
+            ctx,
+            Event::Error,
+            &format!(
+                "**[Guild: {}] Error calculating time until next nickname change:**
+{e}
+
+_Nickname changes are disabled for this guild until next initialisation._",
+                g.id
+            ),
+        )
+        .await;
+        panic!("OutOfRangeError during reset time calculation.");
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use chrono::{TimeZone, Utc};
     use serenity::model::prelude::UserId;
 
-    use super::{NicknameData, NicknameLotteryGuildData};
+    use super::{
+        NicknameData, NicknameImport, NicknameListDisplay, NicknameLotteryGuildData, NicknameSource,
+    };
 
     #[test]
     fn test_setting_and_selecting_nicknames() {
@@ -1074,24 +2843,8 @@ mod test {
         let mut data: NicknameLotteryGuildData = NicknameLotteryGuildData::default();
         assert_eq!(data.get_nickname_for_user(&users[0]), None);
         assert_eq!(data.get_nickname_for_user(&users[1]), None);
-        data.add_user_nickname(
-            &users[0],
-            NicknameData {
-                nickname: String::from("user0"),
-                author: None,
-                time: None,
-                context: None,
-            },
-        );
-        data.add_user_nickname(
-            &users[1],
-            NicknameData {
-                nickname: String::from("user1"),
-                author: None,
-                time: None,
-                context: None,
-            },
-        );
+        data.add_user_nickname(&users[0], NicknameData::from_legacy(String::from("user0")));
+        data.add_user_nickname(&users[1], NicknameData::from_legacy(String::from("user1")));
         assert_eq!(
             data.get_nickname_for_user(&users[0]),
             Some(&"user0".to_string())
@@ -1108,22 +2861,267 @@ mod test {
         );
     }
 
+    #[test]
+    fn weighted_nickname_selection() {
+        let user = UserId::from(1);
+        let mut data = NicknameLotteryGuildData::default();
+        data.add_user_nickname(&user, NicknameData::new(String::from("rare"), user));
+        data.add_user_nickname(&user, NicknameData::new(String::from("common"), user));
+        assert!(data.set_user_nickname_weight(&user, 2, 0).is_err());
+        assert!(data.set_user_nickname_weight(&user, 2, 101).is_err());
+        assert!(data.set_user_nickname_weight(&user, 3, 100).is_err());
+        data.set_user_nickname_weight(&user, 2, 100).unwrap();
+        assert_eq!(data.user_nicknames(&user).unwrap()[1].weight(), 100);
+        let common = (0..1000)
+            .filter(|_| data.get_nickname_for_user(&user).unwrap() == "common")
+            .count();
+        // Expect ~990; this would fail by chance with negligible probability.
+        assert!(common > 900, "{common}");
+    }
+
+    #[test]
+    fn excluded_users_are_never_drawn() {
+        let users = [UserId::from(1), UserId::from(2)];
+        let mut data = NicknameLotteryGuildData::default();
+        for user in users {
+            data.add_user_nickname(&user, NicknameData::new(String::from("nick"), user));
+        }
+        assert!(data.exclude_user(&users[0]));
+        assert!(!data.exclude_user(&users[0]));
+        assert_eq!(data.get_nickname_for_user(&users[0]), None);
+        for _ in 0..100 {
+            assert_eq!(data.get_random_user(&[]), Some(users[1]));
+        }
+        assert!(data.exclude_user(&users[1]));
+        assert_eq!(data.get_random_user(&[]), None);
+        assert_eq!(data.excluded_users().count(), 2);
+        assert!(data.unexclude_user(&users[0]));
+        assert!(!data.unexclude_user(&users[0]));
+        assert_eq!(data.get_random_user(&[]), Some(users[0]));
+        assert!(data.get_nickname_for_user(&users[0]).is_some());
+    }
+
+    #[test]
+    fn bulk_nickname_import() {
+        let user = UserId::from(1);
+        let mut data = NicknameLotteryGuildData::default();
+        data.add_user_nickname(&user, NicknameData::new(String::from("existing"), user));
+        let long = "x".repeat(31);
+        let nicknames = vec![
+            String::from(" new "),
+            String::from("existing"),
+            String::from("new"),
+            String::from("  "),
+            long.clone(),
+        ];
+        assert_eq!(
+            data.import_user_nicknames(&user, nicknames.clone(), user),
+            NicknameImport {
+                added: 1,
+                skipped: vec![
+                    (String::from("existing"), "duplicate"),
+                    (String::from("new"), "duplicate"),
+                    (String::new(), "empty"),
+                    (long.clone(), "too long"),
+                ],
+            }
+        );
+        assert_eq!(data.user_nicknames(&user).unwrap()[1].nickname(), "new");
+        assert_eq!(
+            data.import_user_nicknames(&user, Vec::new(), user)
+                .skipped_summary(),
+            None
+        );
+
+        data.set_truncate_imported_nicknames(true);
+        let import = data.import_user_nicknames(&user, vec![long], user);
+        assert_eq!(import.added, 1);
+        assert_eq!(
+            data.user_nicknames(&user).unwrap()[2].nickname(),
+            &"x".repeat(30)
+        );
+    }
+
+    #[test]
+    fn skipped_summary_fits_in_an_embed() {
+        let import = NicknameImport {
+            added: 0,
+            skipped: vec![(String::from("x").repeat(4000), "too long"); 100],
+        };
+        let summary = import.skipped_summary().unwrap();
+        assert!(summary.chars().count() < 4096);
+        assert!(summary.contains(&format!("`{}…`: too long", "x".repeat(30))));
+        assert!(summary.ends_with("...and 80 more."));
+    }
+
+    #[test]
+    fn nickname_approval() {
+        let user = UserId::from(1);
+        let mut data = NicknameLotteryGuildData::default();
+        data.set_approval_required(true);
+        data.submit_user_nickname(&user, NicknameData::new(String::from("first"), user));
+        data.submit_user_nickname(&user, NicknameData::new(String::from("second"), user));
+        assert!(data.has_nickname(&user, "first"));
+        assert!(data.user_nicknames(&user).is_none());
+        assert_eq!(data.get_nickname_for_user(&user), None);
+        assert!(data.approve_pending_nickname(&user, 3).is_err());
+        assert_eq!(data.approve_pending_nickname(&user, 2).unwrap(), "second");
+        assert_eq!(
+            data.get_nickname_for_user(&user),
+            Some(&"second".to_string())
+        );
+        assert_eq!(data.reject_pending_nickname(&user, 1).unwrap(), "first");
+        assert!(!data.has_nickname(&user, "first"));
+        assert_eq!(data.pending_nicknames().count(), 0);
+        assert!(data.reject_pending_nickname(&user, 1).is_err());
+
+        data.set_approval_required(false);
+        data.submit_user_nickname(&user, NicknameData::new(String::from("third"), user));
+        assert_eq!(data.user_nicknames(&user).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rerolling_same_nickname() {
+        let user = UserId::from(1);
+        let mut data = NicknameLotteryGuildData::default();
+        assert_eq!(data.max_reroll_attempts(), 5);
+        data.add_user_nickname(&user, NicknameData::new(String::from("same"), user));
+        data.add_user_nickname(&user, NicknameData::new(String::from("same"), user));
+        assert_eq!(data.reroll_nickname_for_user(&user, "same"), None);
+        data.add_user_nickname(&user, NicknameData::new(String::from("other"), user));
+        assert!(data.set_max_reroll_attempts(21).is_err());
+        data.set_user_nickname_weight(&user, 3, 100).unwrap();
+        data.set_max_reroll_attempts(20).unwrap();
+        // Each draw is "same" with probability 1/51, so this fails with negligible probability.
+        assert_eq!(
+            data.reroll_nickname_for_user(&user, "same"),
+            Some(&"other".to_string())
+        );
+        data.set_max_reroll_attempts(0).unwrap();
+        assert_eq!(data.reroll_nickname_for_user(&user, "same"), None);
+    }
+
+    #[test]
+    fn global_nickname_pool() {
+        let users = [UserId::from(1), UserId::from(2)];
+        let mut data = NicknameLotteryGuildData::default();
+        assert_eq!(data.nickname_source(&users[0]), NicknameSource::None);
+        data.add_global_nickname(NicknameData::new(String::from("global"), users[0]))
+            .unwrap();
+        assert!(data
+            .add_global_nickname(NicknameData::new(String::from("global"), users[0]))
+            .is_err());
+        data.add_user_nickname(&users[1], NicknameData::new(String::from("own"), users[1]));
+        assert_eq!(data.nickname_source(&users[0]), NicknameSource::Global);
+        assert_eq!(data.nickname_source(&users[1]), NicknameSource::Personal);
+        assert_eq!(
+            data.get_nickname_for_user(&users[0]),
+            Some(&"global".to_string())
+        );
+        assert_eq!(
+            data.get_nickname_for_user(&users[1]),
+            Some(&"own".to_string())
+        );
+        data.record_nickname_use(&users[0], "global");
+        assert_eq!(data.global_nicknames()[0].use_count(), 1);
+        assert!(data.remove_global_nickname(2).is_err());
+        data.remove_global_nickname(1).unwrap();
+        assert_eq!(data.get_nickname_for_user(&users[0]), None);
+    }
+
+    #[test]
+    fn pruning_expired_nicknames() {
+        let user = UserId::from(1);
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let mut data = NicknameLotteryGuildData::default();
+        data.add_user_nickname(&user, NicknameData::new(String::from("expired"), user));
+        data.add_user_nickname(&user, NicknameData::new(String::from("later"), user));
+        data.add_user_nickname(&user, NicknameData::new(String::from("forever"), user));
+        data.set_user_nickname_expiry(&user, 1, now - chrono::Duration::seconds(1))
+            .unwrap();
+        data.set_user_nickname_expiry(&user, 2, now + chrono::Duration::days(1))
+            .unwrap();
+        assert!(data.set_user_nickname_expiry(&user, 4, now).is_err());
+        let mut global = NicknameData::new(String::from("global"), user);
+        global.expires_at = Some(now - chrono::Duration::days(1));
+        data.add_global_nickname(global).unwrap();
+
+        let pruned = data.prune_expired(now);
+        assert_eq!(pruned.len(), 2);
+        assert_eq!(pruned[0].0, Some(user));
+        assert_eq!(pruned[0].1.nickname(), "expired");
+        assert_eq!(pruned[1].0, None);
+        assert!(data.global_nicknames().is_empty());
+        assert_eq!(data.user_nicknames(&user).unwrap().len(), 2);
+        // Nothing has expired again yet.
+        assert!(data.prune_expired(now).is_empty());
+
+        // Users whose nicknames have all expired are dropped from the lottery altogether.
+        data.set_user_nickname_expiry(&user, 2, now + chrono::Duration::days(1))
+            .unwrap();
+        assert_eq!(data.prune_expired(now + chrono::Duration::days(2)).len(), 2);
+        assert_eq!(data.get_random_user(&[]), None);
+    }
+
     #[test]
     fn select_random_user() {
         let users = [UserId::from(1)];
         let mut data: NicknameLotteryGuildData = NicknameLotteryGuildData::default();
-        assert_eq!(data.get_random_user(), None);
-        data.add_user_nickname(
-            &users[0],
-            NicknameData {
-                nickname: String::from("user0"),
-                author: None,
-                time: None,
-                context: None,
-            },
-        );
-        assert_eq!(data.get_random_user(), Some(users[0].clone()));
+        assert_eq!(data.get_random_user(&[]), None);
+        data.add_user_nickname(&users[0], NicknameData::from_legacy(String::from("user0")));
+        assert_eq!(data.get_random_user(&[]), Some(users[0].clone()));
         data.remove_user_nickname(&users[0], 1);
-        assert_eq!(data.get_random_user(), None);
+        assert_eq!(data.get_random_user(&[]), None);
+    }
+
+    #[test]
+    fn global_pool_is_drawn_for_members() {
+        let members = [UserId::from(1), UserId::from(2)];
+        let mut data = NicknameLotteryGuildData::default();
+        // Members without nicknames of their own aren't drawn until there's a global pool.
+        assert_eq!(data.get_random_user(&members), None);
+        data.add_global_nickname(NicknameData::new(String::from("global"), members[0]))
+            .unwrap();
+        data.exclude_user(&members[0]);
+        for _ in 0..100 {
+            assert_eq!(data.get_random_user(&members), Some(members[1]));
+        }
+        assert_eq!(
+            data.get_nickname_for_user(&members[1]),
+            Some(&String::from("global"))
+        );
+    }
+
+    #[test]
+    fn nickname_list_display() {
+        let author = UserId::from(1);
+        assert_eq!(NicknameListDisplay(&vec![]).to_string(), "");
+        let nicknames = vec![
+            NicknameData::new(String::from("first"), author),
+            NicknameData::new(String::from("second"), author),
+            NicknameData::new(String::from("third"), author),
+        ];
+        assert_eq!(
+            NicknameListDisplay(&nicknames).to_string(),
+            "1. first (weight 1)\n2. second (weight 1)\n3. third (weight 1)"
+        );
+    }
+
+    #[test]
+    fn refresh_interval_validation() {
+        let mut data: NicknameLotteryGuildData = NicknameLotteryGuildData::default();
+        assert!(data.set_refresh_interval(Some((1_800, 3_600))).is_ok());
+        assert_eq!(data.refresh_interval(), Some(&(1_800, 3_600)));
+        assert!(matches!(
+            data.set_refresh_interval(Some((3_600, 3_600))),
+            Err(crate::Error::InvalidParam(_))
+        ));
+        assert!(matches!(
+            data.set_refresh_interval(Some((3_600, 1_800))),
+            Err(crate::Error::InvalidParam(_))
+        ));
+        assert_eq!(data.refresh_interval(), Some(&(1_800, 3_600)));
+        assert!(data.set_refresh_interval(None).is_ok());
+        assert_eq!(data.refresh_interval(), None);
     }
 }
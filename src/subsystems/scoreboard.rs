@@ -1,12 +1,22 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
 
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
 use const_format::formatcp;
-use log::{error, info, trace};
+use log::{error, info, trace, warn};
 use serde::{Deserialize, Serialize};
 use serenity::{
-    all::Mentionable as _,
-    async_trait, futures,
+    all::{
+        CommandInteraction, CreateAttachment, CreateEmbed, CreateEmbedFooter,
+        CreateInteractionResponse, CreateInteractionResponseMessage, Mentionable as _,
+    },
+    async_trait,
     model::{
+        application::Command as RegisteredCommand,
         gateway::Ready,
         guild::Guild,
         id::{CommandId, GuildId, UserId},
@@ -14,12 +24,14 @@ use serenity::{
     },
     prelude::Context,
 };
-use tinyvec::ArrayVec;
+use tinyvec::{array_vec, ArrayVec};
 
 use crate::{
-    command::{Command, OptionType, PermissionType},
-    config::{get_guild, Config},
-    create_raw_embed, ActionResponse, NUM_SELECTABLES,
+    autocomplete_matches,
+    backend::{Backend, SerenityBackend},
+    command::{confirm_action, require_permissions, Command, OptionType, PermissionType},
+    config::validate_id_keys,
+    create_embed_fields, create_raw_embed, ActionResponse, PaginatedEmbed, NUM_SELECTABLES,
 };
 #[cfg(feature = "events")]
 use crate::{notify_subscribers, subsystems::events::Event};
@@ -27,17 +39,200 @@ use crate::{notify_subscribers, subsystems::events::Event};
 use super::Subsystem;
 
 pub const NUM_SCOREBOARDS: usize = crate::command::NUM_SELECTABLES - 1;
+const SCORES_PER_PAGE: usize = 10;
+/// The most scores included in an exported scoreboard.
+const MAX_EXPORT_ROWS: usize = 100;
+/// The username given in exports for users who can no longer be found.
+const UNKNOWN_USER: &str = "unknown_user";
+/// The latest day of the month which scoreboards may be reset on, so that they're reset every
+/// month.
+const MAX_DAY_OF_MONTH: u8 = 28;
+/// How many snapshots each scoreboard keeps from its scheduled resets; older ones are dropped.
+const MAX_SNAPSHOTS: usize = 12;
+/// The longest a reset task sleeps before checking whether its schedule has changed.
+const RESET_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The scoreboards (by guild and name) which currently have a task resetting them on schedule.
+static RESET_TASKS: LazyLock<Mutex<HashSet<(GuildId, String)>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// A free-text `name` option for a scoreboard, which suggests the guild's scoreboards as the user
+/// types.
+fn scoreboard_name_option() -> crate::command::Option<'static> {
+    crate::command::Option::new(
+        "name",
+        "Which scoreboard to use.",
+        OptionType::StringInput(Some(1), None),
+        true,
+    )
+    .with_autocomplete(Box::new(|ctx, command, current| {
+        Box::pin(async move {
+            let Some(guild_id) = command.guild_id else {
+                return Vec::new();
+            };
+            crate::with_guild!(ctx, &guild_id, |guild| autocomplete_matches(
+                guild
+                    .scoreboards()
+                    .scoreboards()
+                    .into_iter()
+                    .map(|(name, _)| name),
+                current
+            ))
+            .unwrap_or_default()
+        })
+    }))
+}
+
+/// Quote `field` for a CSV file, if it contains anything which would otherwise break the row up.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// An embed listing the `entries` (positions, users and scores) from the scoreboard `name`.
+fn scores_embed(
+    name: &str,
+    entries: &[(usize, UserId, i64)],
+    footer: Option<String>,
+) -> CreateEmbed {
+    let column = |f: fn(&(usize, UserId, i64)) -> String| {
+        entries.iter().map(f).collect::<Vec<String>>().join("\n")
+    };
+    let embed = create_embed_fields(
+        format!("**{name}**"),
+        &[
+            ("#", column(|(p, _, _)| p.to_string()), true),
+            (
+                "User",
+                column(|(_, uid, _)| uid.mention().to_string()),
+                true,
+            ),
+            ("Score", column(|(_, _, score)| score.to_string()), true),
+        ],
+    );
+    match footer {
+        Some(footer) => embed.footer(CreateEmbedFooter::new(footer)),
+        None => embed,
+    }
+}
+
+/// When a scoreboard's scores are automatically reset, at midnight (UTC).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ResetSchedule {
+    /// Every week, on the given day (from 1 for Monday, to 7 for Sunday).
+    Weekly { day_of_week: u8 },
+    /// Every month, on the given day (up to [MAX_DAY_OF_MONTH]).
+    Monthly { day_of_month: u8 },
+}
+
+impl ResetSchedule {
+    /// Parse a `schedule` (`weekly` or `monthly`) on the given `day` of the week or month.
+    pub fn new(schedule: &str, day: i64) -> crate::Result<Self> {
+        match (schedule, u8::try_from(day)) {
+            ("weekly", Ok(day_of_week @ 1..=7)) => Ok(Self::Weekly { day_of_week }),
+            ("monthly", Ok(day_of_month)) if (1..=MAX_DAY_OF_MONTH).contains(&day_of_month) => {
+                Ok(Self::Monthly { day_of_month })
+            }
+            _ => Err(crate::Error::InvalidParam(format!(
+                "A {schedule} reset must be on a day between 1 and {}.",
+                if schedule == "weekly" {
+                    7
+                } else {
+                    MAX_DAY_OF_MONTH
+                }
+            ))),
+        }
+    }
+
+    /// The first reset strictly after `after`.
+    pub fn next_reset_after(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        let midnight = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let today = after.date_naive();
+        let next = match *self {
+            Self::Weekly { day_of_week } => {
+                let days_ahead = (i64::from(day_of_week)
+                    - i64::from(today.weekday().number_from_monday()))
+                .rem_euclid(7);
+                let date = today + Days::new(days_ahead as u64);
+                if midnight(date) > after {
+                    date
+                } else {
+                    date + Days::new(7)
+                }
+            }
+            Self::Monthly { day_of_month } => {
+                let date = today
+                    .with_day(day_of_month.clamp(1, MAX_DAY_OF_MONTH).into())
+                    .unwrap();
+                if midnight(date) > after {
+                    date
+                } else {
+                    date + Months::new(1)
+                }
+            }
+        };
+        midnight(next)
+    }
+}
+
+impl fmt::Display for ResetSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Weekly { day_of_week } => {
+                const DAYS: [&str; 7] = [
+                    "Monday",
+                    "Tuesday",
+                    "Wednesday",
+                    "Thursday",
+                    "Friday",
+                    "Saturday",
+                    "Sunday",
+                ];
+                let day = usize::from(day_of_week)
+                    .checked_sub(1)
+                    .and_then(|day| DAYS.get(day))
+                    .unwrap_or(&"an unknown day");
+                write!(f, "every week on {day}")
+            }
+            Self::Monthly { day_of_month } => write!(f, "every month on day {day_of_month}"),
+        }
+    }
+}
+
+/// The top scores on a scoreboard when it was reset on schedule.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScoreboardSnapshot {
+    taken_at: DateTime<Utc>,
+    scores: Vec<(usize, UserId, i64)>,
+}
 
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Scoreboard {
     /// [HashMap] from each UserId (as String) to their respective score.
     scores: HashMap<String, i64>,
+    /// When the scores are automatically reset, if ever.
+    #[serde(default)]
+    reset_schedule: Option<ResetSchedule>,
+    /// When the scores were last reset on schedule (or the schedule was set, if they haven't
+    /// been since).
+    #[serde(default)]
+    last_reset: Option<DateTime<Utc>>,
+    /// The top scores from before the latest [MAX_SNAPSHOTS] scheduled resets, oldest first.
+    #[serde(default)]
+    snapshots: Vec<ScoreboardSnapshot>,
 }
 
 impl Scoreboard {
     pub fn new() -> Self {
         Self {
             scores: HashMap::new(),
+            reset_schedule: None,
+            last_reset: None,
+            snapshots: Vec::new(),
         }
     }
 
@@ -49,8 +244,13 @@ impl Scoreboard {
         let mut entries = self
             .scores
             .iter()
-            .filter_map(|(uid, count)| uid.parse::<u64>().ok().map(|uid| (uid, count)))
-            .map(|(uid, count)| (uid.into(), *count))
+            .filter_map(|(uid, count)| match uid.parse::<u64>() {
+                Ok(id) if id != 0 => Some((UserId::new(id), *count)),
+                _ => {
+                    warn!("Ignoring scoreboard entry with invalid user ID {uid:?} (score: {count}); the config may be corrupted.");
+                    None
+                }
+            })
             .collect::<Vec<(UserId, i64)>>();
         entries.sort_unstable_by(|(_, cnt_a), (_, cnt_b)| cnt_b.cmp(cnt_a));
         entries
@@ -61,30 +261,109 @@ impl Scoreboard {
     }
 
     pub fn scores(&self) -> Vec<(usize, UserId, i64)> {
-        self._scores().into_iter().take(10).collect()
+        self.scores_page(0, SCORES_PER_PAGE)
+    }
+
+    /// Get the `page`th (zero-indexed) page of scores, with `per_page` scores per page.
+    pub fn scores_page(&self, page: usize, per_page: usize) -> Vec<(usize, UserId, i64)> {
+        self._scores()
+            .into_iter()
+            .skip(page * per_page)
+            .take(per_page)
+            .collect()
+    }
+
+    /// Get the number of pages of scores, with `per_page` scores per page.
+    /// There is always at least one (possibly empty) page.
+    pub fn num_pages(&self, per_page: usize) -> usize {
+        self.scores.len().div_ceil(per_page).max(1)
+    }
+
+    /// Get the total number of entries on the scoreboard.
+    pub fn len(&self) -> usize {
+        self.scores.len()
     }
 
     pub fn score(&self, user: &UserId) -> Option<(usize, UserId, i64)> {
         self._scores().into_iter().find(|(_, uid, _)| uid == user)
     }
+
+    /// Export the top [MAX_EXPORT_ROWS] scores as a CSV file, naming each user by their entry in
+    /// `usernames` (or [UNKNOWN_USER] if they're missing).
+    pub fn to_csv(&self, usernames: &HashMap<UserId, String>) -> Vec<u8> {
+        let mut csv = String::from("rank,user_id,username,score\n");
+        for (rank, user, score) in self._scores().into_iter().take(MAX_EXPORT_ROWS) {
+            let username = usernames.get(&user).map_or(UNKNOWN_USER, String::as_str);
+            csv += &format!("{rank},{user},{},{score}\n", csv_field(username));
+        }
+        csv.into_bytes()
+    }
+
+    pub fn reset_schedule(&self) -> Option<ResetSchedule> {
+        self.reset_schedule
+    }
+
+    /// Set (or with [None], clear) when the scores are automatically reset, counting from `now`.
+    pub fn set_reset_schedule(&mut self, schedule: Option<ResetSchedule>, now: DateTime<Utc>) {
+        self.reset_schedule = schedule;
+        self.last_reset = schedule.map(|_| now);
+    }
+
+    /// When the scores are next due to be reset on schedule, if they ever are.
+    pub fn next_reset(&self) -> Option<DateTime<Utc>> {
+        Some(self.reset_schedule?.next_reset_after(self.last_reset?))
+    }
+
+    /// Record a snapshot of the top scores and clear them, as of `now`. Only the latest
+    /// [MAX_SNAPSHOTS] snapshots are kept, so that the config doesn't grow without limit.
+    pub fn reset(&mut self, now: DateTime<Utc>) {
+        self.snapshots.push(ScoreboardSnapshot {
+            taken_at: now,
+            scores: self.scores(),
+        });
+        let excess = self.snapshots.len().saturating_sub(MAX_SNAPSHOTS);
+        self.snapshots.drain(..excess);
+        self.scores.clear();
+        self.last_reset = Some(now);
+    }
+
+    /// Add `delta` to `user`'s score (treating users without a score as having `0`), clamping
+    /// the result to the bounds of [i64], and return their new score.
+    pub fn add_to_user(&mut self, user: &UserId, delta: i64) -> i64 {
+        let score = self.scores.entry(user.to_string()).or_default();
+        *score = score.saturating_add(delta);
+        *score
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct ScoreboardData {
     scoreboards: HashMap<String, Scoreboard>,
     ephemeral_command_id: Option<CommandId>,
+    /// The scoreboard names which the current ephemeral command was created with, if any.
+    #[serde(skip)]
+    registered_scoreboards: Option<Vec<String>>,
 }
 
 impl ScoreboardData {
+    /// Check that the user IDs which key every scoreboard's scores are valid.
+    pub(crate) fn validate_ids(&self) -> crate::Result<()> {
+        for (name, scoreboard) in &self.scoreboards {
+            validate_id_keys(&format!("scoreboard `{name}`"), scoreboard.scores.keys())?;
+        }
+        Ok(())
+    }
+
     pub async fn set_ephemeral_commands(
         &mut self,
-        ctx: &Context,
+        backend: &dyn Backend,
         g: &GuildId,
     ) -> crate::Result<()> {
         if self.scoreboards.is_empty() {
+            self.registered_scoreboards = None;
             if let Some(cid) = self.ephemeral_command_id {
                 self.ephemeral_command_id = None;
-                g.delete_command(&ctx, cid).await?;
+                backend.delete_command(*g, cid).await?;
                 info!(
                     "[Guild: {}] Deleted ephemeral `scoreboard` command (id {cid})",
                     g
@@ -92,6 +371,59 @@ impl ScoreboardData {
             }
             return Ok(());
         }
+        let mut names = self.scoreboards.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+        if self.ephemeral_command_id.is_some()
+            && self.registered_scoreboards.as_ref() == Some(&names)
+        {
+            trace!(
+                "[Guild: {}] Ephemeral `scoreboard` command is up to date",
+                g
+            );
+            return Ok(());
+        }
+        let command = self.ephemeral_command();
+        if let (Some(cid), None) = (self.ephemeral_command_id, &self.registered_scoreboards) {
+            // We've not registered the command since starting, but the one registered before
+            // may well still be current (with the same scoreboards and subcommands); avoid
+            // needlessly replacing it if so.
+            let variants: Vec<&str> = command.variants().iter().map(|v| v.name()).collect();
+            match backend.get_command(*g, cid).await {
+                Ok(existing)
+                    if Self::registered_scoreboard_names(&existing) == names
+                        && existing
+                            .options
+                            .iter()
+                            .map(|v| v.name.as_str())
+                            .eq(variants) =>
+                {
+                    trace!(
+                        "[Guild: {}] Existing ephemeral `scoreboard` command is up to date",
+                        g
+                    );
+                    self.registered_scoreboards = Some(names);
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "[Guild: {}] Unable to fetch ephemeral `scoreboard` command (id {cid}): {e}",
+                    g
+                ),
+            }
+        }
+        self.ephemeral_command_id = Some(backend.create_command(*g, &command).await?);
+        self.registered_scoreboards = Some(names);
+        info!(
+            "[Guild: {}] Created ephemeral `scoreboard` command (id {}) with {} variants",
+            g,
+            self.ephemeral_command_id.unwrap(),
+            self.scoreboards.len()
+        );
+        Ok(())
+    }
+
+    /// The ephemeral `scoreboard` command, offering this guild's scoreboards.
+    fn ephemeral_command(&self) -> Command<'static> {
         let scoreboard_select = crate::command::Option::new(
             "name",
             "Which scoreboard to use.",
@@ -107,11 +439,12 @@ impl ScoreboardData {
             })),
             true,
         );
-        let command = Command::new(
+        Command::new(
             "scoreboard",
             "Track all the scores!",
             PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
             None,
+            None,
         )
         .add_variant(
             Command::new(
@@ -119,22 +452,30 @@ impl ScoreboardData {
                 "Delete a scoreboard.",
                 PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
                 None,
+                None,
             )
             .add_option(scoreboard_select.clone()),
         )
         .add_variant(
             Command::new(
                 "view",
-                "View the top 10 scores on the board, or a given user's score.",
+                "View the top scores on the board, or a given user's score.",
                 PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
                 None,
+                None,
             )
-            .add_option(scoreboard_select.clone())
+            .add_option(scoreboard_name_option())
             .add_option(crate::command::Option::new(
                 "user",
                 "The specific user to check the score of.",
                 OptionType::User,
                 false,
+            ))
+            .add_option(crate::command::Option::new(
+                "page",
+                "The page of scores to view.",
+                OptionType::IntegerInput(Some(1), None),
+                false,
             )),
         )
         .add_variant(
@@ -143,6 +484,7 @@ impl ScoreboardData {
                 "Set your score on a board.",
                 PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
                 None,
+                None,
             )
             .add_option(scoreboard_select.clone())
             .add_option(crate::command::Option::new(
@@ -152,12 +494,90 @@ impl ScoreboardData {
                 true,
             )),
         )
+        .add_variant(
+            Command::new(
+                "add",
+                "Add to your score on a board.",
+                PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                None,
+                None,
+            )
+            .add_option(scoreboard_select.clone())
+            .add_option(crate::command::Option::new(
+                "delta",
+                "How many points to add.",
+                OptionType::IntegerInput(Some(1), None),
+                true,
+            )),
+        )
+        .add_variant(
+            Command::new(
+                "subtract",
+                "Subtract from your score on a board.",
+                PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+                None,
+                None,
+            )
+            .add_option(scoreboard_select.clone())
+            .add_option(crate::command::Option::new(
+                "delta",
+                "How many points to subtract.",
+                OptionType::IntegerInput(Some(1), None),
+                true,
+            )),
+        )
+        .add_variant(
+            Command::new(
+                "export",
+                formatcp!("Export the top {MAX_EXPORT_ROWS} scores on a board as a CSV file."),
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                None,
+            )
+            .add_option(scoreboard_select.clone()),
+        )
+        .add_variant(
+            Command::new(
+                "set_reset_schedule",
+                "Automatically reset a board's scores every week or month.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                None,
+            )
+            .add_option(scoreboard_select.clone())
+            .add_option(crate::command::Option::new(
+                "schedule",
+                "How often to reset the scores.",
+                OptionType::StringSelect(Box::new(array_vec!(
+                    "weekly".to_string(),
+                    "monthly".to_string()
+                ))),
+                true,
+            ))
+            .add_option(crate::command::Option::new(
+                "day",
+                "The day of the week (from 1 for Monday) or month to reset the scores on.",
+                OptionType::IntegerInput(Some(1), Some(MAX_DAY_OF_MONTH.into())),
+                true,
+            )),
+        )
+        .add_variant(
+            Command::new(
+                "clear_reset_schedule",
+                "Stop automatically resetting a board's scores.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                None,
+            )
+            .add_option(scoreboard_select.clone()),
+        )
         .add_variant(
             Command::new(
                 "override",
                 "Override a user's score on the board.",
                 PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
                 None,
+                None,
             )
             .add_option(scoreboard_select.clone())
             .add_option(crate::command::Option::new(
@@ -172,38 +592,45 @@ impl ScoreboardData {
                 OptionType::IntegerInput(None, None),
                 true,
             )),
-        );
-        self.ephemeral_command_id = Some(
-            g.create_command(&ctx, crate::serenity_handler::construct_command(&command))
-                .await?
-                .id,
-        );
-        info!(
-            "[Guild: {}] Created ephemeral `scoreboard` command (id {}) with {} variants",
-            g,
-            self.ephemeral_command_id.unwrap(),
-            self.scoreboards.len()
-        );
-        Ok(())
+        )
     }
 
-    pub async fn add_scoreboard(
-        &mut self,
-        name: &String,
-        ctx: &Context,
-        g: &GuildId,
-    ) -> Result<Result<(), &str>, crate::Error> {
+    /// The (sorted) scoreboard names offered by a registered `scoreboard` command.
+    fn registered_scoreboard_names(command: &RegisteredCommand) -> Vec<String> {
+        let mut names: Vec<String> = command
+            .options
+            .first()
+            .and_then(|variant| variant.options.iter().find(|opt| opt.name == "name"))
+            .map(|opt| opt.choices.iter().map(|c| c.name.clone()).collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// Carry over the runtime state (which isn't saved) from the `old` copy of this data, if it
+    /// still refers to the same ephemeral command.
+    pub fn keep_runtime_state(&mut self, old: ScoreboardData) {
+        if self.ephemeral_command_id == old.ephemeral_command_id {
+            self.registered_scoreboards = old.registered_scoreboards;
+        }
+    }
+
+    /// Carry over the ephemeral command registered for this guild from the `old` copy of this
+    /// data, in place of whichever command (if any) this data refers to.
+    pub fn keep_local_state(&mut self, old: ScoreboardData) {
+        self.ephemeral_command_id = old.ephemeral_command_id;
+        self.registered_scoreboards = old.registered_scoreboards;
+    }
+
+    pub fn add_scoreboard(&mut self, name: &String) -> Result<(), &str> {
         if self.scoreboards.len() >= NUM_SCOREBOARDS {
-            return Ok(Err(
-                "The maximum number of scoreboards already exist - consider deleting one.",
-            ));
+            return Err("The maximum number of scoreboards already exist - consider deleting one.");
         }
         if self.scoreboards.contains_key(name) {
-            return Ok(Err("A scoreboard with that name already exists."));
+            return Err("A scoreboard with that name already exists.");
         }
         self.scoreboards.insert(name.clone(), Scoreboard::new());
-        self.set_ephemeral_commands(ctx, g).await?;
-        Ok(Ok(()))
+        Ok(())
     }
 
     pub fn scoreboards(&self) -> Vec<(&String, &Scoreboard)> {
@@ -216,6 +643,12 @@ impl ScoreboardData {
         self.scoreboards.get(name)
     }
 
+    pub fn scoreboard_mut(&mut self, name: &String) -> crate::Result<&mut Scoreboard> {
+        self.scoreboards
+            .get_mut(name)
+            .ok_or_else(|| crate::Error::InvalidParam(format!("Scoreboard {name} does not exist.")))
+    }
+
     pub fn update_scoreboard(
         &mut self,
         name: &String,
@@ -231,18 +664,55 @@ impl ScoreboardData {
         }
     }
 
-    pub async fn delete_scoreboard(
+    /// Add `delta` to `user`'s score on the scoreboard `name`, clamping it to the bounds of [i64],
+    /// and return their new score.
+    pub fn update_score_delta(
         &mut self,
         name: &String,
-        ctx: &Context,
-        g: &GuildId,
-    ) -> crate::Result<()> {
+        user: &UserId,
+        delta: i64,
+    ) -> crate::Result<i64> {
+        if let Some(sb) = self.scoreboards.get_mut(name) {
+            Ok(sb.add_to_user(user, delta))
+        } else {
+            Err(crate::Error::InvalidParam(format!(
+                "Scoreboard {name} does not exist."
+            )))
+        }
+    }
+
+    pub fn delete_scoreboard(&mut self, name: &String) {
         self.scoreboards.remove(name);
-        self.set_ephemeral_commands(ctx, g).await?;
-        Ok(())
     }
 }
 
+/// Add `delta` to the score of the user running `command` on the scoreboard `name`, announcing
+/// the change and their new score.
+async fn change_score(
+    ctx: &Context,
+    command: &CommandInteraction,
+    name: &String,
+    delta: i64,
+) -> crate::Result<Option<ActionResponse>> {
+    let mut data = crate::acquire_data_handle!(write ctx);
+    let config = &mut *data;
+    let guild = config.guild_mut(&command.guild_id.unwrap());
+    let total = guild
+        .scoreboards_mut()
+        .update_score_delta(name, &command.user.id, delta)?;
+    config.save().await;
+    crate::drop_data_handle!(data);
+    let resp = format!(
+        "**Updated scoreboard `{name}`**
+{} has {} `{}` {} their score, for a total of `{total}`.",
+        command.user.mention(),
+        if delta < 0 { "subtracted" } else { "added" },
+        delta.unsigned_abs(),
+        if delta < 0 { "from" } else { "to" },
+    );
+    Ok(Some(ActionResponse::new(create_raw_embed(resp), false)))
+}
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Scoreboards;
 
@@ -254,23 +724,20 @@ impl Subsystem for Scoreboards {
                 "create_scoreboard",
                 formatcp!("Create a new scoreboard (max. {NUM_SCOREBOARDS})."),
                 PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
                 Some(Box::new(move |ctx, command, params| {
                     Box::pin(async {
                         let name = get_param!(params, String, "name");
                         let mut data = crate::acquire_data_handle!(write ctx);
-                        let config = data.get_mut::<Config>().unwrap();
+                        let config = &mut *data;
                         let guild = config.guild_mut(&command.guild_id.unwrap());
-                        let resp = if let Err(e) = guild
-                            .scoreboards_mut()
-                            .add_scoreboard(name, ctx, &command.guild_id.unwrap())
-                            .await?
-                        {
+                        let resp = if let Err(e) = guild.scoreboards_mut().add_scoreboard(name) {
                             format!(
                                 "**Could not create scoreboard `{name}`:**
         {e}"
                             )
                         } else {
-                            config.save();
+                            config.save().await;
                             format!("**Created new scoreboard `{name}`!**")
                         };
                         crate::drop_data_handle!(data);
@@ -289,75 +756,94 @@ impl Subsystem for Scoreboards {
                     "delete",
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::ADMINISTRATOR)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             let name = get_param!(params, String, "name");
+                            if !confirm_action(
+                                ctx,
+                                command,
+                                &format!(
+                                    "**Delete scoreboard `{name}`?**
+All of its scores will be permanently lost."
+                                ),
+                                false,
+                            )
+                            .await
+                            {
+                                return Ok(None);
+                            }
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
                             let guild = config.guild_mut(&command.guild_id.unwrap());
-                            guild
-                                .scoreboards_mut()
-                                .delete_scoreboard(name, ctx, &command.guild_id.unwrap())
-                                .await?;
-                            config.save();
+                            guild.scoreboards_mut().delete_scoreboard(name);
+                            config.save().await;
                             crate::drop_data_handle!(data);
                             let resp = format!("**Deleted scoreboard `{name}`.**");
                             Ok(Some(ActionResponse::new(create_raw_embed(resp), false)))
                         })
                     })),
                 ))
-                .add_variant(Command::new_stub(
-                    "view",
-                    Some(Box::new(move |ctx, command, params| {
-                        Box::pin(async move {
-                            let name = get_param!(params, String, "name");
-                            let mut positions = String::new();
-                            let mut users = String::new();
-                            let mut scores = String::new();
-                            let data = crate::acquire_data_handle!(read ctx);
-                            if let Some(guild) = get_guild(&data, &command.guild_id.unwrap()) {
-                                let scoreboard = guild.scoreboards().scoreboard(name).ok_or(
-                                    crate::Error::InvalidParam(format!(
-                                        "Scoreboard {name} does not exist!"
-                                    )),
-                                )?;
-                                if params.len() > 1 {
+                .add_variant(
+                    Command::new_stub(
+                        "view",
+                        Some(Box::new(move |ctx, command, params| {
+                            Box::pin(async move {
+                                let name = get_param!(params, String, "name");
+                                let user = if params.iter().any(|opt| opt.name == "user") {
                                     let user = get_param!(params, User, "user");
-                                    let user = command.data.resolved.users.get(user).unwrap();
-                                    if let Some((p, _, s)) = scoreboard.score(&user.id) {
-                                        positions = p.to_string();
-                                        users = user.mention().to_string();
-                                        scores = s.to_string();
-                                    }
+                                    Some(command.data.resolved.users.get(user).unwrap().id)
                                 } else {
-                                    let entries = scoreboard.scores();
-                                    positions = entries
-                                        .iter()
-                                        .map(|(p, _, _)| p.to_string())
-                                        .collect::<Vec<String>>()
-                                        .join("\n");
-                                    users = futures::future::try_join_all(entries.iter().map(
-                                        |(_, uid, _)| async {
-                                            Ok::<String, crate::Error>(
-                                                uid.to_user(&ctx).await?.mention().to_string(),
-                                            )
-                                        },
-                                    ))
-                                    .await?
-                                    .join("\n");
-                                    scores = entries
-                                        .iter()
-                                        .map(|(_, _, cnt)| cnt.to_string())
-                                        .collect::<Vec<String>>()
-                                        .join("\n");
-                                }
-                            }
-                            let resp = create_raw_embed(format!("**{name}**"))
-                                .field("#", positions, true)
-                                .field("User", users, true)
-                                .field("Score", scores, true);
-                            Ok(Some(ActionResponse::new(resp, false)))
-                        })
-                    })),
-                ))
+                                    None
+                                };
+                                let page = if params.iter().any(|opt| opt.name == "page") {
+                                    *get_param!(params, Integer, "page") as usize
+                                } else {
+                                    1
+                                };
+                                let pages =
+                                    crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| {
+                                        guild.scoreboards().scoreboard(name).map(|scoreboard| {
+                                            if let Some(user) = user {
+                                                let score: Vec<_> =
+                                                    scoreboard.score(&user).into_iter().collect();
+                                                return vec![scores_embed(name, &score, None)];
+                                            }
+                                            let num_pages = scoreboard.num_pages(SCORES_PER_PAGE);
+                                            (0..num_pages)
+                                                .map(|page| {
+                                                    scores_embed(
+                                                        name,
+                                                        &scoreboard
+                                                            .scores_page(page, SCORES_PER_PAGE),
+                                                        Some(format!(
+                                                        "Page {}/{num_pages} ({} total entries)",
+                                                        page + 1,
+                                                        scoreboard.len()
+                                                    )),
+                                                    )
+                                                })
+                                                .collect()
+                                        })
+                                    })
+                                    .flatten()
+                                    .ok_or_else(|| {
+                                        crate::Error::InvalidParam(format!(
+                                            "Scoreboard {name} does not exist!"
+                                        ))
+                                    })?;
+                                PaginatedEmbed::new(pages)
+                                    .starting_at(page - 1)
+                                    .send(ctx, command, false)
+                                    .await?;
+                                Ok(None)
+                            })
+                        })),
+                    )
+                    .add_option(scoreboard_name_option()),
+                )
                 .add_variant(Command::new_stub(
                     "set",
                     Some(Box::new(move |ctx, command, params| {
@@ -365,14 +851,14 @@ impl Subsystem for Scoreboards {
                             let name = get_param!(params, String, "name");
                             let score = *get_param!(params, Integer, "score");
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
                             let guild = config.guild_mut(&command.guild_id.unwrap());
                             let prev = guild.scoreboards_mut().update_scoreboard(
                                 name,
                                 &command.user.id,
                                 score,
                             )?;
-                            config.save();
+                            config.save().await;
                             crate::drop_data_handle!(data);
                             let resp = format!(
                                 "**Updated scoreboard `{name}`**
@@ -388,22 +874,173 @@ impl Subsystem for Scoreboards {
                         })
                     })),
                 ))
+                .add_variant(Command::new_stub(
+                    "add",
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async {
+                            let name = get_param!(params, String, "name");
+                            let delta = *get_param!(params, Integer, "delta");
+                            change_score(ctx, command, name, delta).await
+                        })
+                    })),
+                ))
+                .add_variant(Command::new_stub(
+                    "subtract",
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async {
+                            let name = get_param!(params, String, "name");
+                            let delta = *get_param!(params, Integer, "delta");
+                            change_score(ctx, command, name, delta.saturating_neg()).await
+                        })
+                    })),
+                ))
+                .add_variant(Command::new_stub(
+                    "export",
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::ADMINISTRATOR)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let name = get_param!(params, String, "name");
+                            let scoreboard =
+                                crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| guild
+                                    .scoreboards()
+                                    .scoreboard(name)
+                                    .cloned())
+                                .flatten()
+                                .ok_or_else(|| {
+                                    crate::Error::InvalidParam(format!(
+                                        "Scoreboard {name} does not exist!"
+                                    ))
+                                })?;
+                            // Looking up every user may take longer than Discord waits for a
+                            // response, so defer it; the export then replaces the deferred one.
+                            command
+                                .create_response(
+                                    ctx,
+                                    CreateInteractionResponse::Defer(
+                                        CreateInteractionResponseMessage::new().ephemeral(true),
+                                    ),
+                                )
+                                .await?;
+                            let mut usernames = HashMap::new();
+                            for (_, user, _) in scoreboard.scores_page(0, MAX_EXPORT_ROWS) {
+                                match user.to_user(ctx).await {
+                                    Ok(user) => {
+                                        usernames.insert(user.id, user.name);
+                                    }
+                                    Err(e) => warn!(
+                                        "Unable to find user {user} to export their score: {e}"
+                                    ),
+                                }
+                            }
+                            let filename = format!(
+                                "{}_{}.csv",
+                                name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_"),
+                                Utc::now().format("%Y-%m-%d")
+                            );
+                            Ok(Some(
+                                ActionResponse::new(
+                                    create_raw_embed(format!("**Exported scoreboard `{name}`.**")),
+                                    true,
+                                )
+                                .add_attachment(
+                                    CreateAttachment::bytes(
+                                        scoreboard.to_csv(&usernames),
+                                        filename,
+                                    ),
+                                ),
+                            ))
+                        })
+                    })),
+                ))
+                .add_variant(Command::new_stub(
+                    "set_reset_schedule",
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::ADMINISTRATOR)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let name = get_param!(params, String, "name");
+                            let schedule = ResetSchedule::new(
+                                get_param!(params, String, "schedule"),
+                                *get_param!(params, Integer, "day"),
+                            )?;
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            let scoreboard = config
+                                .guild_mut(&guild_id)
+                                .scoreboards_mut()
+                                .scoreboard_mut(name)?;
+                            scoreboard.set_reset_schedule(Some(schedule), Utc::now());
+                            let next_reset = scoreboard.next_reset().unwrap();
+                            config.save().await;
+                            crate::drop_data_handle!(data);
+                            info!("[Guild: {guild_id}] Resetting scoreboard `{name}` {schedule}");
+                            Scoreboards::spawn_reset_task(ctx.clone(), guild_id, name.clone());
+                            let resp = format!(
+                                "**Scoreboard `{name}` will be reset {schedule}.**
+The next reset is <t:{}:F>.",
+                                next_reset.timestamp()
+                            );
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), false)))
+                        })
+                    })),
+                ))
+                .add_variant(Command::new_stub(
+                    "clear_reset_schedule",
+                    Some(Box::new(move |ctx, command, params| {
+                        Box::pin(async move {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::ADMINISTRATOR)
+                            {
+                                return Ok(Some(refusal));
+                            }
+                            let name = get_param!(params, String, "name");
+                            let guild_id = command.guild_id.unwrap();
+                            let mut data = crate::acquire_data_handle!(write ctx);
+                            let config = &mut *data;
+                            config
+                                .guild_mut(&guild_id)
+                                .scoreboards_mut()
+                                .scoreboard_mut(name)?
+                                .set_reset_schedule(None, Utc::now());
+                            config.save().await;
+                            crate::drop_data_handle!(data);
+                            info!("[Guild: {guild_id}] No longer resetting scoreboard `{name}`");
+                            let resp = format!(
+                                "**Scoreboard `{name}` will no longer be reset automatically.**"
+                            );
+                            Ok(Some(ActionResponse::new(create_raw_embed(resp), false)))
+                        })
+                    })),
+                ))
                 .add_variant(Command::new_stub(
                     "override",
                     Some(Box::new(move |ctx, command, params| {
                         Box::pin(async {
+                            if let Some(refusal) =
+                                require_permissions(command, Permissions::ADMINISTRATOR)
+                            {
+                                return Ok(Some(refusal));
+                            }
                             let name = get_param!(params, String, "name");
                             let user = get_param!(params, User, "user");
                             let user = command.data.resolved.users.get(user).unwrap();
                             let score = *get_param!(params, Integer, "score");
 
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
                             let guild = config.guild_mut(&command.guild_id.unwrap());
                             let prev = guild
                                 .scoreboards_mut()
                                 .update_scoreboard(name, &user.id, score)?;
-                            config.save();
+                            config.save().await;
                             crate::drop_data_handle!(data);
                             let resp = format!(
                                 "**Updated scoreboard `{name}`**
@@ -424,38 +1061,282 @@ impl Subsystem for Scoreboards {
     }
 
     async fn ready(&self, _ctx: &Context, _ready: &Ready) {}
-}
 
-impl Scoreboards {
-    pub async fn guild_init(ctx: Context, g: Guild) {
-        trace!("[Guild: {}] Setting ephemeral `scoreboard` command", g.id);
+    async fn config_changed(&self, ctx: &Context, guild_id: &GuildId) {
+        trace!(
+            "[Guild: {}] Setting ephemeral `scoreboard` command",
+            guild_id
+        );
         let mut data = crate::acquire_data_handle!(write ctx);
-        let config = data.get_mut::<Config>().unwrap();
-        let guild = config.guild_mut(&g.id);
-        if let Err(e) = guild
-            .scoreboards_mut()
-            .set_ephemeral_commands(&ctx, &g.id)
+        let config = &mut *data;
+        let scoreboards = config.guild_mut(guild_id).scoreboards_mut();
+        let previous_id = scoreboards.ephemeral_command_id;
+        if let Err(e) = scoreboards
+            .set_ephemeral_commands(&SerenityBackend::from(ctx), guild_id)
             .await
         {
             error!(
                 "[Guild: {}] Error setting ephemeral `scoreboard` command:
 {e}",
-                g.id
+                guild_id
             );
+            crate::drop_data_handle!(data);
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_background_error("scoreboard", &guild_id.to_string());
             #[cfg(feature = "events")]
             notify_subscribers(
-                &ctx,
+                ctx,
                 Event::Error,
                 &format!(
                     "**[Guild: {}] Error setting ephemeral `scoreboard` command:**
 {e}",
-                    g.id
+                    guild_id
                 ),
             )
             .await;
-        } else {
-            config.save();
-        };
+            return;
+        }
+        if scoreboards.ephemeral_command_id != previous_id {
+            config.save().await;
+        }
         crate::drop_data_handle!(data);
     }
 }
+
+impl Scoreboards {
+    pub async fn guild_init(ctx: Context, g: Guild) {
+        Self.config_changed(&ctx, &g.id).await;
+        let scheduled = crate::with_guild!(ctx, &g.id, |guild| guild
+            .scoreboards()
+            .scoreboards()
+            .into_iter()
+            .filter(|(_, scoreboard)| scoreboard.reset_schedule().is_some())
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<String>>())
+        .unwrap_or_default();
+        for name in scheduled {
+            Self::spawn_reset_task(ctx.clone(), g.id, name);
+        }
+    }
+
+    /// Spawn a task resetting the scoreboard `name` in `guild` on its schedule, unless one is
+    /// already running.
+    fn spawn_reset_task(ctx: Context, guild: GuildId, name: String) {
+        if RESET_TASKS.lock().unwrap().insert((guild, name.clone())) {
+            tokio::spawn(Self::reset_on_schedule(ctx, guild, name));
+        }
+    }
+
+    /// When the scoreboard `name` in `guild` is next due to be reset, if it has a schedule.
+    async fn next_reset(ctx: &Context, guild: GuildId, name: &String) -> Option<DateTime<Utc>> {
+        crate::with_guild!(ctx, &guild, |g| g
+            .scoreboards()
+            .scoreboard(name)
+            .and_then(Scoreboard::next_reset))
+        .flatten()
+    }
+
+    /// Reset the scoreboard `name` in `guild` whenever it's due, until it no longer has a
+    /// schedule.
+    async fn reset_on_schedule(ctx: Context, guild: GuildId, name: String) {
+        let key = (guild, name.clone());
+        loop {
+            let Some(next_reset) = Self::next_reset(&ctx, guild, &name).await else {
+                RESET_TASKS.lock().unwrap().remove(&key);
+                // The schedule may have been set again just before this task stopped being
+                // tracked, in which case no other task will have been started for it.
+                if Self::next_reset(&ctx, guild, &name).await.is_some()
+                    && RESET_TASKS.lock().unwrap().insert(key.clone())
+                {
+                    continue;
+                }
+                trace!("[Guild: {guild}] Stopped resetting scoreboard `{name}`");
+                return;
+            };
+            let now = Utc::now();
+            if next_reset > now {
+                // Wake up periodically, in case the schedule has changed in the meantime.
+                let wait = (next_reset - now).to_std().unwrap_or_default();
+                tokio::time::sleep(wait.min(RESET_CHECK_INTERVAL)).await;
+                continue;
+            }
+            let mut data = crate::acquire_data_handle!(write ctx);
+            let config = &mut *data;
+            if let Ok(scoreboard) = config
+                .guild_mut(&guild)
+                .scoreboards_mut()
+                .scoreboard_mut(&name)
+            {
+                if scoreboard.next_reset().is_some_and(|next| next <= now) {
+                    scoreboard.reset(now);
+                    config.save().await;
+                    info!("[Guild: {guild}] Reset scoreboard `{name}` on schedule");
+                }
+            }
+            crate::drop_data_handle!(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use serenity::model::id::UserId;
+
+    use chrono::{DateTime, Utc};
+
+    use super::{ResetSchedule, Scoreboard, ScoreboardData, ScoreboardSnapshot, MAX_SNAPSHOTS};
+
+    fn time(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn reset_schedules() {
+        // 2024-01-03 is a Wednesday.
+        let now = time("2024-01-03T12:00:00Z");
+        let monday = ResetSchedule::new("weekly", 1).unwrap();
+        assert_eq!(monday.next_reset_after(now), time("2024-01-08T00:00:00Z"));
+        let wednesday = ResetSchedule::new("weekly", 3).unwrap();
+        assert_eq!(
+            wednesday.next_reset_after(now),
+            time("2024-01-10T00:00:00Z")
+        );
+        assert_eq!(
+            wednesday.next_reset_after(time("2024-01-02T23:59:59Z")),
+            time("2024-01-03T00:00:00Z")
+        );
+
+        let monthly = ResetSchedule::new("monthly", 3).unwrap();
+        assert_eq!(monthly.next_reset_after(now), time("2024-02-03T00:00:00Z"));
+        assert_eq!(
+            monthly.next_reset_after(time("2024-12-02T00:00:00Z")),
+            time("2024-12-03T00:00:00Z")
+        );
+        assert_eq!(
+            monthly.next_reset_after(time("2024-12-03T00:00:00Z")),
+            time("2025-01-03T00:00:00Z")
+        );
+
+        assert_eq!(wednesday.to_string(), "every week on Wednesday");
+        assert_eq!(monthly.to_string(), "every month on day 3");
+
+        assert!(ResetSchedule::new("weekly", 8).is_err());
+        assert!(ResetSchedule::new("monthly", 29).is_err());
+        assert!(ResetSchedule::new("daily", 1).is_err());
+    }
+
+    #[test]
+    fn scheduled_reset() {
+        let mut scoreboard = Scoreboard::new();
+        scoreboard.set_user(&UserId::new(1), 10);
+        scoreboard.set_user(&UserId::new(2), 20);
+        assert_eq!(scoreboard.next_reset(), None);
+
+        let weekly = ResetSchedule::new("weekly", 1).unwrap();
+        scoreboard.set_reset_schedule(Some(weekly), time("2024-01-03T12:00:00Z"));
+        let first_reset = time("2024-01-08T00:00:00Z");
+        assert_eq!(scoreboard.next_reset(), Some(first_reset));
+
+        scoreboard.reset(first_reset);
+        assert!(scoreboard.scores().is_empty());
+        assert_eq!(
+            scoreboard.snapshots,
+            vec![ScoreboardSnapshot {
+                taken_at: first_reset,
+                scores: vec![(1, UserId::new(2), 20), (2, UserId::new(1), 10)],
+            }]
+        );
+        assert_eq!(scoreboard.next_reset(), Some(time("2024-01-15T00:00:00Z")));
+
+        // Snapshots and schedules survive being saved.
+        let toml = toml::to_string(&scoreboard).unwrap();
+        let scoreboard: Scoreboard = toml::from_str(&toml).unwrap();
+        assert_eq!(scoreboard.snapshots.len(), 1);
+        assert_eq!(scoreboard.reset_schedule(), Some(weekly));
+
+        let mut scoreboard = scoreboard;
+        scoreboard.set_reset_schedule(None, first_reset);
+        assert_eq!(scoreboard.next_reset(), None);
+
+        // Only the latest snapshots are kept.
+        for week in 1..=MAX_SNAPSHOTS as i64 {
+            scoreboard.reset(first_reset + chrono::Duration::weeks(week));
+        }
+        assert_eq!(scoreboard.snapshots.len(), MAX_SNAPSHOTS);
+        assert_eq!(
+            scoreboard.snapshots[0].taken_at,
+            first_reset + chrono::Duration::weeks(1)
+        );
+    }
+
+    #[test]
+    fn invalid_user_ids_are_skipped() {
+        let mut scoreboard = Scoreboard::new();
+        scoreboard.set_user(&UserId::new(1), 10);
+        scoreboard.set_user(&UserId::new(2), 20);
+        scoreboard.scores.insert("not a user".to_string(), 30);
+        scoreboard.scores.insert("0".to_string(), 40);
+        assert_eq!(
+            scoreboard.scores(),
+            vec![(1, UserId::new(2), 20), (2, UserId::new(1), 10)]
+        );
+        assert_eq!(
+            scoreboard.score(&UserId::new(1)),
+            Some((2, UserId::new(1), 10))
+        );
+    }
+
+    #[test]
+    fn csv_export() {
+        let mut scoreboard = Scoreboard::new();
+        for id in 1..=150 {
+            scoreboard.set_user(&UserId::new(id), id as i64);
+        }
+        let usernames = HashMap::from([
+            (UserId::new(150), "alice".to_string()),
+            (UserId::new(149), "bob, \"the builder\"".to_string()),
+        ]);
+        let csv = String::from_utf8(scoreboard.to_csv(&usernames)).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 1 + 100);
+        assert_eq!(
+            rows[..4],
+            [
+                "rank,user_id,username,score",
+                "1,150,alice,150",
+                "2,149,\"bob, \"\"the builder\"\"\",149",
+                "3,148,unknown_user,148",
+            ]
+        );
+        assert_eq!(rows[100], "100,51,unknown_user,51");
+    }
+
+    #[test]
+    fn score_deltas_are_clamped() {
+        let name = "points".to_string();
+        let (user, other) = (UserId::new(1), UserId::new(2));
+        let mut data = ScoreboardData::default();
+        data.add_scoreboard(&name).unwrap();
+        assert_eq!(data.update_score_delta(&name, &user, 5).unwrap(), 5);
+        assert_eq!(data.update_score_delta(&name, &user, -7).unwrap(), -2);
+
+        data.update_scoreboard(&name, &user, i64::MAX - 1).unwrap();
+        assert_eq!(data.update_score_delta(&name, &user, 10).unwrap(), i64::MAX);
+        data.update_scoreboard(&name, &other, i64::MIN + 1).unwrap();
+        assert_eq!(
+            data.update_score_delta(&name, &other, -10).unwrap(),
+            i64::MIN
+        );
+        assert_eq!(
+            data.update_score_delta(&name, &other, i64::MIN.saturating_neg())
+                .unwrap(),
+            -1
+        );
+
+        assert!(data
+            .update_score_delta(&"missing".to_string(), &user, 1)
+            .is_err());
+    }
+}
@@ -2,8 +2,6 @@ use std::time::Duration;
 
 use serenity::all::{ActionRowComponent, CreateActionRow, CreateModal, Mentionable as _};
 
-use crate::config::Config;
-
 use crate::command::{self, Command, PermissionType};
 use crate::{create_raw_embed, ActionResponse};
 
@@ -18,11 +16,12 @@ impl Subsystem for StatusMeaning {
                 "set_status_meaning",
                 "Manager-only: sets the meaning of the manager's Discord status.",
                 PermissionType::Universal,
+                None,
                 Some(Box::new(move |ctx, command, _params| {
                     Box::pin(async move {
                         let data = crate::acquire_data_handle!(read ctx);
-                        let config = data.get::<Config>().unwrap();
-                        let manager = config.get_manager().to_user(&ctx).await?;
+                        let config = &*data;
+                        let manager = config.manager_user(&ctx).await?;
                         if command.user != manager {
                             let resp =
                                 format!("**Unauthorised:** You're not {}!", manager.mention());
@@ -63,7 +62,7 @@ impl Subsystem for StatusMeaning {
                             .await
                         {
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
 
                             let inputs: Vec<_> = int
                                 .data
@@ -77,9 +76,9 @@ impl Subsystem for StatusMeaning {
                                     if it.custom_id == "new_status_meaning" {
                                         if let Some(it) = &it.value {
                                             if !it.is_empty() {
-                                                config.set_status_meaning(Some(it.clone()));
+                                                config.set_status_meaning(Some(it.clone())).await;
                                             } else {
-                                                config.set_status_meaning(None);
+                                                config.set_status_meaning(None).await;
                                             }
                                         }
                                     }
@@ -102,11 +101,12 @@ impl Subsystem for StatusMeaning {
                 "status_meaning",
                 "Retrieves the meaning of the bot managers's current Discord status.",
                 command::PermissionType::Universal,
+                None,
                 Some(Box::new(move |ctx, _command, _params| {
                     Box::pin(async move {
                         let data = crate::acquire_data_handle!(read ctx);
-                        let config = data.get::<Config>().unwrap();
-                        let manager = config.get_manager().to_user(&ctx).await?.mention();
+                        let config = &*data;
+                        let manager = config.manager_user(&ctx).await?.mention();
                         let resp = match config.get_status_meaning() {
                             Some(meaning) => format!(
                                 "**Status meaning:**
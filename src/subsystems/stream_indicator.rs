@@ -1,28 +1,349 @@
-use log::error;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use log::{error, info};
 use serenity::{
     all::EditMember,
     async_trait,
-    model::prelude::{ActivityType, GuildId, Presence},
-    prelude::Context,
+    model::{
+        prelude::{ActivityType, ChannelId, GuildId, Presence, UserId},
+        Permissions,
+    },
+    prelude::{Context, GatewayIntents, Mentionable},
 };
 
-use crate::{command::notify_subscribers, config::Config};
+use crate::{
+    backend::{Backend, SerenityBackend},
+    command::{
+        notify_subscribers_in, require_permissions, ChannelTypes, Command, Option, OptionType,
+        PermissionType,
+    },
+    create_raw_embed, ActionResponse, PaginatedEmbed,
+};
 
 use super::Subsystem;
 
 pub const STREAMING_PREFIX: &str = "🔴 ";
+/// How many excluded users `/stream_indicator list_excluded` shows on each page.
+const EXCLUDED_USERS_PER_PAGE: usize = 20;
+/// Discord's limit on the length of nicknames, in characters.
+const NICKNAME_LIMIT: usize = 32;
+/// How many characters of a nickname are kept when the streaming prefix is added.
+const PREFIXED_NICKNAME_LENGTH: usize = 30;
+
+/// The prefix added to each live member's nickname, so it can still be removed if the guild's
+/// prefix changes whilst they're streaming. Only kept in memory, so after a restart the guild's
+/// current prefix is assumed.
+static APPLIED_PREFIXES: LazyLock<Mutex<HashMap<(GuildId, UserId), String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The prefix which was added to `user`'s nickname in `guild`, or `configured` if none is known.
+fn applied_prefix(guild: GuildId, user: UserId, configured: &str) -> String {
+    APPLIED_PREFIXES
+        .lock()
+        .unwrap()
+        .get(&(guild, user))
+        .cloned()
+        .unwrap_or_else(|| configured.to_string())
+}
+
+/// Check that `prefix` can be added to a (truncated) nickname without exceeding Discord's
+/// nickname limit, describing the problem if not.
+fn validate_prefix(prefix: &str) -> Result<(), String> {
+    let length = prefix.chars().count();
+    if length == 0 {
+        Err("The prefix can't be empty.".to_string())
+    } else if length + PREFIXED_NICKNAME_LENGTH > NICKNAME_LIMIT {
+        Err(format!(
+            "The prefix is {length} characters long, but can be at most {} (e.g. an emoji and a \
+space), so that it fits before a {PREFIXED_NICKNAME_LENGTH}-character nickname.",
+            NICKNAME_LIMIT - PREFIXED_NICKNAME_LENGTH
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Remove the streaming `prefix` from `nick`, restoring the original nickname exactly.
+///
+/// If `nick` doesn't actually start with `prefix`, as many characters as are in `prefix` are
+/// dropped instead.
+fn strip_streaming_prefix(nick: &str, prefix: &str) -> String {
+    nick.strip_prefix(prefix)
+        .map(str::to_string)
+        .unwrap_or_else(|| nick.chars().skip(prefix.chars().count()).collect())
+}
 
 pub struct StreamIndicator;
 
 #[async_trait]
 impl Subsystem for StreamIndicator {
-    fn generate_commands(&self) -> Vec<crate::command::Command<'static>> {
-        vec![]
+    fn generate_commands(&self) -> Vec<Command<'static>> {
+        vec![Command::new(
+            "stream_indicator",
+            "Controls for the stream indicator.",
+            PermissionType::Universal,
+            None,
+            None,
+        )
+        .add_variant(
+            Command::new(
+                "set_prefix",
+                "Set the prefix added to streaming members' nicknames.",
+                PermissionType::ServerPerms(Permissions::MANAGE_GUILD),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_GUILD)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let prefix = get_param!(params, String, "prefix");
+                        if let Err(e) = validate_prefix(prefix) {
+                            return Ok(Some(ActionResponse::new(
+                                create_raw_embed(format!("**Invalid prefix:** {e}")),
+                                true,
+                            )));
+                        }
+                        let guild_id = command.guild_id.unwrap();
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        config
+                            .guild_mut(&guild_id)
+                            .set_streaming_prefix(Some(prefix.to_string()));
+                        config.save().await;
+                        crate::drop_data_handle!(data);
+                        info!("[Guild: {guild_id}] Set the streaming prefix to \"{prefix}\"");
+                        Ok(Some(ActionResponse::new(
+                            create_raw_embed(format!(
+                                "**Streaming members' nicknames will be prefixed with \"{prefix}\".**"
+                            )),
+                            true,
+                        )))
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "prefix",
+                "The prefix, including any space before the nickname (e.g. \"🟢 \").",
+                OptionType::StringInput(Some(1), None),
+                true,
+            )),
+        )
+        .add_variant(Command::new(
+            "reset_prefix",
+            "Go back to the default prefix for streaming members' nicknames.",
+            PermissionType::ServerPerms(Permissions::MANAGE_GUILD),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_GUILD)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let guild_id = command.guild_id.unwrap();
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    config.guild_mut(&guild_id).set_streaming_prefix(None);
+                    config.save().await;
+                    crate::drop_data_handle!(data);
+                    info!("[Guild: {guild_id}] Reset the streaming prefix");
+                    Ok(Some(ActionResponse::new(
+                        create_raw_embed(format!(
+                            "**Streaming members' nicknames will be prefixed with \"{STREAMING_PREFIX}\".**"
+                        )),
+                        true,
+                    )))
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "exclude_user",
+                "Never add the streaming prefix to a member's nickname.",
+                PermissionType::ServerPerms(Permissions::MANAGE_GUILD),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_GUILD)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let user = *get_param!(params, User, "user");
+                        let guild_id = command.guild_id.unwrap();
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let resp = if config.guild_mut(&guild_id).exclude_streaming_user(user) {
+                            config.save().await;
+                            info!("[Guild: {guild_id}] Excluded {user} from the stream indicator");
+                            format!(
+                                "**{} will no longer get the streaming prefix.**",
+                                user.mention()
+                            )
+                        } else {
+                            format!("{} is already excluded.", user.mention())
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "user",
+                "The member to exclude.",
+                OptionType::User,
+                true,
+            )),
+        )
+        .add_variant(
+            Command::new(
+                "unexclude_user",
+                "Add the streaming prefix to a member's nickname again.",
+                PermissionType::ServerPerms(Permissions::MANAGE_GUILD),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_GUILD)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let user = *get_param!(params, User, "user");
+                        let guild_id = command.guild_id.unwrap();
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let resp = if config.guild_mut(&guild_id).unexclude_streaming_user(user) {
+                            config.save().await;
+                            info!("[Guild: {guild_id}] Unexcluded {user} from the stream indicator");
+                            format!(
+                                "**{} will get the streaming prefix again.**",
+                                user.mention()
+                            )
+                        } else {
+                            format!("{} isn't excluded.", user.mention())
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "user",
+                "The member to stop excluding.",
+                OptionType::User,
+                true,
+            )),
+        )
+        .add_variant(Command::new(
+            "list_excluded",
+            "List the members who never get the streaming prefix.",
+            PermissionType::ServerPerms(Permissions::MANAGE_GUILD),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_GUILD)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let excluded = crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| {
+                        guild.streaming_excluded_users().to_vec()
+                    })
+                    .unwrap_or_default();
+                    let paginated = if excluded.is_empty() {
+                        PaginatedEmbed::new(vec![create_raw_embed(
+                            "Only the server owner is excluded from the streaming prefix.",
+                        )])
+                    } else {
+                        PaginatedEmbed::paginate(
+                            excluded.iter().map(|user| format!("- {}", user.mention())),
+                            EXCLUDED_USERS_PER_PAGE,
+                        )
+                        .title("Excluded from the streaming prefix (as well as the server owner)")
+                    };
+                    paginated.send(ctx, command, true).await?;
+                    Ok(None)
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "set_announcement_channel",
+                "Announce members going live in a channel.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::MANAGE_CHANNELS)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let channel = *get_param!(params, Channel, "channel");
+                        let guild_id = command.guild_id.unwrap();
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        config
+                            .guild_mut(&guild_id)
+                            .set_stream_announcement_channel(Some(channel));
+                        config.save().await;
+                        crate::drop_data_handle!(data);
+                        info!("[Guild: {guild_id}] Announcing streams in {channel}");
+                        Ok(Some(ActionResponse::new(
+                            create_raw_embed(format!(
+                                "**Members going live will be announced in {}.**",
+                                channel.mention()
+                            )),
+                            true,
+                        )))
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "channel",
+                "The channel to announce streams in.",
+                OptionType::Channel(Some(ChannelTypes::TEXT)),
+                true,
+            )),
+        )
+        .add_variant(Command::new(
+            "unset_announcement_channel",
+            "Stop announcing members going live.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let guild_id = command.guild_id.unwrap();
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    config.guild_mut(&guild_id).set_stream_announcement_channel(None);
+                    config.save().await;
+                    crate::drop_data_handle!(data);
+                    info!("[Guild: {guild_id}] Stopped announcing streams");
+                    Ok(Some(ActionResponse::new(
+                        create_raw_embed("**Members going live will no longer be announced.**"),
+                        true,
+                    )))
+                })
+            })),
+        ))]
+    }
+
+    fn required_intents(&self, _config: &crate::Config) -> GatewayIntents {
+        GatewayIntents::GUILD_PRESENCES
     }
 
     async fn presence(&self, ctx: &Context, new_data: &Presence) {
         let data = crate::acquire_data_handle!(read ctx);
-        let config = data.get::<Config>().unwrap();
+        let config = &*data;
         if let Some(activity) = new_data
             .activities
             .iter()
@@ -30,21 +351,54 @@ impl Subsystem for StreamIndicator {
         {
             if let Some(user) = new_data.user.to_user() {
                 let mut notify = true;
+                // The announcement channels of the guilds in which the prefix was just applied.
+                let mut announcement_channels: Vec<ChannelId> = Vec::new();
+                let mut live_guilds: Vec<GuildId> = Vec::new();
                 for guild in config
                     .guilds()
                     .map(|g| GuildId::new(g.parse::<u64>().unwrap()))
                 {
+                    if config
+                        .guild(&guild)
+                        .is_some_and(|g| g.streaming_excluded_users().contains(&user.id))
+                    {
+                        continue;
+                    }
+                    let prefix = config
+                        .guild(&guild)
+                        .map_or(STREAMING_PREFIX, |g| g.streaming_prefix());
                     let nick = user.nick_in(&ctx, guild).await.unwrap_or(user.name.clone());
-                    if !nick.starts_with(STREAMING_PREFIX) {
+                    if !nick.starts_with(&applied_prefix(guild, user.id, prefix)) {
                         let old_nick = nick.clone();
-                        let nick = STREAMING_PREFIX.to_owned()
-                            + &nick.chars().take(30).collect::<String>();
+                        let nick = prefix.to_owned()
+                            + &nick
+                                .chars()
+                                .take(PREFIXED_NICKNAME_LENGTH)
+                                .collect::<String>();
+                        let announcement_channel = config
+                            .guild(&guild)
+                            .and_then(|g| g.stream_announcement_channel());
                         if let Ok(guild) = guild.to_partial_guild(&ctx).await {
-                            if let Err(e) = guild
+                            // The server owner is always excluded, as Discord doesn't let bots
+                            // change their nickname.
+                            if guild.owner_id == user.id {
+                                continue;
+                            }
+                            match guild
                                 .edit_member(&ctx, user.id, EditMember::new().nickname(&nick))
                                 .await
                             {
-                                error!("Nickname update failed: {old_nick} -> {nick}\n{:?}", e);
+                                Ok(_) => {
+                                    APPLIED_PREFIXES
+                                        .lock()
+                                        .unwrap()
+                                        .insert((guild.id, user.id), prefix.to_string());
+                                    announcement_channels.extend(announcement_channel);
+                                    live_guilds.push(guild.id);
+                                }
+                                Err(e) => {
+                                    error!("Nickname update failed: {old_nick} -> {nick}\n{:?}", e)
+                                }
                             }
                         }
                     } else {
@@ -56,8 +410,26 @@ impl Subsystem for StreamIndicator {
                 }
                 crate::drop_data_handle!(data);
                 if notify {
-                    notify_subscribers(
+                    let announcement = format!(
+                        "**{} is now live!**{}",
+                        user.mention(),
+                        activity
+                            .url
+                            .as_ref()
+                            .map(|url| format!("\n[Watch the stream]({url})"))
+                            .unwrap_or_default()
+                    );
+                    for channel in announcement_channels {
+                        if let Err(e) = SerenityBackend::from(ctx)
+                            .send_message(channel, announcement.clone())
+                            .await
+                        {
+                            error!("Unable to announce stream in {channel}: {e}");
+                        }
+                    }
+                    notify_subscribers_in(
                         ctx,
+                        &live_guilds,
                         super::events::Event::Stream,
                         format!(
                             "**{} is now live!**",
@@ -77,12 +449,21 @@ impl Subsystem for StreamIndicator {
                 .guilds()
                 .map(|g| GuildId::new(g.parse::<u64>().unwrap()))
             {
+                // Whichever prefix was added, even if the guild's prefix has since changed.
+                let prefix = applied_prefix(
+                    guild,
+                    user.id,
+                    config
+                        .guild(&guild)
+                        .map_or(STREAMING_PREFIX, |g| g.streaming_prefix()),
+                );
+                APPLIED_PREFIXES.lock().unwrap().remove(&(guild, user.id));
                 let nick = user.nick_in(&ctx, guild).await;
                 if let Some(nick) = nick {
-                    if nick.starts_with(STREAMING_PREFIX) {
+                    if nick.starts_with(&prefix) {
                         // the user isn't streaming any more, but they are still marked as such.
                         let old_nick = nick.clone();
-                        let nick = nick.chars().skip(2).collect::<String>();
+                        let nick = strip_streaming_prefix(&nick, &prefix);
                         if let Ok(guild) = guild.to_partial_guild(&ctx).await {
                             if let Err(e) = guild
                                 .edit_member(&ctx, user.id, EditMember::new().nickname(&nick))
@@ -97,3 +478,71 @@ impl Subsystem for StreamIndicator {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serenity::model::id::{GuildId, UserId};
+
+    use super::{
+        applied_prefix, strip_streaming_prefix, validate_prefix, APPLIED_PREFIXES, STREAMING_PREFIX,
+    };
+    use crate::config::Guild;
+
+    #[test]
+    fn prefixes_must_fit_before_nicknames() {
+        assert!(validate_prefix(STREAMING_PREFIX).is_ok());
+        assert!(validate_prefix("🟢 ").is_ok());
+        assert!(validate_prefix("L|").is_ok());
+        assert!(validate_prefix("").is_err());
+        assert!(validate_prefix("LIVE | ").is_err());
+        // Emoji made of several characters count as each of them.
+        assert!(validate_prefix("👨‍👩‍👧").is_err());
+    }
+
+    #[test]
+    fn applied_prefix_outlives_prefix_changes() {
+        let (guild, user) = (GuildId::new(1), UserId::new(1));
+        // Without a record (e.g. after a restart), the configured prefix is assumed.
+        assert_eq!(applied_prefix(guild, user, "🟢 "), "🟢 ");
+        APPLIED_PREFIXES
+            .lock()
+            .unwrap()
+            .insert((guild, user), STREAMING_PREFIX.to_string());
+        assert_eq!(applied_prefix(guild, user, "🟢 "), STREAMING_PREFIX);
+        assert_eq!(applied_prefix(GuildId::new(2), user, "🟢 "), "🟢 ");
+    }
+
+    #[test]
+    fn excluded_users() {
+        let (moderator, bot) = (UserId::new(1), UserId::new(2));
+        let mut guild = Guild::default();
+        assert!(guild.streaming_excluded_users().is_empty());
+        assert!(!guild.unexclude_streaming_user(moderator));
+
+        assert!(guild.exclude_streaming_user(moderator));
+        assert!(!guild.exclude_streaming_user(moderator));
+        assert!(guild.exclude_streaming_user(bot));
+        assert_eq!(guild.streaming_excluded_users(), [moderator, bot]);
+
+        assert!(guild.unexclude_streaming_user(moderator));
+        assert!(guild.unexclude_streaming_user(bot));
+        assert!(guild.streaming_excluded_users().is_empty());
+    }
+
+    #[test]
+    fn strip_default_prefix() {
+        assert_eq!(
+            strip_streaming_prefix(&format!("{STREAMING_PREFIX}Loki 🐍✨"), STREAMING_PREFIX),
+            "Loki 🐍✨"
+        );
+    }
+
+    #[test]
+    fn strip_multi_codepoint_prefix() {
+        let prefix = "🔴📺 ";
+        assert_eq!(prefix.chars().count(), 3);
+        assert_eq!(strip_streaming_prefix("🔴📺 👨‍👩‍👧 Loki", prefix), "👨‍👩‍👧 Loki");
+        // Without the prefix, fall back to dropping as many characters as the prefix has.
+        assert_eq!(strip_streaming_prefix("abcLoki", prefix), "Loki");
+    }
+}
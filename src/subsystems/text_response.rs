@@ -1,20 +1,449 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, Mutex};
 use std::time::Duration;
 
-use serenity::all::{ActionRowComponent, CreateActionRow, CreateModal};
+use chrono::{DateTime, Utc};
+use log::info;
+use rand::{seq::SliceRandom, Rng};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use serenity::all::{
+    ActionRowComponent, CommandInteraction, CreateActionRow, CreateAttachment, CreateModal,
+};
 use serenity::async_trait;
-use serenity::model::prelude::Message;
+use serenity::model::prelude::{ChannelId, GuildId, Message};
 use serenity::model::Permissions;
-use serenity::prelude::Context;
+use serenity::prelude::{Context, GatewayIntents};
+use tinyvec::array_vec;
 
-use crate::config::Config;
-use crate::{create_raw_embed, ActionResponse, Error};
+use crate::backend::{Backend, SerenityBackend};
+use crate::{
+    autocomplete_matches, create_embed_fields, create_raw_embed, ActionResponse, Error,
+    PaginatedEmbed,
+};
 
 use crate::command::{
-    create_embed, notify_subscribers, Command, Option, OptionType, PermissionType,
+    notify_subscribers_with_handle, require_permissions, ChannelTypes, Command, Option, OptionType,
+    PermissionType,
 };
 
 use super::Subsystem;
 
+/// How many responses `/response list_responses` shows on each page.
+const RESPONSES_PER_PAGE: usize = 10;
+/// The largest file, in bytes, which `/response import` accepts.
+const MAX_IMPORT_SIZE: u32 = 128 * 1024;
+
+/// When each guild's activation phrases last fired. Only kept in memory, so cooldowns reset
+/// whenever Loki restarts.
+static LAST_FIRED: LazyLock<Mutex<HashMap<GuildId, ResponseCooldowns>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// How an activation phrase is matched against messages.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawPattern", into = "RawPattern")]
+pub enum ResponsePattern {
+    /// Matches messages containing the (lowercase) phrase, ignoring case.
+    Substring(String),
+    /// Matches messages (as sent, so case-sensitively unless the regex says otherwise) which the
+    /// regex matches. Compiled once, when set or loaded.
+    Regex(Regex),
+}
+
+/// A [ResponsePattern] as it's saved, with regexes stored as their raw string.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+enum RawPattern {
+    Substring(String),
+    Regex(String),
+}
+
+impl TryFrom<RawPattern> for ResponsePattern {
+    type Error = regex::Error;
+
+    fn try_from(raw: RawPattern) -> Result<Self, Self::Error> {
+        Ok(match raw {
+            RawPattern::Substring(phrase) => Self::Substring(phrase),
+            RawPattern::Regex(regex) => Self::Regex(compile_regex(&regex)?),
+        })
+    }
+}
+
+impl From<ResponsePattern> for RawPattern {
+    fn from(pattern: ResponsePattern) -> Self {
+        match pattern {
+            ResponsePattern::Substring(phrase) => Self::Substring(phrase),
+            ResponsePattern::Regex(regex) => Self::Regex(regex.as_str().to_string()),
+        }
+    }
+}
+
+/// Compile an activation `regex`, rejecting any which match an empty message, as they'd match
+/// every message (including Loki's own embed-only responses).
+fn compile_regex(regex: &str) -> Result<Regex, regex::Error> {
+    let compiled = Regex::new(regex)?;
+    if compiled.is_match("") {
+        return Err(regex::Error::Syntax(
+            "the regex matches empty messages, so would match every message".to_string(),
+        ));
+    }
+    Ok(compiled)
+}
+
+impl ResponsePattern {
+    /// Create a pattern of the given `kind` ("substring" or "regex") from `phrase`, failing if
+    /// it's an invalid regex.
+    pub fn new(kind: &str, phrase: &str) -> Result<Self, regex::Error> {
+        Ok(match kind {
+            "regex" => Self::Regex(compile_regex(phrase)?),
+            _ => Self::Substring(phrase.to_lowercase()),
+        })
+    }
+
+    /// The phrase (or raw regex) which this pattern matches, as it's listed.
+    pub fn phrase(&self) -> &str {
+        match self {
+            Self::Substring(phrase) => phrase,
+            Self::Regex(regex) => regex.as_str(),
+        }
+    }
+
+    /// Whether the message `content` matches this pattern.
+    pub fn matches(&self, content: &str) -> bool {
+        match self {
+            Self::Substring(phrase) => content.to_lowercase().contains(phrase),
+            Self::Regex(regex) => regex.is_match(content),
+        }
+    }
+}
+
+/// A response to messages matching an activation phrase.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ResponseEntry {
+    pattern: ResponsePattern,
+    /// The responses to pick from at random. Before there could be several, there was a single
+    /// `response`, which is read as the only one.
+    #[serde(alias = "response", deserialize_with = "one_or_many")]
+    responses: Vec<String>,
+    /// The minimum time, in seconds, between responses to the phrase (0 for no cooldown).
+    #[serde(default)]
+    cooldown_seconds: u64,
+    /// The chance (from 0 to 1) of responding when the phrase is seen.
+    #[serde(
+        default = "default_fire_probability",
+        deserialize_with = "fire_probability"
+    )]
+    fire_probability: f64,
+    /// The channels which the response may be given in, or [None] for any channel. (Like other
+    /// IDs, these are saved as strings, as TOML integers can't hold every ID.)
+    #[serde(default)]
+    restricted_to_channels: std::option::Option<Vec<ChannelId>>,
+}
+
+fn default_fire_probability() -> f64 {
+    1.0
+}
+
+/// Deserialise a fire probability, rejecting any outside 0 to 1 (including NaN), which
+/// [ResponseEntry::roll] couldn't roll with.
+fn fire_probability<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let probability = f64::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&probability) {
+        return Err(serde::de::Error::custom(format!(
+            "fire probability {probability} isn't between 0 and 1"
+        )));
+    }
+    Ok(probability)
+}
+
+/// Deserialise either a single string or a list of them, as a list.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(response) => vec![response],
+        OneOrMany::Many(responses) => responses,
+    })
+}
+
+impl ResponseEntry {
+    pub fn new(pattern: ResponsePattern, response: String) -> Self {
+        Self {
+            pattern,
+            responses: vec![response],
+            cooldown_seconds: 0,
+            fire_probability: default_fire_probability(),
+            restricted_to_channels: None,
+        }
+    }
+
+    pub fn cooldown_seconds(&self) -> u64 {
+        self.cooldown_seconds
+    }
+
+    pub fn set_cooldown_seconds(&mut self, cooldown_seconds: u64) {
+        self.cooldown_seconds = cooldown_seconds;
+    }
+
+    pub fn fire_probability(&self) -> f64 {
+        self.fire_probability
+    }
+
+    /// Set the chance of responding to the phrase, which must be between 0 and 1.
+    pub fn set_fire_probability(&mut self, fire_probability: f64) -> crate::Result<()> {
+        if !(0.0..=1.0).contains(&fire_probability) {
+            return Err(Error::InvalidParam("probability".to_string()));
+        }
+        self.fire_probability = fire_probability;
+        Ok(())
+    }
+
+    /// Allow the response to be given in `channel`, returning whether it couldn't be before (or
+    /// was unrestricted, in which case it's now restricted to just `channel`).
+    pub fn restrict_to_channel(&mut self, channel: ChannelId) -> bool {
+        let channels = self.restricted_to_channels.get_or_insert_with(Vec::new);
+        if channels.contains(&channel) {
+            return false;
+        }
+        channels.push(channel);
+        true
+    }
+
+    /// Stop the response being given in `channel`, returning whether it was allowed there. Once
+    /// the last channel is removed, the response may be given anywhere again.
+    pub fn unrestrict_channel(&mut self, channel: ChannelId) -> bool {
+        let Some(channels) = &mut self.restricted_to_channels else {
+            return false;
+        };
+        let before = channels.len();
+        channels.retain(|c| *c != channel);
+        let removed = channels.len() != before;
+        if channels.is_empty() {
+            self.restricted_to_channels = None;
+        }
+        removed
+    }
+
+    /// Whether the response may be given in `channel`.
+    pub fn fires_in(&self, channel: ChannelId) -> bool {
+        self.restricted_to_channels
+            .as_ref()
+            .is_none_or(|channels| channels.is_empty() || channels.contains(&channel))
+    }
+
+    /// The entry's line in `/response list`, with its phrase and any channel restrictions.
+    pub fn list_line(&self) -> String {
+        let mut line = match &self.pattern {
+            ResponsePattern::Substring(phrase) => format!("•\t{phrase}"),
+            ResponsePattern::Regex(regex) => format!("•\t`{}` (regex)", regex.as_str()),
+        };
+        if let Some(channels) = self
+            .restricted_to_channels
+            .as_ref()
+            .filter(|c| !c.is_empty())
+        {
+            let channels: Vec<String> = channels.iter().map(|c| format!("<#{c}>")).collect();
+            line += &format!(" (only in {})", channels.join(", "));
+        }
+        line
+    }
+
+    /// Decide, using `rng`, whether to respond this time the phrase is seen.
+    pub fn roll(&self, rng: &mut impl Rng) -> bool {
+        rng.gen_bool(self.fire_probability)
+    }
+
+    pub fn pattern(&self) -> &ResponsePattern {
+        &self.pattern
+    }
+
+    pub fn set_pattern(&mut self, pattern: ResponsePattern) {
+        self.pattern = pattern;
+    }
+
+    pub fn responses(&self) -> &[String] {
+        &self.responses
+    }
+
+    /// Add `response` to those picked from, returning whether it wasn't already one of them.
+    pub fn add_response(&mut self, response: String) -> bool {
+        if self.responses.contains(&response) {
+            return false;
+        }
+        self.responses.push(response);
+        true
+    }
+
+    /// Remove the `number`th (counting from 1) response, returning it if it exists.
+    pub fn remove_response(&mut self, number: usize) -> std::option::Option<String> {
+        (1..=self.responses.len())
+            .contains(&number)
+            .then(|| self.responses.remove(number - 1))
+    }
+
+    /// Pick one of the responses at random, using `rng`.
+    pub fn choose_response(&self, rng: &mut impl Rng) -> std::option::Option<&str> {
+        self.responses.choose(rng).map(String::as_str)
+    }
+}
+
+/// When a guild's activation phrases last fired.
+#[derive(Default)]
+struct ResponseCooldowns {
+    last_fired: HashMap<String, DateTime<Utc>>,
+}
+
+impl ResponseCooldowns {
+    /// Record that `phrase` fired at `now`, unless it's within `cooldown` seconds of when it last
+    /// fired or `rolled` (the outcome of [ResponseEntry::roll]) is false, in which case the
+    /// response should be skipped.
+    ///
+    /// The cooldown is per-phrase, rather than per-user: anyone's message starts it. Responses
+    /// skipped by the roll don't start it.
+    fn try_fire(&mut self, phrase: &str, cooldown: u64, now: DateTime<Utc>, rolled: bool) -> bool {
+        if cooldown > 0 {
+            if let Some(last) = self.last_fired.get(phrase) {
+                if (now - *last).num_seconds() < cooldown as i64 {
+                    return false;
+                }
+            }
+        }
+        if !rolled {
+            return false;
+        }
+        if cooldown > 0 {
+            self.last_fired.insert(phrase.to_string(), now);
+        }
+        true
+    }
+}
+
+/// The outcome of [import_responses].
+#[derive(Debug, Default, PartialEq)]
+pub struct ResponseImport {
+    pub added: usize,
+    /// Phrases which already had a response, so were left as they were.
+    pub skipped: usize,
+    /// Phrases which already had a response, which was replaced.
+    pub overwritten: usize,
+}
+
+/// Serialise `response_map` as JSON, for `/response export`. Phrases are sorted, so that exports
+/// of the same responses are identical.
+pub fn export_responses(response_map: &HashMap<String, ResponseEntry>) -> String {
+    let sorted: BTreeMap<&String, &ResponseEntry> = response_map.iter().collect();
+    serde_json::to_string_pretty(&sorted).unwrap()
+}
+
+/// Merge the responses in `json` (in the format of [export_responses]) into `response_map`,
+/// replacing those for phrases which already have a response if `overwrite` is set.
+///
+/// Each response is keyed by its pattern's phrase, whatever its key in `json`. Nothing is
+/// imported if any of the responses are invalid.
+pub fn import_responses(
+    response_map: &mut HashMap<String, ResponseEntry>,
+    json: &str,
+    overwrite: bool,
+) -> crate::Result<ResponseImport> {
+    let imported: HashMap<String, ResponseEntry> =
+        serde_json::from_str(json).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+    let mut entries = Vec::with_capacity(imported.len());
+    for (key, mut entry) in imported {
+        if let ResponsePattern::Substring(phrase) = &mut entry.pattern {
+            *phrase = phrase.to_lowercase();
+        }
+        if key.is_empty() || entry.pattern.phrase().is_empty() {
+            return Err(Error::InvalidConfig(format!(
+                "The phrase for \"{key}\" is empty."
+            )));
+        }
+        if entry.responses.is_empty() || entry.responses.iter().any(String::is_empty) {
+            return Err(Error::InvalidConfig(format!(
+                "\"{key}\" has an empty response."
+            )));
+        }
+        entries.push(entry);
+    }
+    let mut import = ResponseImport::default();
+    for entry in entries {
+        let key = entry.pattern.phrase().to_string();
+        if !response_map.contains_key(&key) {
+            import.added += 1;
+        } else if overwrite {
+            import.overwritten += 1;
+        } else {
+            import.skipped += 1;
+            continue;
+        }
+        response_map.insert(key, entry);
+    }
+    Ok(import)
+}
+
+/// The key in `response_map` of the activation `phrase`, as it was given to `/response set`.
+fn entry_key(
+    response_map: &HashMap<String, ResponseEntry>,
+    phrase: &str,
+) -> std::option::Option<String> {
+    // Substring phrases are stored lowercase, but regexes as they were given.
+    [phrase.to_string(), phrase.to_lowercase()]
+        .into_iter()
+        .find(|key| response_map.contains_key(key))
+}
+
+/// Apply `f` to the response to the activation phrase given in `command`, saving the config and
+/// responding with the message `f` returns.
+async fn update_entry(
+    ctx: &Context,
+    command: &CommandInteraction,
+    phrase: &str,
+    f: impl FnOnce(&mut ResponseEntry) -> String,
+) -> crate::Result<std::option::Option<ActionResponse>> {
+    let mut data = crate::acquire_data_handle!(write ctx);
+    let config = &mut *data;
+    let response_map = config
+        .guild_mut(&command.guild_id.unwrap())
+        .response_map_mut();
+    let resp = match entry_key(response_map, phrase).and_then(|key| response_map.get_mut(&key)) {
+        Some(entry) => {
+            let resp = f(entry);
+            config.save().await;
+            resp
+        }
+        None => format!("**No response is set for \"{phrase}\".**"),
+    };
+    crate::drop_data_handle!(data);
+    Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+}
+
+/// A free-text `activation_phrase` option, which suggests the guild's activation phrases as the
+/// user types.
+fn activation_phrase_option(description: &'static str) -> Option<'static> {
+    Option::new(
+        "activation_phrase",
+        description,
+        OptionType::StringInput(Some(1), None),
+        true,
+    )
+    .with_autocomplete(Box::new(|ctx, command, current| {
+        Box::pin(async move {
+            let Some(guild_id) = command.guild_id else {
+                return Vec::new();
+            };
+            crate::with_guild!(ctx, &guild_id, |guild| guild
+                .response_map()
+                .as_ref()
+                .map(|response_map| autocomplete_matches(response_map.keys(), current))
+                .unwrap_or_default())
+            .unwrap_or_default()
+        })
+    }))
+}
+
 pub struct TextResponse;
 
 #[async_trait]
@@ -26,17 +455,24 @@ impl Subsystem for TextResponse {
                 "Controls for the text response subsystem.",
                 PermissionType::Universal,
                 None,
+                None,
             ).add_variant(Command::new(
                 "list",
                 "List all text inputs which have an associated response set.",
                 PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
                 Some(Box::new(move |ctx, command, _params| {
                     Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
                         let data = crate::acquire_data_handle!(read ctx);
                         if let Some(guild) = crate::config::get_guild(&data, &command.guild_id.unwrap()) {
                             if let Some(response_map) = guild.response_map() {
                                 let mut resp = format!("**{} activation phrase(s):**", response_map.keys().count());
-                                response_map.keys().for_each(|phrase| resp += format!("\n•\t{phrase}").as_str());
+                                response_map.values().for_each(|entry| resp += format!("\n{}", entry.list_line()).as_str());
                                 crate::drop_data_handle!(data);
                                 Ok(Some(ActionResponse::new(create_raw_embed(&resp), true)))
                             } else {
@@ -51,32 +487,43 @@ Perhaps try adding some?"), true)))
             ))
             .add_variant(Command::new(
                 "set",
-                "Set the response the bot gives to a given text input.",
+                "Add a response the bot may give to a given text input.",
                 PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
                 Some(Box::new(move |ctx, command, params| {
                     Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
                         let activation_phrase = get_param!(params, String, "activation_phrase");
+                        let pattern_type = params
+                            .iter()
+                            .find(|opt| opt.name == "pattern_type")
+                            .and_then(|opt| opt.value.as_str())
+                            .unwrap_or("substring");
+                        let pattern = match ResponsePattern::new(pattern_type, activation_phrase) {
+                            Ok(pattern) => pattern,
+                            Err(e) => {
+                                return Ok(Some(ActionResponse::new(create_raw_embed(format!("**Invalid regex:**\n```\n{e}\n```")), true)));
+                            }
+                        };
+                        // Substring phrases are matched regardless of case, so are stored lowercase;
+                        // regexes are stored as given, since lowercasing would change their meaning.
+                        let key = pattern.phrase().to_string();
 
-                        let mut new_response = serenity::builder::CreateInputText::new(serenity::all::InputTextStyle::Paragraph, format!("Response for \"{}\"", if activation_phrase.len() > 30 {
+                        let new_response = serenity::builder::CreateInputText::new(serenity::all::InputTextStyle::Paragraph, format!("Response for \"{}\"", if activation_phrase.len() > 30 {
                                     activation_phrase.chars().take(27).collect::<String>() + "…"
                                 } else {
                                     activation_phrase.to_string()
-                                }), "new_response_value").placeholder("Enter the response to this phrase here, or submit an empty response to unset.")
-                            .required(false);
-                        let data = crate::acquire_data_handle!(read ctx);
-                        if let Some(guild) = crate::config::get_guild(&data, &command.guild_id.unwrap()) {
-                            if let Some(response_map) = guild.response_map() {
-                                if let Some(old_response) = response_map.get(activation_phrase) {
-                                    new_response = new_response.value(old_response);
-                                }
-                            }
-                        }
-                        crate::drop_data_handle!(data);
+                                }), "new_response_value").placeholder("Enter a response to add to those this phrase may get.")
+                            .required(true);
 
                         let components = vec![CreateActionRow::InputText(new_response)];
 
                         command
-                            .create_response(&ctx, serenity::all::CreateInteractionResponse::Modal(CreateModal::new("set_response_value", "Set text response value").components(components)))
+                            .create_response(&ctx, serenity::all::CreateInteractionResponse::Modal(CreateModal::new("set_response_value", "Add text response").components(components)))
                             .await?;
 
                         let guild_id = command.guild_id.unwrap();
@@ -87,7 +534,7 @@ Perhaps try adding some?"), true)))
                                 .filter(|int| int.data.custom_id == "set_response_value")
                                 .timeout(Duration::new(300, 0)).await {
                             let mut data = crate::acquire_data_handle!(write ctx);
-                            let config = data.get_mut::<Config>().unwrap();
+                            let config = &mut *data;
 
                             let inputs: Vec<_> = int
                                 .data
@@ -101,13 +548,15 @@ Perhaps try adding some?"), true)))
                                     if it.custom_id == "new_response_value" {
                                         let guild = config.guild_mut(&guild_id);
                                         let response_map = guild.response_map_mut();
-                                        if let Some(it) = &it.value {
-                                            if !it.is_empty() {
-                                            response_map.insert(activation_phrase.to_string().to_lowercase(), it.clone());
-                                        } else {
-                                            response_map.remove(&activation_phrase.to_lowercase());
-                                        }
-                                        config.save();
+                                        if let Some(it) = it.value.as_ref().filter(|it| !it.is_empty()) {
+                                            response_map
+                                                .entry(key.clone())
+                                                .and_modify(|entry| {
+                                                    entry.set_pattern(pattern.clone());
+                                                    entry.add_response(it.clone());
+                                                })
+                                                .or_insert_with(|| ResponseEntry::new(pattern.clone(), it.clone()));
+                                            config.save().await;
                                         }
                                     }
                                 }
@@ -122,42 +571,385 @@ Perhaps try adding some?"), true)))
                         Ok(None)
                     })
                 })),
-            ).add_option(Option::new(
-                "activation_phrase",
-                "The phrase which will activate this response when seen.",
-                OptionType::StringInput(Some(1), None),
+            ).add_option(activation_phrase_option("The phrase which will activate this response when seen."))
+            .add_option(Option::new(
+                "pattern_type",
+                "How to match the phrase against messages (by default, as a substring ignoring case).",
+                OptionType::StringSelect(Box::new(array_vec!("substring".to_string(), "regex".to_string()))),
+                false,
+            )))
+            .add_variant(Command::new(
+                "set_cooldown",
+                "Set the minimum time between responses to a phrase.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let phrase = get_param!(params, String, "activation_phrase");
+                        let seconds = *get_param!(params, Integer, "seconds");
+                        let Ok(seconds) = u64::try_from(seconds) else {
+                            return Err(Error::InvalidParam("seconds".to_string()));
+                        };
+                        update_entry(ctx, command, phrase, |entry| {
+                            entry.set_cooldown_seconds(seconds);
+                            if seconds == 0 {
+                                format!("**\"{phrase}\" will be responded to every time it's seen.**")
+                            } else {
+                                format!("**\"{phrase}\" will be responded to at most once every {seconds} seconds.**")
+                            }
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(activation_phrase_option("The phrase whose cooldown to set."))
+            .add_option(Option::new(
+                "seconds",
+                "The minimum time between responses, across all users (0 to always respond).",
+                OptionType::IntegerInput(Some(0), None),
+                true,
+            )))
+            .add_variant(Command::new(
+                "set_probability",
+                "Set the chance of responding to a phrase when it's seen.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let phrase = get_param!(params, String, "activation_phrase");
+                        let probability = *get_param!(params, Number, "probability");
+                        update_entry(ctx, command, phrase, |entry| {
+                            match entry.set_fire_probability(probability) {
+                                Ok(()) => format!("**\"{phrase}\" will be responded to {:.0}% of the time.**", probability * 100.0),
+                                Err(_) => format!("**Invalid probability:** {probability} isn't between 0 and 1."),
+                            }
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(activation_phrase_option("The phrase whose response probability to set."))
+            .add_option(Option::new(
+                "probability",
+                "The chance of responding, from 0 (never) to 1 (always).",
+                OptionType::NumberInput(Some(0.0), Some(1.0)),
+                true,
+            )))
+            .add_variant(Command::new(
+                "restrict_to_channel",
+                "Only respond to a phrase in the given channel (and any others it's restricted to).",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let phrase = get_param!(params, String, "activation_phrase");
+                        let channel = *get_param!(params, Channel, "channel");
+                        update_entry(ctx, command, phrase, |entry| {
+                            if entry.restrict_to_channel(channel) {
+                                format!("**\"{phrase}\" will be responded to in <#{channel}>.**\n{}", entry.list_line())
+                            } else {
+                                format!("\"{phrase}\" is already responded to in <#{channel}>.")
+                            }
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(activation_phrase_option("The phrase to restrict."))
+            .add_option(Option::new(
+                "channel",
+                "A channel to respond to the phrase in.",
+                OptionType::Channel(Some(ChannelTypes::TEXT)),
+                true,
+            )))
+            .add_variant(Command::new(
+                "unrestrict_channel",
+                "Stop responding to a phrase in the given channel.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let phrase = get_param!(params, String, "activation_phrase");
+                        let channel = *get_param!(params, Channel, "channel");
+                        update_entry(ctx, command, phrase, |entry| {
+                            if entry.unrestrict_channel(channel) {
+                                format!("**\"{phrase}\" will no longer be responded to in <#{channel}>.**\n{}", entry.list_line())
+                            } else {
+                                format!("\"{phrase}\" isn't restricted to <#{channel}>.")
+                            }
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(activation_phrase_option("The phrase to unrestrict."))
+            .add_option(Option::new(
+                "channel",
+                "The channel to stop responding to the phrase in.",
+                OptionType::Channel(Some(ChannelTypes::TEXT)),
+                true,
+            )))
+            .add_variant(Command::new(
+                "list_responses",
+                "List the responses which the bot may give to a phrase.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let phrase = get_param!(params, String, "activation_phrase");
+                        let responses = crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| guild
+                            .response_map()
+                            .as_ref()
+                            .and_then(|response_map| entry_key(response_map, phrase).map(|key| response_map[&key].responses().to_vec())))
+                        .flatten();
+                        let Some(responses) = responses else {
+                            return Ok(Some(ActionResponse::new(create_raw_embed(format!("**No response is set for \"{phrase}\".**")), true)));
+                        };
+                        let lines = responses.iter().enumerate().map(|(i, response)| format!("**{}.** {response}", i + 1));
+                        PaginatedEmbed::paginate(lines, RESPONSES_PER_PAGE)
+                            .title(format!("Responses to \"{phrase}\""))
+                            .send(ctx, command, true)
+                            .await?;
+                        Ok(None)
+                    })
+                })),
+            )
+            .add_option(activation_phrase_option("The phrase whose responses to list.")))
+            .add_variant(Command::new(
+                "remove_response",
+                "Remove one of the responses the bot may give to a phrase.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let phrase = get_param!(params, String, "activation_phrase");
+                        let number = *get_param!(params, Integer, "number");
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let response_map = config.guild_mut(&command.guild_id.unwrap()).response_map_mut();
+                        let resp = match entry_key(response_map, phrase) {
+                            None => format!("**No response is set for \"{phrase}\".**"),
+                            Some(key) => match response_map.get_mut(&key).unwrap().remove_response(number as usize) {
+                                None => format!("**\"{phrase}\" has no response #{number}.** See `/response list_responses`."),
+                                Some(removed) => {
+                                    let resp = if response_map[&key].responses().is_empty() {
+                                        response_map.remove(&key);
+                                        format!("**Removed the response:** {removed}\nAs it was the only one, \"{phrase}\" will no longer be responded to.")
+                                    } else {
+                                        format!("**Removed the response:** {removed}")
+                                    };
+                                    config.save().await;
+                                    resp
+                                }
+                            },
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                    })
+                })),
+            )
+            .add_option(activation_phrase_option("The phrase to remove a response from."))
+            .add_option(Option::new(
+                "number",
+                "The number of the response to remove, as shown by `/response list_responses`.",
+                OptionType::IntegerInput(Some(1), None),
                 true,
+            )))
+            .add_variant(Command::new(
+                "export",
+                "Export all of the activation phrases and their responses as a JSON file.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                Some(Box::new(move |ctx, command, _params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let guild_id = command.guild_id.unwrap();
+                        let export = crate::with_guild!(ctx, &guild_id, |guild| guild
+                            .response_map()
+                            .as_ref()
+                            .filter(|response_map| !response_map.is_empty())
+                            .map(export_responses))
+                        .flatten();
+                        let resp = match export {
+                            Some(json) => ActionResponse::new(create_raw_embed("**Exported this server's text responses.**"), true)
+                                .add_attachment(CreateAttachment::bytes(json, format!("loki-responses-{guild_id}.json"))),
+                            None => ActionResponse::new(create_raw_embed("**No activation phrases to export.**"), true),
+                        };
+                        Ok(Some(resp))
+                    })
+                })),
+            ))
+            .add_variant(Command::new(
+                "import",
+                "Add the activation phrases and responses from an exported JSON file.",
+                PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        if let Some(refusal) =
+                            require_permissions(command, Permissions::ADMINISTRATOR)
+                        {
+                            return Ok(Some(refusal));
+                        }
+                        let guild_id = command.guild_id.unwrap();
+                        let attachment = params
+                            .iter()
+                            .find(|opt| opt.name == "file")
+                            .and_then(|opt| opt.value.as_attachment_id())
+                            .and_then(|id| command.data.resolved.attachments.get(&id))
+                            .ok_or_else(|| Error::InvalidParam("file".to_string()))?;
+                        let overwrite = params
+                            .iter()
+                            .find(|opt| opt.name == "overwrite")
+                            .and_then(|opt| opt.value.as_bool())
+                            .unwrap_or(false);
+                        if attachment.size > MAX_IMPORT_SIZE {
+                            return Ok(Some(ActionResponse::new(
+                                create_raw_embed(format!(
+                                    "**Unable to import `{}`:** the file is larger than {} KiB.",
+                                    attachment.filename,
+                                    MAX_IMPORT_SIZE / 1024
+                                )),
+                                true,
+                            )));
+                        }
+                        let bytes = attachment.download().await?;
+                        let Ok(json) = String::from_utf8(bytes) else {
+                            return Ok(Some(ActionResponse::new(
+                                create_raw_embed(format!(
+                                    "**Unable to import `{}`:** the file isn't valid UTF-8 text.",
+                                    attachment.filename
+                                )),
+                                true,
+                            )));
+                        };
+
+                        let mut data = crate::acquire_data_handle!(write ctx);
+                        let config = &mut *data;
+                        let resp = match import_responses(config.guild_mut(&guild_id).response_map_mut(), &json, overwrite) {
+                            Ok(import) => {
+                                config.save().await;
+                                info!("[Guild: {guild_id}] Imported text responses from `{}`: {import:?}", attachment.filename);
+                                create_embed_fields(
+                                    format!("**Imported text responses from `{}`.**", attachment.filename),
+                                    &[
+                                        ("Added", import.added.to_string(), true),
+                                        ("Skipped", import.skipped.to_string(), true),
+                                        ("Overwritten", import.overwritten.to_string(), true),
+                                    ],
+                                )
+                            }
+                            Err(e) => create_raw_embed(format!("**Unable to import `{}`:**\n{e}", attachment.filename)),
+                        };
+                        crate::drop_data_handle!(data);
+                        Ok(Some(ActionResponse::new(resp, true)))
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "file",
+                "A file produced by `/response export`.",
+                OptionType::Attachment,
+                true,
+            ))
+            .add_option(Option::new(
+                "overwrite",
+                "Whether to replace the responses to phrases which already have some (by default, they're kept).",
+                OptionType::Boolean,
+                false,
             ))),
         ]
     }
 
+    fn required_intents(&self, _config: &crate::Config) -> GatewayIntents {
+        GatewayIntents::MESSAGE_CONTENT
+    }
+
     async fn message(&self, ctx: &Context, message: &Message) {
+        // Invariant: this handler only ever holds a read handle, and nothing it calls may acquire a
+        // write handle whilst that's held (which would deadlock). Hence subscribers are notified via
+        // `notify_subscribers_with_handle`, which defers any config changes until the handle is dropped.
+        // Never respond to bots, including ourselves: our responses could match again.
+        if message.author.bot {
+            return;
+        }
         let data = crate::acquire_data_handle!(read ctx);
         if let Some(guild) = message.guild_id {
             if let Some(guild) = crate::config::get_guild(&data, &guild) {
                 if let Some(response_map) = guild.response_map() {
-                    for (activator, response) in response_map {
-                        if message.content.to_lowercase().contains(activator) {
-                            if let Ok(channel) = message.channel(&ctx).await {
-                                if let Some(channel) = channel.guild() {
-                                    if let Err(e) = channel
-                                        .send_message(&ctx, create_embed(response.to_string()))
-                                        .await
-                                    {
-                                        notify_subscribers(
-                                            ctx,
-                                            super::events::Event::Error,
-                                            format!(
-                                                "Error in text response handler:
+                    let now = Utc::now();
+                    for (phrase, entry) in response_map {
+                        if !entry.pattern().matches(&message.content)
+                            || !entry.fires_in(message.channel_id)
+                        {
+                            continue;
+                        }
+                        // Rolled before locking, so nothing can panic whilst the lock is held.
+                        let rolled = entry.roll(&mut rand::thread_rng());
+                        if LAST_FIRED
+                            .lock()
+                            .unwrap()
+                            .entry(message.guild_id.unwrap())
+                            .or_default()
+                            .try_fire(phrase, entry.cooldown_seconds(), now, rolled)
+                        {
+                            let Some(response) = entry
+                                .choose_response(&mut rand::thread_rng())
+                                .map(String::from)
+                            else {
+                                continue;
+                            };
+                            if let Err(e) = SerenityBackend::from(ctx)
+                                .send_message(message.channel_id, response)
+                                .await
+                            {
+                                notify_subscribers_with_handle(
+                                    ctx,
+                                    &data,
+                                    super::events::Event::Error,
+                                    format!(
+                                        "Error in text response handler:
 ```
 {e}
 ```"
-                                            )
-                                            .as_str(),
-                                        )
-                                        .await;
-                                    }
-                                }
+                                    )
+                                    .as_str(),
+                                )
+                                .await;
                             }
                         }
                     }
@@ -166,3 +958,251 @@ Perhaps try adding some?"), true)))
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+    use rand::{rngs::StdRng, SeedableRng};
+    use serenity::model::prelude::ChannelId;
+
+    use std::collections::HashMap;
+
+    use super::{
+        export_responses, import_responses, ResponseCooldowns, ResponseEntry, ResponseImport,
+        ResponsePattern,
+    };
+
+    #[test]
+    fn import_merges_exported_responses() {
+        let entry = |phrase: &str, response: &str| {
+            ResponseEntry::new(
+                ResponsePattern::Substring(phrase.to_string()),
+                response.to_string(),
+            )
+        };
+        let mut source = HashMap::new();
+        source.insert("hello".to_string(), entry("hello", "world"));
+        source.insert("bye".to_string(), entry("bye", "for now"));
+        let json = export_responses(&source);
+
+        let mut target = HashMap::new();
+        target.insert("hello".to_string(), entry("hello", "there"));
+        assert_eq!(
+            import_responses(&mut target, &json, false).unwrap(),
+            ResponseImport {
+                added: 1,
+                skipped: 1,
+                overwritten: 0
+            }
+        );
+        assert_eq!(target["hello"].responses(), ["there"]);
+        assert_eq!(target["bye"].responses(), ["for now"]);
+
+        assert_eq!(
+            import_responses(&mut target, &json, true).unwrap(),
+            ResponseImport {
+                added: 0,
+                skipped: 0,
+                overwritten: 2
+            }
+        );
+        assert_eq!(target["hello"].responses(), ["world"]);
+        assert_eq!(export_responses(&target), json);
+    }
+
+    #[test]
+    fn import_rejects_invalid_responses() {
+        let mut response_map = HashMap::new();
+        for json in [
+            "not json",
+            r#"{"": {"pattern": {"kind": "substring", "value": ""}, "responses": ["hi"]}}"#,
+            r#"{"hi": {"pattern": {"kind": "substring", "value": "hi"}, "responses": []}}"#,
+            r#"{"hi": {"pattern": {"kind": "substring", "value": "hi"}, "responses": ["", "x"]}}"#,
+            r#"{"hi": {"pattern": {"kind": "regex", "value": "("}, "responses": ["hi"]}}"#,
+        ] {
+            assert!(matches!(
+                import_responses(&mut response_map, json, true),
+                Err(crate::Error::InvalidConfig(_))
+            ));
+        }
+        assert!(response_map.is_empty());
+
+        // Hand-written substrings are lowercased, to be matched regardless of case.
+        let json = r#"{"x": {"pattern": {"kind": "substring", "value": "Hi"}, "response": "yo"}}"#;
+        import_responses(&mut response_map, json, false).unwrap();
+        assert!(response_map["hi"].pattern().matches("HI THERE"));
+    }
+
+    #[test]
+    fn cooldowns_are_per_phrase() {
+        let now = Utc::now();
+        let mut cooldowns = ResponseCooldowns::default();
+        assert!(cooldowns.try_fire("hello", 60, now, true));
+        assert!(!cooldowns.try_fire("hello", 60, now + Duration::seconds(59), true));
+        // Other phrases have their own cooldowns...
+        assert!(cooldowns.try_fire("bye", 60, now + Duration::seconds(59), true));
+        // ...and phrases without one always fire.
+        assert!(cooldowns.try_fire("hi", 0, now, true));
+        assert!(cooldowns.try_fire("hi", 0, now, true));
+        // A skipped response doesn't restart the cooldown.
+        assert!(cooldowns.try_fire("hello", 60, now + Duration::seconds(60), true));
+        assert!(!cooldowns.try_fire("hello", 60, now + Duration::seconds(61), true));
+    }
+
+    #[test]
+    fn skipped_rolls_do_not_start_cooldowns() {
+        let now = Utc::now();
+        let mut cooldowns = ResponseCooldowns::default();
+        assert!(!cooldowns.try_fire("hello", 60, now, false));
+        assert!(cooldowns.try_fire("hello", 60, now, true));
+        // A successful roll doesn't matter whilst the phrase is cooling down.
+        assert!(!cooldowns.try_fire("hello", 60, now, true));
+    }
+
+    #[test]
+    fn response_probability() {
+        let mut entry = ResponseEntry::new(
+            ResponsePattern::Substring("hello".to_string()),
+            "world".to_string(),
+        );
+        let mut rng = StdRng::seed_from_u64(1);
+        // By default, the phrase is always responded to.
+        assert!((0..100).all(|_| entry.roll(&mut rng)));
+
+        entry.set_fire_probability(0.0).unwrap();
+        assert!((0..100).all(|_| !entry.roll(&mut rng)));
+
+        entry.set_fire_probability(0.25).unwrap();
+        let fired = (0..1000).filter(|_| entry.roll(&mut rng)).count();
+        assert!((200..300).contains(&fired), "Fired {fired} times.");
+
+        for invalid in [-0.1, 1.1, f64::NAN] {
+            assert!(entry.set_fire_probability(invalid).is_err());
+        }
+        assert_eq!(entry.fire_probability(), 0.25);
+        // Nor are they accepted when loading.
+        assert!(serde_json::from_str::<ResponseEntry>(
+            r#"{"pattern":{"kind":"substring","value":"hi"},"response":"hey","fire_probability":1.5}"#
+        )
+        .is_err());
+        assert!(toml::from_str::<ResponseEntry>(
+            "response = \"hey\"\nfire_probability = nan\n[pattern]\nkind = \"substring\"\nvalue = \"hi\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn channel_restrictions() {
+        let (memes, announcements) = (ChannelId::new(1), ChannelId::new(2));
+        let mut entry = ResponseEntry::new(
+            ResponsePattern::Substring("hello".to_string()),
+            "world".to_string(),
+        );
+        assert!(entry.fires_in(announcements));
+        assert!(!entry.unrestrict_channel(memes));
+
+        assert!(entry.restrict_to_channel(memes));
+        assert!(!entry.restrict_to_channel(memes));
+        assert!(entry.fires_in(memes));
+        assert!(!entry.fires_in(announcements));
+        assert_eq!(entry.list_line(), "•\thello (only in <#1>)");
+
+        // Removing the last channel lifts the restriction.
+        assert!(!entry.unrestrict_channel(announcements));
+        assert!(entry.unrestrict_channel(memes));
+        assert!(entry.fires_in(announcements));
+        assert_eq!(entry.list_line(), "•\thello");
+    }
+
+    #[test]
+    fn response_rotation() {
+        let mut entry = ResponseEntry::new(
+            ResponsePattern::Substring("hello".to_string()),
+            "world".to_string(),
+        );
+        assert!(entry.add_response("there".to_string()));
+        assert!(!entry.add_response("world".to_string()));
+        assert_eq!(entry.responses(), ["world", "there"]);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let chosen: Vec<&str> = (0..100)
+            .filter_map(|_| entry.choose_response(&mut rng))
+            .collect();
+        assert!(chosen.contains(&"world") && chosen.contains(&"there"));
+
+        assert_eq!(entry.remove_response(0), None);
+        assert_eq!(entry.remove_response(3), None);
+        assert_eq!(entry.remove_response(1).as_deref(), Some("world"));
+        assert_eq!(entry.remove_response(1).as_deref(), Some("there"));
+        assert_eq!(entry.choose_response(&mut rng), None);
+    }
+
+    #[test]
+    fn single_responses_are_read_as_lists() {
+        let entry: ResponseEntry = toml::from_str(
+            r#"
+            pattern = { kind = "substring", value = "hello" }
+            response = "world"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(entry.responses(), ["world"]);
+        let entry: ResponseEntry = toml::from_str(
+            r#"
+            pattern = { kind = "substring", value = "hello" }
+            responses = ["world", "there"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(entry.responses(), ["world", "there"]);
+    }
+
+    #[test]
+    fn substring_matching_ignores_case() {
+        let pattern = ResponsePattern::new("substring", "Hello").unwrap();
+        assert_eq!(pattern.phrase(), "hello");
+        assert!(pattern.matches("Well, HELLO there"));
+        assert!(pattern.matches("othello"));
+        assert!(!pattern.matches("hell no"));
+    }
+
+    #[test]
+    fn regex_matching() {
+        let pattern = ResponsePattern::new("regex", r"\bhi\b").unwrap();
+        assert_eq!(pattern.phrase(), r"\bhi\b");
+        assert!(pattern.matches("hi there"));
+        assert!(pattern.matches("oh, hi!"));
+        // Unlike a substring, the regex needn't match within other words...
+        assert!(!pattern.matches("this is high up"));
+        // ...and is case-sensitive unless it says otherwise.
+        assert!(!pattern.matches("Hi there"));
+        assert!(ResponsePattern::new("regex", r"(?i)\bhi\b")
+            .unwrap()
+            .matches("Hi there"));
+        assert!(ResponsePattern::new("regex", "(unclosed").is_err());
+        // Regexes which match empty messages would match every message.
+        assert!(ResponsePattern::new("regex", "a*").is_err());
+        assert!(ResponsePattern::new("regex", "").is_err());
+    }
+
+    #[test]
+    fn regex_round_trips_as_raw_string() {
+        let entry = ResponseEntry::new(
+            ResponsePattern::new("regex", "^ping$").unwrap(),
+            "pong".to_string(),
+        );
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            json,
+            r#"{"pattern":{"kind":"regex","value":"^ping$"},"responses":["pong"],"cooldown_seconds":0,"fire_probability":1.0,"restricted_to_channels":null}"#
+        );
+        let entry: ResponseEntry = serde_json::from_str(&json).unwrap();
+        assert!(entry.pattern().matches("ping"));
+        assert!(!entry.pattern().matches("ping pong"));
+        // Invalid regexes are rejected when loading, rather than when matching.
+        assert!(serde_json::from_str::<ResponseEntry>(
+            r#"{"pattern":{"kind":"regex","value":"("},"response":"pong"}"#
+        )
+        .is_err());
+    }
+}
@@ -1,63 +1,606 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
 
-use log::error;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
 use serenity::{
-    all::{CacheHttp, EditThread},
+    all::CommandInteraction,
     async_trait,
-    model::prelude::{ChannelType, Guild, GuildChannel},
-    prelude::Context,
+    model::{
+        prelude::{ChannelId, ChannelType, Guild, GuildChannel, GuildId},
+        Permissions, Timestamp,
+    },
+    prelude::{Context, Mentionable},
 };
+use tokio::sync::RwLock;
 
 use super::Subsystem;
+use crate::{
+    backend::{Backend, SerenityBackend},
+    command::{ChannelTypes, Command, Option, OptionType, PermissionType},
+    create_raw_embed, ActionResponse, Config,
+};
+
+/// The threads which are waiting to be revived, once they've been archived for long enough, so
+/// that each only has one revival scheduled. Only kept in memory, as they're rescheduled on start.
+static PENDING_REVIVALS: LazyLock<Mutex<HashSet<ChannelId>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
 
 struct ChannelError {
     public: bool,
     channel: String,
 }
 
+/// Guild-wide configuration for the thread reviver.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ThreadReviverGuildConfig {
+    /// The channels whose threads are revived, or [None] (or empty) for every channel.
+    #[serde(default)]
+    channels: std::option::Option<Vec<ChannelId>>,
+    /// How long, in hours, threads must have been archived for before they're revived, or
+    /// [None] to revive them straight away.
+    min_archive_age_hours: std::option::Option<u32>,
+    /// How many times each thread (by ID) has been revived.
+    #[serde(default)]
+    revival_counts: HashMap<String, u32>,
+    /// How many times a thread may be revived before it's left archived, or [None] for no limit.
+    #[serde(default)]
+    max_revivals_per_thread: std::option::Option<u32>,
+}
+
+impl ThreadReviverGuildConfig {
+    /// Check that the thread IDs which key the revival counts are valid.
+    pub(crate) fn validate_ids(&self) -> crate::Result<()> {
+        crate::config::validate_id_keys("thread revival counts", self.revival_counts.keys())
+    }
+
+    pub fn channels(&self) -> &[ChannelId] {
+        self.channels.as_deref().unwrap_or_default()
+    }
+
+    /// Whether threads in `channel` should be revived: either it's listed, or no channels are.
+    pub fn revives_threads_in(&self, channel: ChannelId) -> bool {
+        let channels = self.channels();
+        channels.is_empty() || channels.contains(&channel)
+    }
+
+    /// Add `channel` to those whose threads are revived, returning whether it wasn't listed.
+    pub fn add_channel(&mut self, channel: ChannelId) -> bool {
+        let channels = self.channels.get_or_insert_with(Vec::new);
+        if channels.contains(&channel) {
+            return false;
+        }
+        channels.push(channel);
+        true
+    }
+
+    /// Remove `channel` from those whose threads are revived, returning whether it was listed.
+    /// Once the last channel is removed, threads in every channel are revived again.
+    pub fn remove_channel(&mut self, channel: ChannelId) -> bool {
+        let Some(channels) = &mut self.channels else {
+            return false;
+        };
+        let before = channels.len();
+        channels.retain(|c| *c != channel);
+        let removed = channels.len() != before;
+        if channels.is_empty() {
+            self.channels = None;
+        }
+        removed
+    }
+
+    pub fn min_archive_age_hours(&self) -> std::option::Option<u32> {
+        self.min_archive_age_hours
+    }
+
+    pub fn set_min_archive_age_hours(&mut self, hours: std::option::Option<u32>) {
+        self.min_archive_age_hours = hours;
+    }
+
+    /// How much longer a thread archived at `archived_at` must stay archived, as of `now`, before
+    /// it may be revived; [None] if it may be revived now.
+    ///
+    /// Threads whose archive time isn't known may always be revived.
+    fn revival_delay(
+        &self,
+        archived_at: std::option::Option<Timestamp>,
+        now: DateTime<Utc>,
+    ) -> std::option::Option<Duration> {
+        let min_age = chrono::Duration::hours(self.min_archive_age_hours?.into());
+        let age = now - archived_at?.with_timezone(&Utc);
+        (min_age - age)
+            .to_std()
+            .ok()
+            .filter(|delay| !delay.is_zero())
+    }
+
+    pub fn max_revivals_per_thread(&self) -> std::option::Option<u32> {
+        self.max_revivals_per_thread
+    }
+
+    pub fn set_max_revivals_per_thread(&mut self, max: std::option::Option<u32>) {
+        self.max_revivals_per_thread = max;
+    }
+
+    /// How many times `thread` has been revived.
+    pub fn revival_count(&self, thread: ChannelId) -> u32 {
+        self.revival_counts
+            .get(&thread.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Whether `thread` may be revived again, without exceeding the maximum number of revivals.
+    fn may_revive(&self, thread: ChannelId) -> bool {
+        self.max_revivals_per_thread
+            .is_none_or(|max| self.revival_count(thread) < max)
+    }
+
+    /// Count a revival of `thread`, returning how many times it's now been revived.
+    fn record_revival(&mut self, thread: ChannelId) -> u32 {
+        let count = self.revival_counts.entry(thread.to_string()).or_default();
+        *count = count.saturating_add(1);
+        *count
+    }
+
+    /// Forget how many times `thread` has been revived, returning whether it had been at all.
+    pub fn reset_revival_count(&mut self, thread: ChannelId) -> bool {
+        self.revival_counts.remove(&thread.to_string()).is_some()
+    }
+
+    /// The threads which have been revived, and so have a revival count.
+    fn revived_threads(&self) -> Vec<ChannelId> {
+        self.revival_counts
+            .keys()
+            .filter_map(|id| id.parse().ok())
+            .filter(|id| *id != 0)
+            .map(ChannelId::new)
+            .collect()
+    }
+}
+
+/// Whether `e` is the result of a request to a resource which doesn't exist (HTTP 404).
+fn is_not_found(e: &serenity::Error) -> bool {
+    matches!(
+        e,
+        serenity::Error::Http(serenity::all::HttpError::UnsuccessfulRequest(resp))
+            if resp.status_code == serenity::http::StatusCode::NOT_FOUND
+    )
+}
+
+/// A description of which channels' threads are revived under `reviver_config`.
+fn revived_channels(reviver_config: &ThreadReviverGuildConfig) -> String {
+    let channels = reviver_config.channels();
+    if channels.is_empty() {
+        "Threads in every channel will be revived.".to_string()
+    } else {
+        let channels: Vec<String> = channels.iter().map(|c| c.mention().to_string()).collect();
+        format!("Threads will only be revived in {}.", channels.join(", "))
+    }
+}
+
+/// Apply `f` to the config of the guild `command` was run in, saving it and responding with the
+/// message `f` returns.
+async fn update_guild(
+    ctx: &Context,
+    command: &CommandInteraction,
+    f: impl FnOnce(&mut crate::config::Guild) -> String,
+) -> crate::Result<std::option::Option<ActionResponse>> {
+    let mut data = crate::acquire_data_handle!(write ctx);
+    let config = &mut *data;
+    let resp = f(config.guild_mut(&command.guild_id.unwrap()));
+    config.save().await;
+    crate::drop_data_handle!(data);
+    Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+}
+
+/// A `channel` option, for the channel to add to or remove from the revived channels.
+fn channel_option(description: &'static str) -> Option<'static> {
+    Option::new(
+        "channel",
+        description,
+        OptionType::Channel(Some(ChannelTypes::TEXT)),
+        true,
+    )
+}
+
 pub struct ThreadReviver;
 
 #[async_trait]
 impl Subsystem for ThreadReviver {
-    fn generate_commands(&self) -> Vec<crate::command::Command<'static>> {
-        vec![]
+    fn generate_commands(&self) -> Vec<Command<'static>> {
+        vec![Command::new(
+            "thread_reviver",
+            "Controls for the thread reviver.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            None,
+        )
+        .add_variant(
+            Command::new(
+                "add_channel",
+                "Revive threads in a channel; once any are added, only listed channels are.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let channel = *get_param!(params, Channel, "channel");
+                        update_guild(ctx, command, |guild| {
+                            let reviver_config = guild.thread_reviver_config_mut();
+                            let resp = if reviver_config.add_channel(channel) {
+                                info!(
+                                    "[Guild: {}] Reviving threads in {channel}",
+                                    command.guild_id.unwrap()
+                                );
+                                format!("**Added {}.**", channel.mention())
+                            } else {
+                                format!("{} was already listed.", channel.mention())
+                            };
+                            format!("{resp}\n{}", revived_channels(reviver_config))
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(channel_option("The channel whose threads to revive.")),
+        )
+        .add_variant(
+            Command::new(
+                "remove_channel",
+                "Stop reviving threads in a listed channel.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let channel = *get_param!(params, Channel, "channel");
+                        update_guild(ctx, command, |guild| {
+                            let reviver_config = guild.thread_reviver_config_mut();
+                            let resp = if reviver_config.remove_channel(channel) {
+                                info!(
+                                    "[Guild: {}] No longer reviving threads in {channel}",
+                                    command.guild_id.unwrap()
+                                );
+                                format!("**Removed {}.**", channel.mention())
+                            } else {
+                                format!("{} wasn't listed.", channel.mention())
+                            };
+                            format!("{resp}\n{}", revived_channels(reviver_config))
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(channel_option(
+                "The channel whose threads to stop reviving.",
+            )),
+        )
+        .add_variant(
+            Command::new(
+                "set_min_age",
+                "Only revive threads once they've been archived for a while.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let hours = *get_param!(params, Integer, "hours");
+                        let Ok(hours) = u32::try_from(hours) else {
+                            return Err(crate::Error::InvalidParam("hours".to_string()));
+                        };
+                        update_guild(ctx, command, |guild| {
+                            guild
+                                .thread_reviver_config_mut()
+                                .set_min_archive_age_hours(Some(hours));
+                            info!(
+                                "[Guild: {}] Reviving threads after {hours} hours",
+                                command.guild_id.unwrap()
+                            );
+                            format!("**Threads will be revived once they've been archived for {hours} hour(s).**")
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "hours",
+                "How long threads must have been archived for.",
+                OptionType::IntegerInput(Some(1), Some(u32::MAX.into())),
+                true,
+            )),
+        )
+        .add_variant(Command::new(
+            "clear_min_age",
+            "Revive threads as soon as they're archived.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    update_guild(ctx, command, |guild| {
+                        guild.thread_reviver_config_mut().set_min_archive_age_hours(None);
+                        info!(
+                            "[Guild: {}] Reviving threads straight away",
+                            command.guild_id.unwrap()
+                        );
+                        "**Threads will be revived as soon as they're archived.**".to_string()
+                    })
+                    .await
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "set_max_revivals",
+                "Stop reviving threads once they've been revived a number of times.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let max = *get_param!(params, Integer, "revivals");
+                        let Ok(max) = u32::try_from(max) else {
+                            return Err(crate::Error::InvalidParam("revivals".to_string()));
+                        };
+                        update_guild(ctx, command, |guild| {
+                            guild
+                                .thread_reviver_config_mut()
+                                .set_max_revivals_per_thread(Some(max));
+                            info!(
+                                "[Guild: {}] Reviving threads at most {max} times",
+                                command.guild_id.unwrap()
+                            );
+                            format!("**Threads will be revived at most {max} time(s).**")
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "revivals",
+                "How many times each thread may be revived.",
+                OptionType::IntegerInput(Some(1), Some(u32::MAX.into())),
+                true,
+            )),
+        )
+        .add_variant(Command::new(
+            "clear_max_revivals",
+            "Revive threads however many times they're archived.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, _params| {
+                Box::pin(async move {
+                    update_guild(ctx, command, |guild| {
+                        guild
+                            .thread_reviver_config_mut()
+                            .set_max_revivals_per_thread(None);
+                        info!(
+                            "[Guild: {}] Reviving threads without limit",
+                            command.guild_id.unwrap()
+                        );
+                        "**Threads will be revived however many times they're archived.**"
+                            .to_string()
+                    })
+                    .await
+                })
+            })),
+        ))
+        .add_variant(
+            Command::new(
+                "reset_revival_count",
+                "Forget how many times a thread has been revived, so it may be revived again.",
+                PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+                None,
+                Some(Box::new(move |ctx, command, params| {
+                    Box::pin(async move {
+                        let thread = get_param!(params, String, "thread_id");
+                        let Some(thread) = thread
+                            .trim()
+                            .parse()
+                            .ok()
+                            .filter(|id| *id != 0)
+                            .map(ChannelId::new)
+                        else {
+                            return Err(crate::Error::InvalidParam("thread_id".to_string()));
+                        };
+                        update_guild(ctx, command, |guild| {
+                            if guild.thread_reviver_config_mut().reset_revival_count(thread) {
+                                info!(
+                                    "[Guild: {}] Reset revival count of thread {thread}",
+                                    command.guild_id.unwrap()
+                                );
+                                format!("**Reset the revival count of {}.**", thread.mention())
+                            } else {
+                                format!("{} hasn't been revived.", thread.mention())
+                            }
+                        })
+                        .await
+                    })
+                })),
+            )
+            .add_option(Option::new(
+                "thread_id",
+                "The ID of the thread.",
+                OptionType::StringInput(Some(1), Some(20)),
+                true,
+            )),
+        )]
     }
 
     async fn thread(&self, ctx: &Context, thread: &GuildChannel) {
-        Self::revive_thread(&ctx, thread).await;
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let Some(reviver_config) = reviver_config(&*config.read().await, thread) else {
+            return;
+        };
+        let backend: Arc<dyn Backend> = Arc::new(SerenityBackend::from(ctx));
+        ThreadReviver::revive_thread(backend, config, thread, &reviver_config).await;
     }
 }
 
+/// The thread reviver config of `thread`'s guild, if threads in its channel are revived.
+fn reviver_config(
+    config: &Config,
+    thread: &GuildChannel,
+) -> std::option::Option<ThreadReviverGuildConfig> {
+    let parent = thread.parent_id?;
+    crate::config::get_guild(config, &thread.guild_id).map_or(
+        Some(ThreadReviverGuildConfig::default()),
+        |guild| {
+            let reviver_config = guild.thread_reviver_config();
+            reviver_config
+                .revives_threads_in(parent)
+                .then(|| reviver_config.clone())
+        },
+    )
+}
+
+/// Unarchive `thread` in `guild`, unless it's already been revived as many times as the guild
+/// allows, and count the revival.
+///
+/// The write handle is held throughout, so that concurrent revivals of the same thread can't both
+/// pass the check before either is counted.
+async fn unarchive(
+    backend: &dyn Backend,
+    config: &RwLock<Config>,
+    guild: GuildId,
+    thread: ChannelId,
+) {
+    let mut config = config.write().await;
+    let reviver_config = config.guild_mut(&guild).thread_reviver_config_mut();
+    if !reviver_config.may_revive(thread) {
+        warn!("[Guild: {guild}] Not reviving thread {thread}, as it's been revived the maximum number of times.");
+        return;
+    }
+    if let Err(error) = backend.unarchive_thread(thread).await {
+        error!(
+            "Failed to revive thread (does the bot have permission?): {}",
+            error
+        );
+        return;
+    }
+    reviver_config.record_revival(thread);
+    config.save().await;
+}
+
 impl ThreadReviver {
-    async fn revive_thread(http: impl CacheHttp, thread: &GuildChannel) {
+    /// Revive `thread` if it's archived, waiting until it's been archived for as long as the
+    /// guild's `reviver_config` requires.
+    async fn revive_thread(
+        backend: Arc<dyn Backend>,
+        config: Arc<RwLock<Config>>,
+        thread: &GuildChannel,
+        reviver_config: &ThreadReviverGuildConfig,
+    ) {
         if let Some(metadata) = thread.thread_metadata {
             if metadata.archived {
-                let result = thread
-                    .id
-                    .edit_thread(http, EditThread::new().archived(false))
-                    .await;
-                match result {
-                    Ok(_) => (),
-                    Err(error) => error!(
-                        "Failed to revive thread (does the bot have permission?): {}",
-                        error
-                    ),
+                match reviver_config.revival_delay(metadata.archive_timestamp, Utc::now()) {
+                    None => unarchive(&*backend, &config, thread.guild_id, thread.id).await,
+                    // Each update to the thread would otherwise schedule another revival.
+                    Some(_) if !PENDING_REVIVALS.lock().unwrap().insert(thread.id) => {}
+                    Some(delay) => {
+                        info!(
+                            "Reviving thread {} in {}s, once it's been archived for long enough.",
+                            thread.id,
+                            delay.as_secs()
+                        );
+                        tokio::spawn(Self::revive_later(backend, config, thread.id, delay));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Revive `thread` after `delay`. The thread and its guild's config may change in the
+    /// meantime, so it's only revived if it's still archived in a channel whose threads are
+    /// revived, and has been archived for as long as the guild now requires.
+    async fn revive_later(
+        backend: Arc<dyn Backend>,
+        config: Arc<RwLock<Config>>,
+        thread_id: ChannelId,
+        mut delay: Duration,
+    ) {
+        loop {
+            tokio::time::sleep(delay).await;
+            let thread = match backend.get_channel(thread_id).await {
+                Ok(thread) => thread,
+                Err(e) => {
+                    warn!("Unable to check thread {thread_id} before reviving it: {e}");
+                    break;
                 }
+            };
+            let Some(metadata) = thread.thread_metadata.filter(|m| m.archived) else {
+                break;
+            };
+            let Some(reviver_config) = reviver_config(&*config.read().await, &thread) else {
+                break;
+            };
+            match reviver_config.revival_delay(metadata.archive_timestamp, Utc::now()) {
+                None => {
+                    unarchive(&*backend, &config, thread.guild_id, thread_id).await;
+                    break;
+                }
+                // The minimum age was raised, or the thread was archived again, whilst waiting.
+                Some(remaining) => delay = remaining,
+            }
+        }
+        PENDING_REVIVALS.lock().unwrap().remove(&thread_id);
+    }
+
+    /// Forget the revival counts of threads in `guild` which no longer exist.
+    async fn prune_revival_counts(ctx: &Context, guild: GuildId) {
+        let Some(threads) =
+            crate::with_guild!(ctx, &guild, |g| g.thread_reviver_config().revived_threads())
+        else {
+            return;
+        };
+        let mut deleted = Vec::new();
+        for thread in threads {
+            match ctx.http.get_channel(thread).await {
+                Err(e) if is_not_found(&e) => deleted.push(thread),
+                Err(e) => warn!("[Guild: {guild}] Unable to check thread {thread} exists: {e}"),
+                Ok(_) => {}
             }
         }
+        if deleted.is_empty() {
+            return;
+        }
+        info!(
+            "[Guild: {guild}] Forgetting the revival counts of {} deleted thread(s)",
+            deleted.len()
+        );
+        let mut data = crate::acquire_data_handle!(write ctx);
+        let config = &mut *data;
+        let reviver_config = config.guild_mut(&guild).thread_reviver_config_mut();
+        for thread in deleted {
+            reviver_config.reset_revival_count(thread);
+        }
+        config.save().await;
+        crate::drop_data_handle!(data);
     }
 
     pub async fn guild_init(ctx: Context, g: Guild) {
+        Self::prune_revival_counts(&ctx, g.id).await;
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let backend: Arc<dyn Backend> = Arc::new(SerenityBackend::from(&ctx));
+        let reviver_config =
+            crate::with_guild!(ctx, &g.id, |guild| guild.thread_reviver_config().clone())
+                .unwrap_or_default();
         let mut channel_errors: HashMap<String, Vec<ChannelError>> = HashMap::new();
         for (channel_id, channel) in g.channels {
-            if channel.kind == ChannelType::Text {
+            if channel.kind == ChannelType::Text && reviver_config.revives_threads_in(channel_id) {
                 match channel_id
                     .get_archived_private_threads(&ctx, None, None)
                     .await
                 {
                     Ok(threads_data) => {
                         for thread in threads_data.threads {
-                            Self::revive_thread(&ctx, &thread).await;
+                            Self::revive_thread(
+                                backend.clone(),
+                                config.clone(),
+                                &thread,
+                                &reviver_config,
+                            )
+                            .await;
                         }
                     }
                     Err(error) => {
@@ -74,7 +617,13 @@ impl ThreadReviver {
                 {
                     Ok(threads_data) => {
                         for thread in threads_data.threads {
-                            Self::revive_thread(&ctx, &thread).await;
+                            Self::revive_thread(
+                                backend.clone(),
+                                config.clone(),
+                                &thread,
+                                &reviver_config,
+                            )
+                            .await;
                         }
                     }
                     Err(error) => {
@@ -106,6 +655,74 @@ impl ThreadReviver {
                 }
             }
             error!("{}", err);
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_background_error("thread-reviver", &g.id.to_string());
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use serenity::model::id::ChannelId;
+
+    use super::ThreadReviverGuildConfig;
+
+    #[test]
+    fn min_archive_age() {
+        let now = Utc::now();
+        let hours_ago = |hours: i64| Some((now - chrono::Duration::hours(hours)).into());
+        let mut config = ThreadReviverGuildConfig::default();
+        // Without a minimum age, threads are revived straight away.
+        assert_eq!(config.revival_delay(hours_ago(0), now), None);
+
+        config.set_min_archive_age_hours(Some(24));
+        assert_eq!(
+            config.revival_delay(hours_ago(1), now),
+            Some(Duration::from_secs(23 * 60 * 60))
+        );
+        assert_eq!(config.revival_delay(hours_ago(24), now), None);
+        assert_eq!(config.revival_delay(hours_ago(48), now), None);
+        // If it's not known when the thread was archived, it's revived straight away.
+        assert_eq!(config.revival_delay(None, now), None);
+    }
+
+    #[test]
+    fn revival_limit() {
+        let (thread, other) = (ChannelId::new(1), ChannelId::new(2));
+        let mut config = ThreadReviverGuildConfig::default();
+        for _ in 0..5 {
+            assert!(config.may_revive(thread));
+            config.record_revival(thread);
+        }
+        assert_eq!(config.revival_count(thread), 5);
+
+        config.set_max_revivals_per_thread(Some(5));
+        assert!(!config.may_revive(thread));
+        assert!(config.may_revive(other));
+
+        assert!(config.reset_revival_count(thread));
+        assert!(!config.reset_revival_count(thread));
+        assert!(config.may_revive(thread));
+        assert!(config.revived_threads().is_empty());
+    }
+
+    #[test]
+    fn channel_allowlist() {
+        let (general, archive) = (ChannelId::new(1), ChannelId::new(2));
+        let mut config = ThreadReviverGuildConfig::default();
+        assert!(config.revives_threads_in(general) && config.revives_threads_in(archive));
+
+        assert!(config.add_channel(general));
+        assert!(!config.add_channel(general));
+        assert!(config.revives_threads_in(general));
+        assert!(!config.revives_threads_in(archive));
+
+        // Removing the last channel revives threads everywhere again.
+        assert!(!config.remove_channel(archive));
+        assert!(config.remove_channel(general));
+        assert!(config.revives_threads_in(archive));
+    }
+}
@@ -1,28 +1,79 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use serenity::{
-    all::Mentionable as _,
+    all::{CommandInteraction, CreateEmbed, CreateMessage, Mentionable as _},
     async_trait, futures,
     model::{
-        application::CommandDataOptionValue,
-        id::UserId,
-        prelude::{Channel, ChannelId, ChannelType, Member},
-        Permissions, Timestamp,
+        guild::audit_log::{Action, AuditLogEntry, Change, MemberAction},
+        id::{GuildId, RoleId, UserId},
+        prelude::{Channel, ChannelId, Member},
+        Colour, Permissions, Timestamp,
     },
-    prelude::Context,
+    prelude::{Context, GatewayIntents},
 };
 use tinyvec::array_vec;
 
+#[cfg(feature = "events")]
+use crate::{command::notify_subscribers_in_with_handle, subsystems::events::Event};
 use crate::{
-    command::{Command, OptionType, PermissionType},
-    config::{get_guild, Config},
-    create_embed, create_raw_embed, ActionResponse,
+    command::{
+        confirm_action, require_permissions, ChannelTypes, Command, OptionType, PermissionType,
+    },
+    config::{get_guild, Guild},
+    create_embed, create_embed_fields, create_raw_embed, ActionResponse, PaginatedEmbed,
 };
 
 use super::Subsystem;
 
-const ANNOUNCEMENT_TEXT: &str = "[User] has been timed out [x] times now!";
+const ANNOUNCEMENT_TEXT: &str = "{user} has been timed out {count} times now!";
+/// The longest a custom announcement template may be.
+const MAX_TEMPLATE_LENGTH: u16 = 1000;
+const MILESTONE_TEXT: &str = "{user} has reached **{count}** timeouts!";
+/// The colour of milestone announcements, to set them apart from the usual announcements.
+const MILESTONE_COLOUR: Colour = Colour::GOLD;
+
+/// Expand each `{name}` placeholder in `template` with its respective value.
+///
+/// Expansion happens in a single pass, so placeholders appearing within the
+/// substituted values are left untouched. Unknown placeholders are preserved
+/// as-is.
+fn expand_placeholders(template: &str, placeholders: &[(&str, &str)]) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    'outer: while let Some(i) = rest.find('{') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        for (name, value) in placeholders {
+            if let Some(after) = rest
+                .strip_prefix('{')
+                .and_then(|r| r.strip_prefix(name))
+                .and_then(|r| r.strip_prefix('}'))
+            {
+                expanded.push_str(value);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        expanded.push('{');
+        rest = &rest[1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Check that an announcement `template` is usable: it must mention the `{user}`, or the
+/// announcement wouldn't say who was timed out.
+fn validate_template(template: &str) -> crate::Result<()> {
+    if !template.contains("{user}") {
+        return Err(crate::Error::InvalidParam(
+            "the template must include `{user}`".to_string(),
+        ));
+    }
+    Ok(())
+}
 
 /// Configuration for the announcements in a specific guild.
 #[derive(Serialize, Deserialize)]
@@ -31,6 +82,14 @@ pub struct AnnouncementsConfig {
     channel: ChannelId,
     /// Prefix to prepend before the number of times a user was timed out, during an announcement.
     prefix: String,
+    /// Timeout counts which warrant an additional, celebratory announcement. These are sorted and
+    /// deduplicated whenever they're changed, and so as they're saved.
+    #[serde(default)]
+    milestones: Vec<u64>,
+    /// A custom announcement, with `{user}`, `{count}`, `{duration}` and `{prefix}` placeholders,
+    /// used instead of the prefix followed by [ANNOUNCEMENT_TEXT].
+    #[serde(default)]
+    message_template: Option<String>,
 }
 
 impl AnnouncementsConfig {
@@ -39,6 +98,8 @@ impl AnnouncementsConfig {
         Self {
             channel: channel.id(),
             prefix: String::default(),
+            milestones: Vec::new(),
+            message_template: None,
         }
     }
 
@@ -62,17 +123,89 @@ impl AnnouncementsConfig {
         self.prefix = prefix.into();
     }
 
-    pub fn announcement_text(&self) -> String {
-        format!(
-            "{}{}{}",
-            self.prefix(),
-            if self.prefix() != "" { " " } else { "" },
-            ANNOUNCEMENT_TEXT
+    /// The custom announcement template, if any.
+    pub fn message_template(&self) -> Option<&String> {
+        self.message_template.as_ref()
+    }
+
+    /// Set a custom announcement template, or go back to the default with [None].
+    ///
+    /// Returns [crate::Error::InvalidParam] if the template doesn't mention the `{user}`, as the
+    /// announcement wouldn't say who was timed out.
+    pub fn set_message_template(&mut self, template: Option<String>) -> crate::Result<()> {
+        if let Some(template) = &template {
+            validate_template(template)?;
+        }
+        self.message_template = template;
+        Ok(())
+    }
+
+    /// The announcement to make when `user` has been timed out `count` times, most recently for
+    /// `duration` seconds.
+    pub fn announcement_text(&self, user: &str, count: i64, duration: i64) -> String {
+        self.expand_announcement(user, &count.to_string(), &format_duration(duration))
+    }
+
+    /// The timeout counts which warrant a milestone announcement, in ascending order.
+    pub fn milestones(&self) -> &[u64] {
+        &self.milestones
+    }
+
+    /// Add a milestone at `count` timeouts, returning whether it wasn't already one.
+    pub fn add_milestone(&mut self, count: u64) -> bool {
+        let added = !self.milestones.contains(&count);
+        self.milestones.push(count);
+        self.milestones.sort_unstable();
+        self.milestones.dedup();
+        added
+    }
+
+    /// Remove the milestone at `count` timeouts, returning whether there was one.
+    pub fn remove_milestone(&mut self, count: u64) -> bool {
+        let before = self.milestones.len();
+        self.milestones.retain(|milestone| *milestone != count);
+        self.milestones.sort_unstable();
+        self.milestones.len() != before
+    }
+
+    /// Whether being timed out `count` times is a milestone.
+    pub fn is_milestone(&self, count: i64) -> bool {
+        u64::try_from(count).is_ok_and(|count| self.milestones.contains(&count))
+    }
+
+    /// The milestone announcement to make when `user` has been timed out `count` times.
+    pub fn milestone_text(&self, user: &str, count: i64) -> String {
+        expand_placeholders(
+            MILESTONE_TEXT,
+            &[("user", user), ("count", &count.to_string())],
         )
     }
+
+    /// The announcement text, with placeholders shown in place of the user, count and duration.
+    pub fn announcement_preview(&self) -> String {
+        self.expand_announcement("[User]", "[x]", "[duration]")
+    }
+
+    fn expand_announcement(&self, user: &str, count: &str, duration: &str) -> String {
+        let placeholders = [
+            ("user", user),
+            ("count", count),
+            ("duration", duration),
+            ("prefix", self.prefix()),
+        ];
+        match &self.message_template {
+            Some(template) => expand_placeholders(template, &placeholders),
+            None => format!(
+                "{}{}{}",
+                self.prefix(),
+                if self.prefix() != "" { " " } else { "" },
+                expand_placeholders(ANNOUNCEMENT_TEXT, &placeholders)
+            ),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct UserTimeoutData {
     /// Total number of timeouts that have been noticed.
     count: i64,
@@ -82,10 +215,336 @@ pub struct UserTimeoutData {
     last_timed_out: Option<DateTime<Utc>>,
     /// The timestamp that the current timeout is expected to end.
     expected_expiry: Option<Timestamp>,
+    /// The reason given in the audit log for the last timeout, if any.
+    #[serde(default)]
+    last_reason: Option<String>,
+    /// The number of seconds the user was timed out for the last time, if known.
+    #[serde(default)]
+    last_duration: Option<i64>,
+}
+
+impl UserTimeoutData {
+    /// Give the user a clean slate, as if they'd never been timed out, returning their statistics
+    /// from before.
+    fn reset(&mut self) -> Self {
+        std::mem::take(self)
+    }
+
+    /// A summary of the user's timeout statistics.
+    fn summary(&self) -> String {
+        format!(
+            "**{}** time(s), for a total of **{} second(s)**",
+            self.count, self.total_time
+        )
+    }
+
+    /// Refund the unserved remainder of the current timeout from the total time, as it was lifted
+    /// early at `now`. The refund is never negative, so an expiry which has already passed (e.g.
+    /// if it was missed whilst offline) leaves the total untouched.
+    ///
+    /// Returns whether anything was refunded.
+    fn refund_interrupted_timeout(&mut self, now: DateTime<Utc>) -> bool {
+        if let Some(expected_expiry) = self.expected_expiry.take() {
+            let refund = (expected_expiry.with_timezone(&Utc) - now)
+                .num_seconds()
+                .max(0);
+            self.total_time = self.total_time.saturating_sub(refund);
+            self.last_duration = self
+                .last_duration
+                .map(|duration| duration.saturating_sub(refund));
+            refund > 0
+        } else {
+            false
+        }
+    }
+}
+
+/// Guild-wide configuration for the timeout monitor.
+#[derive(Serialize, Deserialize, Default)]
+pub struct TimeoutMonitorGuildConfig {
+    /// Roles whose members' timeouts aren't tracked.
+    ignored_roles: Vec<RoleId>,
+}
+
+impl TimeoutMonitorGuildConfig {
+    /// The roles whose members' timeouts aren't tracked.
+    pub fn ignored_roles(&self) -> &[RoleId] {
+        &self.ignored_roles
+    }
+
+    /// Stop tracking timeouts of the role's members, returning whether they were tracked before.
+    pub fn ignore_role(&mut self, role: RoleId) -> bool {
+        if self.ignored_roles.contains(&role) {
+            return false;
+        }
+        self.ignored_roles.push(role);
+        true
+    }
+
+    /// Track timeouts of the role's members again, returning whether they were ignored before.
+    pub fn unignore_role(&mut self, role: RoleId) -> bool {
+        let before = self.ignored_roles.len();
+        self.ignored_roles.retain(|ignored| *ignored != role);
+        self.ignored_roles.len() != before
+    }
+
+    /// Whether a member with the given `roles` is ignored.
+    pub fn is_ignored(&self, roles: &[RoleId]) -> bool {
+        roles.iter().any(|role| self.ignored_roles.contains(role))
+    }
+}
+
+/// Record that `user` (with the given `roles`) has been timed out until `until` in `guild`, as
+/// noticed at `now`, unless it's a timeout which has already been recorded or the user has an
+/// ignored role.
+///
+/// Returns the user's new timeout count and the timeout's duration (in seconds), if recorded.
+fn record_new_timeout(
+    guild: &mut Guild,
+    user: UserId,
+    roles: &[RoleId],
+    until: Timestamp,
+    now: DateTime<Utc>,
+) -> Option<(i64, i64)> {
+    // User is currently timed out! We should check if this is new...
+    let is_new_timeout = guild
+        .timeouts()
+        .as_ref()
+        .and_then(|timeouts| timeouts.get(&user.to_string()))
+        .and_then(|utd| utd.expected_expiry)
+        .is_none_or(|expected_expiry| until > expected_expiry);
+    if !is_new_timeout {
+        return None;
+    }
+    if guild.timeout_monitor_config().is_ignored(roles) {
+        info!("Ignoring timeout of {user}, who has an ignored role.");
+        return None;
+    }
+    // User is newly timed-out.
+    let utd = guild.timeouts_mut().entry(user.to_string()).or_default();
+    utd.last_timed_out = Some(now);
+    utd.expected_expiry = Some(until);
+    utd.count += 1;
+    let duration = (until.with_timezone(&Utc) - now).num_seconds();
+    utd.total_time += duration;
+    utd.last_duration = Some(duration);
+    Some((utd.count, duration))
 }
 
 pub struct TimeoutMonitor;
 
+/// The number of timeouts listed by `/timeouts recent`, unless asked otherwise.
+const DEFAULT_RECENT_TIMEOUTS: i64 = 5;
+/// The most timeouts `/timeouts recent` may list.
+const MAX_RECENT_TIMEOUTS: i64 = 20;
+/// The number of timeouts shown on each page of `/timeouts recent`.
+const RECENT_TIMEOUTS_PER_PAGE: usize = 10;
+
+/// Format a number of `seconds` as hours, minutes and seconds.
+fn format_duration(seconds: i64) -> String {
+    format!(
+        "{}h {}m {}s",
+        seconds / 60 / 60,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// The `limit` users (by stringified [UserId]) among `timeouts` who were most recently timed
+/// out, most recent first. Those whose last timeout predates Loki recording its time come last,
+/// and those whose statistics have been reset aren't included.
+fn recent_timeouts(
+    timeouts: &HashMap<String, UserTimeoutData>,
+    limit: usize,
+) -> Vec<(&String, &UserTimeoutData)> {
+    let mut entries: Vec<_> = timeouts.iter().filter(|(_, utd)| utd.count > 0).collect();
+    entries.sort_by_key(|(_, utd)| std::cmp::Reverse(utd.last_timed_out));
+    entries.truncate(limit);
+    entries
+}
+
+/// A line of `/timeouts recent`, describing the last timeout of the user `uid`.
+fn recent_timeout_line(uid: &str, utd: &UserTimeoutData) -> String {
+    match utd.last_timed_out {
+        Some(time) => format!(
+            "<@{uid}> at <t:{}:F>, for {}",
+            time.timestamp(),
+            utd.last_duration
+                .map_or_else(|| "an unknown time".to_string(), format_duration)
+        ),
+        None => format!("<@{uid}>: legacy data"),
+    }
+}
+
+/// The most recent audit log entries searched for the reason behind a timeout.
+const AUDIT_LOG_LIMIT: u8 = 10;
+
+/// The reason given for timing out `user`, from the most recent of the `entries` (from a guild's
+/// audit log, most recent first) which timed them out, if any.
+fn timeout_reason(entries: &[AuditLogEntry], user: UserId) -> Option<String> {
+    entries
+        .iter()
+        .find(|entry| {
+            entry
+                .target_id
+                .is_some_and(|target| target.get() == user.get())
+                && entry.changes.as_ref().is_some_and(|changes| {
+                    changes.iter().any(|change| {
+                        matches!(
+                            change,
+                            Change::CommunicationDisabledUntil { new: Some(_), .. }
+                        )
+                    })
+                })
+        })
+        .and_then(|entry| entry.reason.clone())
+}
+
+/// Record the reason for `user`'s new timeout in `guild_id` (from the audit log), then notify
+/// subscribers and make any configured announcements.
+// The expiry is only used to notify subscribers.
+#[cfg_attr(not(feature = "events"), allow(unused_variables))]
+async fn announce_timeout(
+    ctx: Context,
+    guild_id: GuildId,
+    user: UserId,
+    communication_disabled_until: Timestamp,
+    count: i64,
+    duration: i64,
+) {
+    let reason = match guild_id
+        .audit_logs(
+            &ctx.http,
+            Some(Action::Member(MemberAction::Update)),
+            None,
+            None,
+            Some(AUDIT_LOG_LIMIT),
+        )
+        .await
+    {
+        Ok(logs) => timeout_reason(&logs.entries, user),
+        Err(e) => {
+            info!("[Guild: {guild_id}] Unable to fetch the audit log for {user}'s timeout reason (is VIEW_AUDIT_LOG granted?): {e}");
+            None
+        }
+    };
+    let mut data = crate::acquire_data_handle!(write ctx);
+    let config = &mut *data;
+    if let Some(utd) = config
+        .guild_mut(&guild_id)
+        .timeouts_mut()
+        .get_mut(&user.to_string())
+    {
+        utd.last_reason.clone_from(&reason);
+        config.save().await;
+    }
+    crate::drop_data_handle!(data);
+    let data = crate::acquire_data_handle!(read ctx);
+    #[cfg(feature = "events")]
+    notify_subscribers_in_with_handle(
+        &ctx,
+        &data,
+        &[guild_id],
+        Event::Timeout,
+        &format!(
+            "**Timeout**
+{} was timed out in {} until <t:{}:F>; that's {count} time(s) now.",
+            user.mention(),
+            guild_id.name(&ctx).unwrap_or_else(|| guild_id.to_string()),
+            communication_disabled_until.unix_timestamp(),
+        ),
+    )
+    .await;
+    let guild = get_guild(&data, &guild_id).unwrap();
+    if let Some(announcements_config) = guild.timeouts_announcement_config() {
+        if let Some(channel) = announcements_config
+            .channel
+            .to_channel(&ctx)
+            .await
+            .unwrap()
+            .guild()
+        {
+            channel
+                .send_message(
+                    &ctx,
+                    create_embed(
+                        announcements_config.announcement_text(
+                            &user.mention().to_string(),
+                            count,
+                            duration,
+                        ) + &reason
+                            .as_ref()
+                            .map(|reason| format!("\nReason: {reason}"))
+                            .unwrap_or_default(),
+                    ),
+                )
+                .await
+                .unwrap();
+            if announcements_config.is_milestone(count) {
+                info!(
+                    "[Guild: {}] {} reached a milestone of {count} timeouts",
+                    guild_id, user
+                );
+                if let Err(e) = channel
+                    .send_message(
+                        &ctx,
+                        CreateMessage::new().add_embed(
+                            CreateEmbed::new()
+                                .title("Timeout milestone!")
+                                .description(
+                                    announcements_config
+                                        .milestone_text(&user.mention().to_string(), count),
+                                )
+                                .colour(MILESTONE_COLOUR),
+                        ),
+                    )
+                    .await
+                {
+                    error!(
+                        "[Guild: {}] Error announcing {}'s milestone of {count} timeouts:
+{e}",
+                        guild_id, user
+                    );
+                }
+            }
+        } else {
+            error!(
+                "Invalid channel {} in guild {}",
+                announcements_config.channel, &guild_id
+            );
+        }
+    }
+}
+
+/// Apply `update` to the milestones of the guild `command` was run in, returning the response
+/// to give (including the updated milestones), or asking for announcements to be set up first.
+async fn update_milestones(
+    ctx: &Context,
+    command: &CommandInteraction,
+    update: impl FnOnce(&mut AnnouncementsConfig) -> String,
+) -> String {
+    let mut data = crate::acquire_data_handle!(write ctx);
+    let config = &mut *data;
+    let guild = config.guild_mut(&command.guild_id.unwrap());
+    let Some(announcements_config) = guild.timeouts_announcement_config_mut() else {
+        return "You must set an announcements channel first!".to_string();
+    };
+    let resp = update(announcements_config);
+    let milestones = announcements_config
+        .milestones()
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    config.save().await;
+    crate::drop_data_handle!(data);
+    if milestones.is_empty() {
+        format!("{resp}\nThere are no milestones now.")
+    } else {
+        format!("{resp}\nMilestones: {milestones}")
+    }
+}
+
 #[async_trait]
 impl Subsystem for TimeoutMonitor {
     fn generate_commands(&self) -> Vec<crate::command::Command<'static>> {
@@ -94,22 +553,36 @@ impl Subsystem for TimeoutMonitor {
             "Timeout statistics for a given user.",
             PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
             None,
+            None,
         )
         .add_variant(Command::new(
             "check",
             "Check timeout statistics for a given user.",
             PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+            None,
             Some(Box::new(move |ctx, command, params| {
-                Box::pin(async {
+                Box::pin(async move {
                     let user = get_param!(params, User, "user");
-                    let data = crate::acquire_data_handle!(read ctx);
-                    let mut resp = format!("{} hasn't been timed out!", user.mention());
-                    if let Some(guild) = get_guild(&data, &command.guild_id.unwrap()) {
-                        if let Some(timeouts) = guild.timeouts() {
-                            if let Some(utd) = timeouts.get(&user.to_string()) {
-                                resp = format!("{} has been timed out **{}** time(s), for a total of **{} second(s)**.", user.mention(), utd.count, utd.total_time);
+                    let mut resp = crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| {
+                        guild.timeouts().as_ref().and_then(|timeouts| timeouts.get(&user.to_string())).map(|utd| {
+                            let mut resp = format!("{} has been timed out **{}** time(s), for a total of **{} second(s)**.", user.mention(), utd.count, utd.total_time);
+                            if let Some(reason) = &utd.last_reason {
+                                resp += &format!("\nLast timed out for: {reason}");
+                            }
+                            resp
+                        })
+                    })
+                    .flatten()
+                    .unwrap_or_else(|| format!("{} hasn't been timed out!", user.mention()));
+                    match command.guild_id.unwrap().member(ctx, *user).await {
+                        Ok(member) => {
+                            if let Some(until) = member.communication_disabled_until {
+                                if until > Utc::now().into() {
+                                    resp = format!("Currently timed out until <t:{}:F>.\n{resp}", until.unix_timestamp());
+                                }
                             }
                         }
+                        Err(e) => info!("Unable to fetch member {user} to check their current timeout status (have they left the guild?): {e}"),
                     }
                     Ok(Some(ActionResponse::new(create_raw_embed(resp), false)))
                 })
@@ -125,59 +598,61 @@ impl Subsystem for TimeoutMonitor {
             "configure_announcements",
             "Configure announcements when a user is timed out.",
             PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
             Some(Box::new(move |ctx, command, params| {
                 Box::pin(async move {
-                    // Set announcement channel if it's been supplied.
-                    if let Some(channel_opt) = params.iter().find(|opt| opt.name == "channel") {
-                        let mut data = crate::acquire_data_handle!(write ctx);
-                        let config = data.get_mut::<Config>().unwrap();
-                        let guild = config.guild_mut(&command.guild_id.unwrap());
-                        if let CommandDataOptionValue::Channel(channel) = &channel_opt.value {
-                            let channel = channel.to_channel(&ctx).await?;
-                            if let Some(announcement_config) = guild.timeouts_announcement_config_mut() {
-                                announcement_config.set_channel(channel);
-                            } else {
-                                guild.timeouts_announcement_init(channel);
-                            }
-                            config.save();
-                        }
-                    } else {
-                        // No channel set - is there one already...?
-                        // If not, for any reason, we should stop processing immediately.
-                        let data = crate::acquire_data_handle!(read ctx);
-                        let guild = get_guild(&data, &command.guild_id.unwrap());
-                        // We don't know this guild.
-                        if guild.is_none() {
-                            return Ok(Some(ActionResponse::new(create_raw_embed("You must set an announcements channel first!"), true)));
-                        }
-                        let announcements_config = guild.unwrap().timeouts_announcement_config();
-                        // No announcements channel set!
-                        if announcements_config.is_none() {
-                            return Ok(Some(ActionResponse::new(create_raw_embed("You must set an announcements channel first!"), true)));
-                        }
-                        // There is an announcements channel set, so we can continue with that.
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let channel = params
+                        .iter()
+                        .find(|opt| opt.name == "channel")
+                        .and_then(|opt| opt.value.as_channel_id());
+                    let prefix = params
+                        .iter()
+                        .find(|opt| opt.name == "announcement_prefix")
+                        .and_then(|opt| opt.value.as_str());
+                    let template = params
+                        .iter()
+                        .find(|opt| opt.name == "template")
+                        .and_then(|opt| opt.value.as_str());
+                    // Check everything before changing anything, so nothing is half-applied.
+                    if let Some(Err(e)) = template.map(validate_template) {
+                        return Ok(Some(ActionResponse::new(create_raw_embed(format!("**Unable to set the template:** {e}.")), true)));
+                    }
+                    let channel = match channel {
+                        Some(channel) => Some(channel.to_channel(&ctx).await?),
+                        None => None,
                     };
 
-                    // Set announcement prefix if it's been supplied.
-                    if let Some(prefix_opt) = params.iter().find(|opt| opt.name == "announcement_prefix") {
-                        let mut data = crate::acquire_data_handle!(write ctx);
-                        let config = data.get_mut::<Config>().unwrap();
-                        let guild = config.guild_mut(&command.guild_id.unwrap());
-                        let announcement_config = guild.timeouts_announcement_config_mut().unwrap();
-                        if let CommandDataOptionValue::String(prefix) = &prefix_opt.value {
-                            announcement_config.set_prefix(prefix);
-                            config.save();
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    let guild = config.guild_mut(&command.guild_id.unwrap());
+                    match (channel, guild.timeouts_announcement_config_mut()) {
+                        (Some(channel), Some(announcement_config)) => announcement_config.set_channel(channel),
+                        (Some(channel), None) => guild.timeouts_announcement_init(channel),
+                        (None, Some(_)) => (),
+                        (None, None) => {
+                            crate::drop_data_handle!(data);
+                            return Ok(Some(ActionResponse::new(create_raw_embed("You must set an announcements channel first!"), true)));
                         }
-                    };
-
-                    let data = crate::acquire_data_handle!(read ctx);
-                    let guild = get_guild(&data, &command.guild_id.unwrap());
-                    let announcements_config = &guild.unwrap().timeouts_announcement_config().unwrap();
+                    }
+                    let announcement_config = guild.timeouts_announcement_config_mut().unwrap();
+                    if let Some(prefix) = prefix {
+                        announcement_config.set_prefix(prefix);
+                    }
+                    if let Some(template) = template {
+                        announcement_config.set_message_template(Some(template.to_string()))?;
+                    }
                     let resp = format!("**Timeouts announcement config updated!**
 Channel: {}
 Announcement text: {}",
-                        announcements_config.channel().to_channel(&ctx).await?,
-                        announcements_config.announcement_text());
+                        announcement_config.channel().mention(),
+                        announcement_config.announcement_preview());
+                    config.save().await;
+                    crate::drop_data_handle!(data);
                     Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
                 })
             })),
@@ -185,7 +660,7 @@ Announcement text: {}",
         .add_option(crate::command::Option::new(
             "channel",
             "The channel to announce timeouts in.",
-            OptionType::Channel(Some(vec![ChannelType::Text])),
+            OptionType::Channel(Some(ChannelTypes::TEXT)),
             false,
         ))
         .add_option(crate::command::Option::new(
@@ -193,15 +668,69 @@ Announcement text: {}",
             "Text to prepend before the timeout counter message.",
             OptionType::StringInput(None, None),
             false,
+        ))
+        .add_option(crate::command::Option::new(
+            "template",
+            "A custom announcement, using {user}, {count}, {duration} and {prefix}.",
+            OptionType::StringInput(Some(1), Some(MAX_TEMPLATE_LENGTH)),
+            false,
+        )))
+        .add_variant(Command::new(
+            "set_template",
+            "Set a custom announcement, or go back to the default if no template is given.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let template = params
+                        .iter()
+                        .find(|opt| opt.name == "template")
+                        .and_then(|opt| opt.value.as_str())
+                        .map(String::from);
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    let guild = config.guild_mut(&command.guild_id.unwrap());
+                    let Some(announcements_config) = guild.timeouts_announcement_config_mut() else {
+                        return Ok(Some(ActionResponse::new(create_raw_embed("You must set an announcements channel first!"), true)));
+                    };
+                    let resp = match announcements_config.set_message_template(template) {
+                        Ok(()) => {
+                            let preview = announcements_config.announcement_preview();
+                            config.save().await;
+                            format!("**Timeouts announcement template updated!**\nAnnouncement text: {preview}")
+                        }
+                        Err(e) => format!("**Unable to set the template:** {e}."),
+                    };
+                    crate::drop_data_handle!(data);
+                    Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                })
+            })),
+        )
+        .add_option(crate::command::Option::new(
+            "template",
+            "The announcement, using {user}, {count}, {duration} and {prefix}.",
+            OptionType::StringInput(Some(1), Some(MAX_TEMPLATE_LENGTH)),
+            false,
         )))
         .add_variant(Command::new(
             "stop_announcements",
             "Stop all announcements. Unsets all configuration values.",
             PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
             Some(Box::new(move |ctx, command, _params| {
                 Box::pin(async {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
                     let mut data = crate::acquire_data_handle!(write ctx);
-                    let config = data.get_mut::<Config>().unwrap();
+                    let config = &mut *data;
                     let guild = config.guild_mut(&command.guild_id.unwrap());
                     let announcements_config = guild.timeouts_announcement_config_mut();
                     // No announcements channel set!
@@ -210,17 +739,249 @@ Announcement text: {}",
                     }
                     // There is an announcements channel set.
                     guild.timeouts_announcement_uninit();
-                    config.save();
+                    config.save().await;
                     crate::drop_data_handle!(data);
 
                     Ok(Some(ActionResponse::new(create_raw_embed("Announcements have been uninitialised."), true)))
                 })
             })),
         ))
+        .add_variant(Command::new(
+            "add_milestone",
+            "Make an additional announcement when a user reaches a number of timeouts.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let count = *get_param!(params, Integer, "count") as u64;
+                    Ok(Some(ActionResponse::new(create_raw_embed(update_milestones(ctx, command, |config| {
+                        if config.add_milestone(count) {
+                            format!("**Added a milestone at {count} timeouts.**")
+                        } else {
+                            format!("There's already a milestone at {count} timeouts.")
+                        }
+                    }).await), true)))
+                })
+            })),
+        )
+        .add_option(crate::command::Option::new(
+            "count",
+            "The number of timeouts to announce.",
+            OptionType::IntegerInput(Some(1), None),
+            true,
+        )))
+        .add_variant(Command::new(
+            "remove_milestone",
+            "Stop making an additional announcement when a user reaches a number of timeouts.",
+            PermissionType::ServerPerms(Permissions::MANAGE_CHANNELS),
+            None,
+            Some(Box::new(move |ctx, command, params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::MANAGE_CHANNELS)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let count = *get_param!(params, Integer, "count") as u64;
+                    Ok(Some(ActionResponse::new(create_raw_embed(update_milestones(ctx, command, |config| {
+                        if config.remove_milestone(count) {
+                            format!("**Removed the milestone at {count} timeouts.**")
+                        } else {
+                            format!("There's no milestone at {count} timeouts.")
+                        }
+                    }).await), true)))
+                })
+            })),
+        )
+        .add_option(crate::command::Option::new(
+            "count",
+            "The number of timeouts to stop announcing.",
+            OptionType::IntegerInput(Some(1), None),
+            true,
+        )))
+        .add_variant(Command::new(
+            "reset",
+            "Reset a user's timeout statistics, giving them a clean slate.",
+            PermissionType::ServerPerms(Permissions::ADMINISTRATOR),
+            None,
+            Some(Box::new(move |ctx, command, params| {
+                Box::pin(async move {
+                    if let Some(refusal) =
+                        require_permissions(command, Permissions::ADMINISTRATOR)
+                    {
+                        return Ok(Some(refusal));
+                    }
+                    let user = *get_param!(params, User, "user");
+                    let guild_id = command.guild_id.unwrap();
+                    let known = crate::with_guild!(ctx, &guild_id, |guild| {
+                        guild.timeouts().as_ref().is_some_and(|timeouts| timeouts.contains_key(&user.to_string()))
+                    })
+                    .unwrap_or(false);
+                    if !known {
+                        return Ok(Some(ActionResponse::new(
+                            create_raw_embed(format!("{} hasn't been timed out!", user.mention())),
+                            true,
+                        )));
+                    }
+                    if !confirm_action(
+                        ctx,
+                        command,
+                        &format!("**Reset {}'s timeout statistics?**\nThis can't be undone.", user.mention()),
+                        true,
+                    )
+                    .await
+                    {
+                        return Ok(None);
+                    }
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    let before = config
+                        .guild_mut(&guild_id)
+                        .timeouts_mut()
+                        .get_mut(&user.to_string())
+                        .map(UserTimeoutData::reset);
+                    let resp = match before {
+                        Some(before) => {
+                            config.save().await;
+                            info!(
+                                "[Guild: {guild_id}] Reset timeout statistics for {user} (by {}); previously {} timeout(s), {}s",
+                                command.user.id, before.count, before.total_time
+                            );
+                            format!(
+                                "**Reset {}'s timeout statistics.**\nBefore: {}.\nAfter: {}.",
+                                user.mention(),
+                                before.summary(),
+                                UserTimeoutData::default().summary()
+                            )
+                        }
+                        None => format!("{} hasn't been timed out!", user.mention()),
+                    };
+                    crate::drop_data_handle!(data);
+                    Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                })
+            })),
+        )
+        .add_option(crate::command::Option::new(
+            "user",
+            "The user to reset the timeout statistics of.",
+            OptionType::User,
+            true,
+        )))
+        .add_variant(Command::new(
+            "recent",
+            "List the most recent timeouts in this server.",
+            PermissionType::ServerPerms(Permissions::MANAGE_GUILD),
+            None,
+            Some(Box::new(move |ctx, command, params| {
+                Box::pin(async move {
+                    if let Some(refusal) = require_permissions(command, Permissions::MANAGE_GUILD) {
+                        return Ok(Some(refusal));
+                    }
+                    let limit = params
+                        .iter()
+                        .find(|opt| opt.name == "limit")
+                        .and_then(|opt| opt.value.as_i64())
+                        .unwrap_or(DEFAULT_RECENT_TIMEOUTS)
+                        .clamp(1, MAX_RECENT_TIMEOUTS) as usize;
+                    let lines = crate::with_guild!(ctx, &command.guild_id.unwrap(), |guild| {
+                        guild.timeouts().as_ref().map(|timeouts| {
+                            recent_timeouts(timeouts, limit)
+                                .into_iter()
+                                .map(|(uid, utd)| recent_timeout_line(uid, utd))
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .flatten()
+                    .unwrap_or_default();
+                    let paginated = if lines.is_empty() {
+                        PaginatedEmbed::new(vec![create_raw_embed("Nobody has been timed out yet!")])
+                    } else {
+                        PaginatedEmbed::paginate(lines.into_iter(), RECENT_TIMEOUTS_PER_PAGE)
+                            .title("Recent timeouts")
+                    };
+                    paginated.send(ctx, command, true).await?;
+                    Ok(None)
+                })
+            })),
+        )
+        .add_option(crate::command::Option::new(
+            "limit",
+            "How many timeouts to list (default 5).",
+            OptionType::IntegerInput(Some(1), Some(MAX_RECENT_TIMEOUTS)),
+            false,
+        )))
+        .add_variant(Command::new(
+            "ignore_role",
+            "Stop tracking timeouts of a role's members.",
+            PermissionType::ServerPerms(Permissions::MANAGE_GUILD),
+            None,
+            Some(Box::new(move |ctx, command, params| {
+                Box::pin(async move {
+                    if let Some(refusal) = require_permissions(command, Permissions::MANAGE_GUILD) {
+                        return Ok(Some(refusal));
+                    }
+                    let role = *get_param!(params, Role, "role");
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    let resp = if config.guild_mut(&command.guild_id.unwrap()).timeout_monitor_config_mut().ignore_role(role) {
+                        config.save().await;
+                        info!("[Guild: {}] Ignoring timeouts of role {role}", command.guild_id.unwrap());
+                        format!("**Timeouts of {} members will no longer be tracked.**", role.mention())
+                    } else {
+                        format!("Timeouts of {} members are already ignored.", role.mention())
+                    };
+                    crate::drop_data_handle!(data);
+                    Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                })
+            })),
+        )
+        .add_option(crate::command::Option::new(
+            "role",
+            "The role whose members' timeouts to ignore.",
+            OptionType::Role,
+            true,
+        )))
+        .add_variant(Command::new(
+            "unignore_role",
+            "Track timeouts of a role's members again.",
+            PermissionType::ServerPerms(Permissions::MANAGE_GUILD),
+            None,
+            Some(Box::new(move |ctx, command, params| {
+                Box::pin(async move {
+                    if let Some(refusal) = require_permissions(command, Permissions::MANAGE_GUILD) {
+                        return Ok(Some(refusal));
+                    }
+                    let role = *get_param!(params, Role, "role");
+                    let mut data = crate::acquire_data_handle!(write ctx);
+                    let config = &mut *data;
+                    let resp = if config.guild_mut(&command.guild_id.unwrap()).timeout_monitor_config_mut().unignore_role(role) {
+                        config.save().await;
+                        info!("[Guild: {}] Tracking timeouts of role {role} again", command.guild_id.unwrap());
+                        format!("**Timeouts of {} members will be tracked again.**", role.mention())
+                    } else {
+                        format!("Timeouts of {} members aren't ignored.", role.mention())
+                    };
+                    crate::drop_data_handle!(data);
+                    Ok(Some(ActionResponse::new(create_raw_embed(resp), true)))
+                })
+            })),
+        )
+        .add_option(crate::command::Option::new(
+            "role",
+            "The role whose members' timeouts to track again.",
+            OptionType::Role,
+            true,
+        )))
         .add_variant(Command::new(
             "leaderboard",
             "Display the leaderboard for timeout statistics.",
             PermissionType::ServerPerms(Permissions::USE_APPLICATION_COMMANDS),
+            None,
             Some(Box::new(move |ctx, command, params| {
                 Box::pin(async move {
                     let metric = get_param!(params, String, "metric").to_lowercase();
@@ -236,22 +997,17 @@ Announcement text: {}",
                     let data = crate::acquire_data_handle!(read ctx);
                     if let Some(guild) = get_guild(&data, &command.guild_id.unwrap()) {
                         if let Some(timeouts) = guild.timeouts() {
-                            let mut entries = timeouts.iter().map(|(uid, utd)| (uid.clone(), *utd)).collect::<Vec<(String, UserTimeoutData)>>();
+                            let mut entries = timeouts.iter().map(|(uid, utd)| (uid.clone(), utd.clone())).collect::<Vec<(String, UserTimeoutData)>>();
                             entries.sort_unstable_by(sort_by);
                             let iter = entries.iter().take(10);
                             users = futures::future::try_join_all(iter.clone().map(|(uid, _)| async {
                                 Ok::<String, crate::Error>(UserId::from(uid.parse::<u64>().unwrap()).to_user(&ctx).await?.mention().to_string())
                             })).await?.join("\n");
                             counts = iter.clone().map(|(_, utd)| { utd.count.to_string() }).collect::<Vec<String>>().join("\n");
-                            times = iter.map(|(_, utd)| {
-                                let seconds = utd.total_time % 60;
-                                let minutes = (utd.total_time / 60) % 60;
-                                let hours = utd.total_time / 60 / 60;
-                                format!("{hours}h {minutes}m {seconds}s")
-                            }).collect::<Vec<String>>().join("\n");
+                            times = iter.map(|(_, utd)| format_duration(utd.total_time)).collect::<Vec<String>>().join("\n");
                         }
                     }
-                    let resp = create_raw_embed(format!("**Top 10 Timeout leaderboard** (sorted by {metric})")).field("User", users, true).field("Count", counts, true).field("Total time", times, true);
+                    let resp = create_embed_fields(format!("**Top 10 Timeout leaderboard** (sorted by {metric})"), &[("User", users, true), ("Count", counts, true), ("Total time", times, true)]);
                     Ok(Some(ActionResponse::new(resp, false)))
                 })
             })),
@@ -264,6 +1020,10 @@ Announcement text: {}",
         )))]
     }
 
+    fn required_intents(&self, _config: &crate::Config) -> GatewayIntents {
+        GatewayIntents::GUILD_MEMBERS
+    }
+
     async fn member(&self, ctx: &Context, old: &Option<Member>, new: &Member) {
         let now = Utc::now();
         info!(
@@ -282,92 +1042,48 @@ Announcement text: {}",
                 }
             }
         }
+        // Discord may report a timeout which has already expired (e.g. a very short timeout
+        // which ended before the event was processed); treat that as not being timed out, so
+        // that it's not counted again.
+        let communication_disabled_until = new
+            .communication_disabled_until
+            .filter(|until| *until > now.into());
         let mut data = crate::acquire_data_handle!(write ctx);
-        let config = data.get_mut::<Config>().unwrap();
+        let config = &mut *data;
         let guild = config.guild_mut(&new.guild_id);
-        if let Some(communication_disabled_until) = new.communication_disabled_until {
-            // User is currently timed out! We should check if this is new...
-            if communication_disabled_until > now.into() {
-                let mut is_new_timeout = false;
-                if let Some(timeouts) = guild.timeouts() {
-                    if let Some(utd) = timeouts.get(&new.user.id.to_string()) {
-                        if let Some(expected_expiry) = utd.expected_expiry {
-                            if communication_disabled_until > expected_expiry {
-                                is_new_timeout = true;
-                            }
-                        } else {
-                            is_new_timeout = true;
-                        }
-                    } else {
-                        is_new_timeout = true;
-                    }
-                } else {
-                    is_new_timeout = true;
-                }
-                if is_new_timeout {
-                    // User is newly timed-out.
-                    let utd = guild
-                        .timeouts_mut()
-                        .entry(new.user.id.to_string())
-                        .or_default();
-                    utd.last_timed_out = Some(now);
-                    utd.expected_expiry = Some(communication_disabled_until);
-                    utd.count += 1;
-                    utd.total_time +=
-                        (communication_disabled_until.with_timezone(&Utc) - now).num_seconds();
-                    let count = utd.count;
-                    config.save();
-                    crate::drop_data_handle!(data);
-                    let data = crate::acquire_data_handle!(read ctx);
-                    let guild = get_guild(&data, &new.guild_id).unwrap();
-                    if let Some(announcements_config) = guild.timeouts_announcement_config() {
-                        if let Some(channel) = announcements_config
-                            .channel
-                            .to_channel(&ctx)
-                            .await
-                            .unwrap()
-                            .guild()
-                        {
-                            channel
-                                .send_message(
-                                    &ctx,
-                                    create_embed(format!(
-                                        "{}{}{} has been timed out {} times now!",
-                                        announcements_config.prefix(),
-                                        if announcements_config.prefix() != "" {
-                                            " "
-                                        } else {
-                                            ""
-                                        },
-                                        new.user.mention(),
-                                        count,
-                                    )),
-                                )
-                                .await
-                                .unwrap();
-                        } else {
-                            error!(
-                                "Invalid channel {} in guild {}",
-                                announcements_config.channel, &new.guild_id
-                            );
-                        }
-                    }
-                }
+        if let Some(communication_disabled_until) = communication_disabled_until {
+            if let Some((count, duration)) = record_new_timeout(
+                guild,
+                new.user.id,
+                &new.roles,
+                communication_disabled_until,
+                now,
+            ) {
+                config.save().await;
+                crate::drop_data_handle!(data);
+                // Looking up the reason takes another request, so do so (and announce the
+                // timeout) without holding up the handling of other events.
+                tokio::spawn(announce_timeout(
+                    ctx.clone(),
+                    new.guild_id,
+                    new.user.id,
+                    communication_disabled_until,
+                    count,
+                    duration,
+                ));
             }
         } else {
             // User is not currently timed out! We should check if they *were*.
             if let Some(timeouts) = guild.timeouts() {
                 if let Some(utd) = timeouts.get(&new.user.id.to_string()) {
-                    if let Some(expected_expiry) = utd.expected_expiry {
-                        if expected_expiry > now.into() {
+                    if utd.expected_expiry.is_some() {
+                        let utd = guild
+                            .timeouts_mut()
+                            .get_mut(&new.user.id.to_string())
+                            .unwrap();
+                        if utd.refund_interrupted_timeout(now) {
                             // Interrupted timeout!
-                            let utd = guild
-                                .timeouts_mut()
-                                .get_mut(&new.user.id.to_string())
-                                .unwrap();
-                            utd.total_time -=
-                                (expected_expiry.with_timezone(&Utc) - now).num_seconds();
-                            config.save();
+                            config.save().await;
                         }
                     }
                 }
@@ -375,3 +1091,276 @@ Announcement text: {}",
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+    use serenity::model::{
+        guild::audit_log::AuditLogEntry,
+        id::{ChannelId, RoleId, UserId},
+    };
+
+    use std::collections::HashMap;
+
+    use super::{
+        expand_placeholders, recent_timeout_line, recent_timeouts, record_new_timeout,
+        timeout_reason, AnnouncementsConfig, UserTimeoutData,
+    };
+    use crate::config::Guild;
+
+    #[test]
+    fn interrupted_timeout_refund() {
+        let now = Utc::now();
+        let mut utd = UserTimeoutData {
+            count: 1,
+            total_time: 600,
+            last_timed_out: Some(now - Duration::seconds(300)),
+            expected_expiry: Some((now + Duration::seconds(300)).into()),
+            last_reason: None,
+            last_duration: None,
+        };
+        assert!(utd.refund_interrupted_timeout(now));
+        assert_eq!(utd.total_time, 300);
+        // The refund only applies once.
+        assert!(!utd.refund_interrupted_timeout(now));
+        assert_eq!(utd.total_time, 300);
+    }
+
+    #[test]
+    fn ignored_roles_are_not_tracked() {
+        let now = Utc::now();
+        let until = (now + Duration::seconds(600)).into();
+        let (ignored, other) = (RoleId::new(1), RoleId::new(2));
+        let mut guild = Guild::default();
+        assert!(guild.timeout_monitor_config_mut().ignore_role(ignored));
+        assert!(!guild.timeout_monitor_config_mut().ignore_role(ignored));
+
+        let user = UserId::new(3);
+        assert_eq!(
+            record_new_timeout(&mut guild, user, &[other, ignored], until, now),
+            None
+        );
+        assert!(guild
+            .timeouts()
+            .as_ref()
+            .is_none_or(|timeouts| !timeouts.contains_key(&user.to_string())));
+
+        assert_eq!(
+            record_new_timeout(&mut guild, user, &[other], until, now),
+            Some((1, 600))
+        );
+        // The same timeout isn't counted twice.
+        assert_eq!(
+            record_new_timeout(&mut guild, user, &[other], until, now),
+            None
+        );
+
+        assert!(guild.timeout_monitor_config_mut().unignore_role(ignored));
+        assert!(!guild.timeout_monitor_config_mut().unignore_role(ignored));
+        let later = (now + Duration::seconds(1200)).into();
+        assert_eq!(
+            record_new_timeout(&mut guild, user, &[ignored], later, now),
+            Some((2, 1200))
+        );
+    }
+
+    #[test]
+    fn reset_clears_statistics() {
+        let now = Utc::now();
+        let mut utd = UserTimeoutData {
+            count: 3,
+            total_time: 600,
+            last_timed_out: Some(now),
+            expected_expiry: Some((now + Duration::seconds(300)).into()),
+            last_reason: Some("spam".to_string()),
+            last_duration: Some(300),
+        };
+        let before = utd.reset();
+        assert_eq!(before.count, 3);
+        assert_eq!(before.total_time, 600);
+        assert_eq!(utd.count, 0);
+        assert_eq!(utd.total_time, 0);
+        assert!(utd.last_timed_out.is_none());
+        assert!(utd.expected_expiry.is_none());
+        assert!(utd.last_reason.is_none());
+    }
+
+    #[test]
+    fn recent_timeouts_are_most_recent_first() {
+        let now = Utc::now();
+        let utd = |ago: Option<i64>, duration: Option<i64>| UserTimeoutData {
+            count: 1,
+            last_timed_out: ago.map(|ago| now - Duration::seconds(ago)),
+            last_duration: duration,
+            ..Default::default()
+        };
+        let timeouts = HashMap::from([
+            ("1".to_string(), utd(Some(300), Some(3_661))),
+            ("2".to_string(), utd(None, None)),
+            ("3".to_string(), utd(Some(60), None)),
+            ("4".to_string(), utd(Some(600), Some(60))),
+            ("5".to_string(), UserTimeoutData::default()),
+        ]);
+        let recent = recent_timeouts(&timeouts, 3);
+        assert_eq!(
+            recent
+                .iter()
+                .map(|(uid, _)| uid.as_str())
+                .collect::<Vec<_>>(),
+            ["3", "1", "4"]
+        );
+        assert!(recent_timeout_line("1", recent[1].1).ends_with(", for 1h 1m 1s"));
+        assert!(recent_timeout_line("3", recent[0].1).ends_with(", for an unknown time"));
+        let all = recent_timeouts(&timeouts, 20);
+        assert_eq!(all.len(), 4);
+        assert_eq!(recent_timeout_line(all[3].0, all[3].1), "<@2>: legacy data");
+    }
+
+    #[test]
+    fn expired_timeout_is_not_refunded() {
+        let now = Utc::now();
+        let mut utd = UserTimeoutData {
+            count: 1,
+            total_time: 600,
+            last_timed_out: Some(now - Duration::seconds(900)),
+            expected_expiry: Some((now - Duration::seconds(300)).into()),
+            last_reason: None,
+            last_duration: None,
+        };
+        assert!(!utd.refund_interrupted_timeout(now));
+        assert_eq!(utd.total_time, 600);
+    }
+
+    #[test]
+    fn timeout_reason_from_audit_log() {
+        let entry = |id: u64, target: u64, key: &str, reason: &str| {
+            serde_json::from_value::<AuditLogEntry>(serde_json::json!({
+                "id": id.to_string(),
+                "action_type": 24,
+                "target_id": target.to_string(),
+                "user_id": "9",
+                "reason": reason,
+                "changes": [{ "key": key, "new_value": "2030-01-01T00:00:00+00:00" }],
+            }))
+            .unwrap()
+        };
+        let entries = [
+            entry(4, 3, "communication_disabled_until", "someone else"),
+            entry(3, 2, "nick", "not a timeout"),
+            entry(2, 2, "communication_disabled_until", "spam"),
+            entry(1, 2, "communication_disabled_until", "an older timeout"),
+        ];
+        assert_eq!(
+            timeout_reason(&entries, UserId::new(2)),
+            Some("spam".to_string())
+        );
+        assert_eq!(timeout_reason(&entries, UserId::new(5)), None);
+    }
+
+    #[test]
+    fn announcement_text_expansion() {
+        let mut config = AnnouncementsConfig {
+            channel: ChannelId::new(1),
+            prefix: String::new(),
+            milestones: Vec::new(),
+            message_template: None,
+        };
+        assert_eq!(
+            config.announcement_text("<@2>", 3, 60),
+            "<@2> has been timed out 3 times now!"
+        );
+        config.set_prefix(":clown:");
+        assert_eq!(
+            config.announcement_text("<@2>", 3, 60),
+            ":clown: <@2> has been timed out 3 times now!"
+        );
+        assert_eq!(
+            config.announcement_preview(),
+            ":clown: [User] has been timed out [x] times now!"
+        );
+    }
+
+    #[test]
+    fn announcement_templates() {
+        let mut config = AnnouncementsConfig {
+            channel: ChannelId::new(1),
+            prefix: ":clown:".to_string(),
+            milestones: Vec::new(),
+            message_template: None,
+        };
+        assert!(config
+            .set_message_template(Some("Someone was timed out.".to_string()))
+            .is_err());
+        assert_eq!(config.message_template(), None);
+        config
+            .set_message_template(Some(
+                "{prefix} {user}: {count} timeouts, the last for {duration}".to_string(),
+            ))
+            .unwrap();
+        assert_eq!(
+            config.announcement_text("<@2>", 3, 3_725),
+            ":clown: <@2>: 3 timeouts, the last for 1h 2m 5s"
+        );
+        assert_eq!(
+            config.announcement_preview(),
+            ":clown: [User]: [x] timeouts, the last for [duration]"
+        );
+        // The prefix is only included where the template asks for it.
+        config
+            .set_message_template(Some("{user} timed out".to_string()))
+            .unwrap();
+        assert_eq!(config.announcement_text("<@2>", 3, 60), "<@2> timed out");
+        config.set_message_template(None).unwrap();
+        assert_eq!(
+            config.announcement_text("<@2>", 3, 60),
+            ":clown: <@2> has been timed out 3 times now!"
+        );
+    }
+
+    #[test]
+    fn milestone_triggers() {
+        let mut config = AnnouncementsConfig {
+            channel: ChannelId::new(1),
+            prefix: String::new(),
+            milestones: Vec::new(),
+            message_template: None,
+        };
+        assert!(!config.is_milestone(5));
+        assert!(config.add_milestone(25));
+        assert!(config.add_milestone(5));
+        assert!(config.add_milestone(10));
+        assert!(!config.add_milestone(10));
+        assert_eq!(config.milestones(), &[5, 10, 25]);
+        assert!(config.is_milestone(5));
+        assert!(!config.is_milestone(6));
+        assert!(!config.is_milestone(-5));
+        assert_eq!(
+            config.milestone_text("<@2>", 10),
+            "<@2> has reached **10** timeouts!"
+        );
+        assert!(config.remove_milestone(5));
+        assert!(!config.remove_milestone(5));
+        assert!(!config.is_milestone(5));
+        assert_eq!(config.milestones(), &[10, 25]);
+    }
+
+    #[test]
+    fn placeholder_expansion_edge_cases() {
+        let placeholders = [("user", "<@2>"), ("count", "3")];
+        assert_eq!(
+            expand_placeholders("{user}, {user} and {user}", &placeholders),
+            "<@2>, <@2> and <@2>"
+        );
+        assert_eq!(expand_placeholders("{count}{count}", &placeholders), "33");
+        assert_eq!(
+            expand_placeholders("{unknown} {user", &placeholders),
+            "{unknown} {user"
+        );
+        assert_eq!(expand_placeholders("{{user}}", &placeholders), "{<@2>}");
+        // Placeholders within substituted values are not expanded.
+        assert_eq!(
+            expand_placeholders("{user}", &[("user", "{count}"), ("count", "3")]),
+            "{count}"
+        );
+    }
+}